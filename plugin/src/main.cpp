@@ -156,6 +156,8 @@ void hk_workspace_anim(CWorkspace* thisptr, bool in, bool left, bool instant) {
         case Animation::Fade: {
             conf->pValues->internalStyle = "fade";
         } break;
+        case Animation::Native: {
+        } break;
         default: {
             instant = true;
         } break;
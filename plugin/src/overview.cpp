@@ -159,12 +159,14 @@ void GridOverview::init() {
         for (int x = 0; x < g_KoolConfig.workspaces_x; x++) {
             auto ow = OverviewWorkspace();
             ow.name = activity + ":(" + std::to_string(x + 1) + " " + std::to_string(y + 1) + ")";
+            // column as seen on screen
+            int vx = g_KoolConfig.invert_horizontal ? g_KoolConfig.workspaces_x - 1 - x : x;
             ow.box = box;
-            ow.box.x += box.w * x;
+            ow.box.x += box.w * vx;
             ow.box.y += box.h * y;
             ow.box.scale(scale);
 
-            ow.box.x += w_gap * (x + 1);
+            ow.box.x += w_gap * (vx + 1);
             ow.box.y += h_gap * (y + 1);
 
             ow.scale = scale;
@@ -6,7 +6,7 @@
 void* renderWindow;
 void* renderLayer;
 
-Animation anim_dir = Animation::None;
+Animation anim_dir = Animation::Native;
 
 inline HANDLE PHANDLE = nullptr;
 std::string sock_path;
@@ -55,6 +55,7 @@ void _set_config() {
     // default values
     g_KoolConfig.workspaces_x = 2;
     g_KoolConfig.workspaces_y = 2;
+    g_KoolConfig.invert_horizontal = false;
 
     const auto HOME = getenv("HOME");
     auto path = std::string(HOME) + "/.config/hypr/hyprkool.toml";
@@ -63,6 +64,7 @@ void _set_config() {
     }
     // NOTE: parsing error is nicely displayed in the notif area
     auto manifest = toml::parse_file(path);
+    g_KoolConfig.invert_horizontal = manifest["invert_horizontal"].value_or(false);
     auto workspaces = manifest["workspaces"].as_array();
     if (!workspaces) {
         return;
@@ -16,6 +16,8 @@ enum Animation {
     Up = 3,
     Down = 4,
     Fade = 5,
+    // leave the animation configured in hyprland.conf untouched
+    Native = 6,
 };
 enum PluginEvent {
     AnimationNone = 0,
@@ -24,6 +26,7 @@ enum PluginEvent {
     AnimationUp = 3,
     AnimationDown = 4,
     AnimationFade = 5,
+    AnimationNative = 6,
 };
 extern Animation anim_dir;
 
@@ -39,6 +42,7 @@ void throw_err_notif(std::string msg);
 struct KoolConfig {
     int workspaces_x;
     int workspaces_y;
+    bool invert_horizontal;
 };
 extern KoolConfig g_KoolConfig;
 
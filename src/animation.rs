@@ -0,0 +1,162 @@
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+
+use crate::{
+    batch,
+    config::{AnimationConfig, SwitchAnimation},
+    state::Animation,
+};
+
+/// workspace animation styles that hyprland accepts. the slidefade ones can take a percentage
+pub const STYLES: [&str; 5] = ["slide", "slidevert", "fade", "slidefade", "slidefadevert"];
+
+/// what kind of switch is animated. picks the section of [animation] that is used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchKind {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    /// activity switches and jumps to any other workspace
+    Activity,
+}
+
+impl SwitchKind {
+    /// kind of the switches that hyprkool animates with anim. None if they are not animated
+    pub fn of(anim: Animation) -> Option<Self> {
+        match anim {
+            Animation::Left | Animation::Right => Some(Self::Horizontal),
+            Animation::Up | Animation::Down => Some(Self::Vertical),
+            Animation::Fade => Some(Self::Activity),
+            Animation::None | Animation::Native => None,
+        }
+    }
+}
+
+impl AnimationConfig {
+    pub fn get(&self, kind: SwitchKind) -> &SwitchAnimation {
+        match kind {
+            SwitchKind::Horizontal => &self.horizontal,
+            SwitchKind::Vertical => &self.vertical,
+            SwitchKind::Diagonal => &self.diagonal,
+            SwitchKind::Activity => &self.activity,
+        }
+    }
+
+    /// (name, settings) of every kind of switch
+    pub fn all(&self) -> [(&'static str, &SwitchAnimation); 4] {
+        [
+            ("horizontal", &self.horizontal),
+            ("vertical", &self.vertical),
+            ("diagonal", &self.diagonal),
+            ("activity", &self.activity),
+        ]
+    }
+}
+
+impl SwitchAnimation {
+    pub fn check(&self) -> Result<()> {
+        if self.curve.is_some() != self.duration.is_some() {
+            return Err(anyhow!("curve and duration have to be set together"));
+        }
+        if self.duration.is_some_and(|d| d <= 0.0) {
+            return Err(anyhow!("duration must be more than 0"));
+        }
+        let Some(style) = self.style.as_deref() else {
+            return Ok(());
+        };
+        let mut parts = style.split_whitespace();
+        let name = parts.next().unwrap_or_default();
+        if name != "none" && !STYLES.contains(&name) {
+            return Err(anyhow!(
+                "unknown style '{}'. use none, {}",
+                style,
+                STYLES.join(", ")
+            ));
+        }
+        if let Some(percent) = parts.next() {
+            let valid = name.starts_with("slidefade")
+                && percent
+                    .strip_suffix('%')
+                    .and_then(|p| p.parse::<f32>().ok())
+                    .is_some_and(|p| (0.0..=100.0).contains(&p));
+            if !valid || parts.next().is_some() {
+                return Err(anyhow!(
+                    "invalid style '{}'. only slidefade and slidefadevert take a percentage (like 'slidefade 20%')",
+                    style
+                ));
+            }
+        }
+        if self.keyword_style().is_some() && self.curve.is_none() {
+            return Err(anyhow!(
+                "style '{}' needs curve and duration. hyprkool can only do none, slide, slidevert and fade without them",
+                style
+            ));
+        }
+        Ok(())
+    }
+
+    /// plugin animation for a switch that would be animated with anim, and the value of
+    /// hyprland's animation keyword if the workspaces animation has to change for it
+    pub fn resolve(&self, anim: Animation) -> (Animation, Option<String>) {
+        let anim = match self.style.as_deref() {
+            None => anim,
+            Some("none") => Animation::None,
+            Some("fade") => Animation::Fade,
+            // switches without a direction slide forward
+            Some("slide") => match anim {
+                Animation::Left | Animation::Up => Animation::Left,
+                _ => Animation::Right,
+            },
+            Some("slidevert") => match anim {
+                Animation::Left | Animation::Up => Animation::Up,
+                _ => Animation::Down,
+            },
+            // the plugin only knows the simple ones
+            Some(_) => Animation::Native,
+        };
+        let keyword = self
+            .curve
+            .as_ref()
+            .zip(self.duration)
+            .map(|(curve, duration)| match self.keyword_style() {
+                Some(style) => format!("workspaces,1,{},{},{}", duration, curve, style),
+                None => format!("workspaces,1,{},{}", duration, curve),
+            });
+        (anim, keyword)
+    }
+
+    /// style that has to be set with the keyword as the plugin can't do it
+    fn keyword_style(&self) -> Option<&str> {
+        self.style
+            .as_deref()
+            .filter(|s| !["none", "fade", "slide", "slidevert"].contains(s))
+    }
+}
+
+/// value of hyprland's animation keyword that gives the workspaces animation its current settings
+pub async fn workspaces_animation() -> Result<String> {
+    let resp = batch::query(&["j/animations"]).await?;
+    let animations = serde_json::from_str::<Value>(&resp)
+        .context("could not parse the animations in the hyprland reply")?;
+    // animations and beziers come in separate lists
+    let workspaces = animations
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|list| list.as_array())
+        .flatten()
+        .find(|a| a["name"] == "workspaces")
+        .context("hyprland has no workspaces animation")?;
+    let enabled = workspaces["enabled"].as_bool().unwrap_or(true);
+    let speed = workspaces["speed"].as_f64().unwrap_or(1.0);
+    let curve = workspaces["bezier"].as_str().unwrap_or("default");
+    let mut value = format!("workspaces,{},{},{}", enabled as u8, speed, curve);
+    match workspaces["style"].as_str() {
+        Some(style) if !style.is_empty() => {
+            value.push(',');
+            value.push_str(style);
+        }
+        _ => (),
+    }
+    Ok(value)
+}
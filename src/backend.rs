@@ -0,0 +1,111 @@
+use std::future::Future;
+
+use anyhow::Result;
+use hyprland::{
+    data::{Client, CursorPosition, Monitor, Monitors, Workspace},
+    shared::{HyprData, HyprDataActive, HyprDataActiveOptional},
+};
+
+use crate::batch::DispatchBatch;
+
+/// the parts of hyprland that the mouse loop talks to. [`Hyprland`] is the real thing. tests
+/// drive the loop without a compositor through a scripted one
+pub trait HyprlandApi {
+    /// name of the focused workspace
+    fn active_workspace(&self) -> impl Future<Output = Result<String>> + Send;
+    fn monitors(&self) -> impl Future<Output = Result<Vec<Monitor>>> + Send;
+    fn cursor_position(&self) -> impl Future<Output = Result<CursorPosition>> + Send;
+    fn active_window(&self) -> impl Future<Output = Result<Option<Client>>> + Send;
+    fn dispatch(&self, batch: DispatchBatch) -> impl Future<Output = Result<()>> + Send;
+}
+
+/// talks to the running hyprland instance
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Hyprland;
+
+impl HyprlandApi for Hyprland {
+    async fn active_workspace(&self) -> Result<String> {
+        Ok(Workspace::get_active_async().await?.name)
+    }
+
+    async fn monitors(&self) -> Result<Vec<Monitor>> {
+        Ok(Monitors::get_async().await?.into_iter().collect())
+    }
+
+    async fn cursor_position(&self) -> Result<CursorPosition> {
+        Ok(CursorPosition::get_async().await?)
+    }
+
+    async fn active_window(&self) -> Result<Option<Client>> {
+        Ok(Client::get_active_async().await?)
+    }
+
+    async fn dispatch(&self, batch: DispatchBatch) -> Result<()> {
+        batch.send().await
+    }
+}
+
+/// a scripted hyprland for tests
+#[cfg(test)]
+pub mod mock {
+    use std::sync::{Arc, Mutex, MutexGuard};
+
+    use super::*;
+
+    /// what [`MockHyprland`] pretends hyprland looks like
+    #[derive(Debug, Clone, Default)]
+    pub struct MockState {
+        pub workspace: String,
+        /// the mouse loop starts on the focused one. the first one if none is focused
+        pub monitors: Vec<Monitor>,
+        pub cursor: (i64, i64),
+        pub active_window: Option<Client>,
+        /// every command that was dispatched, oldest first
+        pub dispatched: Vec<String>,
+    }
+
+    /// hyprland that answers from a [`MockState`] and records dispatches instead of sending
+    /// them. clones share the same state, so it can be changed while something else owns a clone
+    #[derive(Debug, Clone, Default)]
+    pub struct MockHyprland {
+        state: Arc<Mutex<MockState>>,
+    }
+
+    impl MockHyprland {
+        pub fn new(state: MockState) -> Self {
+            Self {
+                state: Arc::new(Mutex::new(state)),
+            }
+        }
+
+        pub fn state(&self) -> MutexGuard<'_, MockState> {
+            self.state.lock().expect("mock state lock poisoned")
+        }
+    }
+
+    impl HyprlandApi for MockHyprland {
+        async fn active_workspace(&self) -> Result<String> {
+            Ok(self.state().workspace.clone())
+        }
+
+        async fn monitors(&self) -> Result<Vec<Monitor>> {
+            Ok(self.state().monitors.clone())
+        }
+
+        async fn cursor_position(&self) -> Result<CursorPosition> {
+            let (x, y) = self.state().cursor;
+            Ok(CursorPosition { x, y })
+        }
+
+        async fn active_window(&self) -> Result<Option<Client>> {
+            Ok(self.state().active_window.clone())
+        }
+
+        async fn dispatch(&self, batch: DispatchBatch) -> Result<()> {
+            self.state()
+                .dispatched
+                .extend(batch.commands().iter().cloned());
+            Ok(())
+        }
+    }
+}
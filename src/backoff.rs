@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+/// growing delays between retries of long running loops, so that they survive hyprland
+/// restarts and socket hiccups without spinning
+#[derive(Debug)]
+pub struct Backoff {
+    delay: Duration,
+    last_failure: Option<Instant>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            delay: Self::MIN,
+            last_failure: None,
+        }
+    }
+}
+
+impl Backoff {
+    const MIN: Duration = Duration::from_millis(100);
+    const MAX: Duration = Duration::from_secs(10);
+    /// a failure this long after the previous one starts over from the shortest delay
+    const RESET_AFTER: Duration = Duration::from_secs(60);
+
+    /// waits before the next retry. waits longer every time things keep failing
+    pub async fn wait(&mut self) {
+        if self
+            .last_failure
+            .is_some_and(|t| t.elapsed() > Self::RESET_AFTER)
+        {
+            self.delay = Self::MIN;
+        }
+        tokio::time::sleep(self.delay).await;
+        self.delay = (self.delay * 2).min(Self::MAX);
+        self.last_failure = Some(Instant::now());
+    }
+}
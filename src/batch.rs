@@ -0,0 +1,157 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::UnixStream,
+};
+
+use crate::profile;
+
+/// hyprland commands that are sent in a single socket message, so that nothing
+/// else can land in between them
+#[derive(Debug, Default, Clone)]
+pub struct DispatchBatch {
+    commands: Vec<String>,
+}
+
+impl DispatchBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn commands(&self) -> &[String] {
+        &self.commands
+    }
+
+    pub fn dispatch(&mut self, dispatcher: &str, args: impl AsRef<str>) -> &mut Self {
+        self.commands
+            .push(format!("dispatch {} {}", dispatcher, args.as_ref()));
+        self
+    }
+
+    pub fn keyword(&mut self, key: &str, value: &str) -> &mut Self {
+        self.commands.push(format!("keyword {} {}", key, value));
+        self
+    }
+
+    pub fn workspace(&mut self, name: impl AsRef<str>) -> &mut Self {
+        self.dispatch("workspace", format!("name:{}", name.as_ref()))
+    }
+
+    /// brings the workspace to the focused monitor if it is on another one
+    pub fn workspace_on_current_monitor(&mut self, name: impl AsRef<str>) -> &mut Self {
+        self.dispatch(
+            "focusworkspaceoncurrentmonitor",
+            format!("name:{}", name.as_ref()),
+        )
+    }
+
+    pub fn focus_monitor(&mut self, name: impl AsRef<str>) -> &mut Self {
+        self.dispatch("focusmonitor", name)
+    }
+
+    pub fn move_to_workspace(&mut self, name: impl AsRef<str>) -> &mut Self {
+        self.dispatch("movetoworkspace", format!("name:{}", name.as_ref()))
+    }
+
+    /// moves the window without following it
+    pub fn move_window_silent(
+        &mut self,
+        address: impl AsRef<str>,
+        workspace: impl AsRef<str>,
+    ) -> &mut Self {
+        self.dispatch(
+            "movetoworkspacesilent",
+            format!("name:{},address:{}", workspace.as_ref(), address.as_ref()),
+        )
+    }
+
+    /// takes the workspace id, as hyprland splits the args at the first space and
+    /// workspace names can have spaces
+    pub fn move_workspace_to_monitor(&mut self, id: i32, monitor: impl AsRef<str>) -> &mut Self {
+        self.dispatch(
+            "moveworkspacetomonitor",
+            format!("{} {}", id, monitor.as_ref()),
+        )
+    }
+
+    pub fn move_cursor(&mut self, x: i64, y: i64) -> &mut Self {
+        self.dispatch("movecursor", format!("{} {}", x, y))
+    }
+
+    pub fn focus_window(&mut self, address: impl AsRef<str>) -> &mut Self {
+        self.dispatch("focuswindow", format!("address:{}", address.as_ref()))
+    }
+
+    pub fn extend(&mut self, other: DispatchBatch) -> &mut Self {
+        self.commands.extend(other.commands);
+        self
+    }
+
+    pub async fn send(&self) -> Result<()> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let dispatchers = self
+            .commands
+            .iter()
+            .filter_map(|c| c.split_whitespace().nth(1))
+            .collect::<Vec<_>>()
+            .join(", ");
+        profile::span(format!("dispatch ({})", dispatchers), self._send()).await
+    }
+
+    async fn _send(&self) -> Result<()> {
+        let resp = request(&format!("[[BATCH]]{}", self.commands.join(";"))).await?;
+        if resp.split_whitespace().any(|r| r != "ok") {
+            return Err(anyhow!(
+                "hyprland rejected batch {:?}: {}",
+                &self.commands,
+                resp.trim()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// hyprctl style requests (like "j/clients") in a single socket message. the replies come back
+/// one after the other
+pub async fn query(requests: &[&str]) -> Result<String> {
+    request(&format!("[[BATCH]]{}", requests.join(";"))).await
+}
+
+async fn request(message: &str) -> Result<String> {
+    let sock_path = get_hyprland_socket_path()?;
+    let mut sock = UnixStream::connect(&sock_path)
+        .await
+        .with_context(|| format!("could not connect to hyprland socket at {:?}", &sock_path))?;
+    sock.write_all(message.as_bytes()).await?;
+
+    let mut resp = String::new();
+    sock.read_to_string(&mut resp).await?;
+    Ok(resp)
+}
+
+fn get_hyprland_socket_path() -> Result<PathBuf> {
+    let hypr_signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+        .context("could not get HYPRLAND_INSTANCE_SIGNATURE")?;
+
+    // hyprland moved its sockets to XDG_RUNTIME_DIR in v0.40
+    let runtime_sock = std::env::var("XDG_RUNTIME_DIR").map(|dir| {
+        PathBuf::from(dir)
+            .join("hypr")
+            .join(&hypr_signature)
+            .join(".socket.sock")
+    });
+    match runtime_sock {
+        Ok(p) if p.exists() => Ok(p),
+        _ => Ok(PathBuf::from("/tmp/hypr")
+            .join(&hypr_signature)
+            .join(".socket.sock")),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::config::Config;
+
+/// keys used for numbered binds
+const NUMBER_KEYS: [&str; 9] = ["1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// hyprland.conf snippet with binds for the commands that make sense with this config
+pub fn generate(config: &Config, modifier: &str) -> String {
+    let mut lines = vec![
+        "# generated by `hyprkool generate-binds`. source it from hyprland.conf".to_owned(),
+        format!("$hyprkoolMod = {}", modifier),
+    ];
+
+    section(
+        &mut lines,
+        "move around the grid. SHIFT takes the focused window along",
+    );
+    for dir in ["left", "right", "up", "down"] {
+        bind(&mut lines, "", dir, format!("move-{} -c", dir));
+        bind(&mut lines, "SHIFT", dir, format!("move-{} -c -w", dir));
+    }
+
+    section(
+        &mut lines,
+        "activities. SHIFT takes the focused window along",
+    );
+    bind(&mut lines, "", "TAB", "next-activity -c".into());
+    bind(&mut lines, "SHIFT", "TAB", "prev-activity -c".into());
+    let activities = match config.activities.is_empty() {
+        true => vec!["default".to_owned()],
+        false => config.activities.clone(),
+    };
+    for (key, activity) in NUMBER_KEYS.iter().zip(activities.iter()) {
+        bind(
+            &mut lines,
+            "",
+            key,
+            format!("switch-to-activity -n {}", activity),
+        );
+        bind(
+            &mut lines,
+            "SHIFT",
+            key,
+            format!("switch-to-activity -n {} -w", activity),
+        );
+    }
+
+    section(&mut lines, "workspaces of the current activity, row by row");
+    let (nx, ny) = config.workspaces;
+    let cells = (1..=ny).flat_map(|y| (1..=nx).map(move |x| (x, y)));
+    for (key, (x, y)) in NUMBER_KEYS.iter().zip(cells) {
+        let args = format!("switch-to-workspace-in-activity -n \"({} {})\"", x, y);
+        bind(&mut lines, "CTRL", key, args.clone());
+        bind(&mut lines, "CTRL SHIFT", key, format!("{} -w", args));
+    }
+
+    let mut named_focii = config.named_focii.keys().collect::<Vec<_>>();
+    named_focii.sort();
+    if !named_focii.is_empty() {
+        section(
+            &mut lines,
+            "named focii. SHIFT sets them to the current workspace",
+        );
+    }
+    for (key, name) in NUMBER_KEYS.iter().zip(named_focii) {
+        let name = quoted(name);
+        bind(
+            &mut lines,
+            "ALT",
+            key,
+            format!("switch-named-focus -n {}", name),
+        );
+        bind(
+            &mut lines,
+            "ALT SHIFT",
+            key,
+            format!("set-named-focus -n {}", name),
+        );
+    }
+
+    section(&mut lines, "misc");
+    bind(&mut lines, "", "grave", "switch-back".into());
+    bind(&mut lines, "", "b", "toggle-overview".into());
+
+    lines.join("\n")
+}
+
+fn section(lines: &mut Vec<String>, comment: &str) {
+    lines.push(String::new());
+    lines.push(format!("# {}", comment));
+}
+
+fn bind(lines: &mut Vec<String>, mods: &str, key: &str, args: String) {
+    let mods = match mods {
+        "" => "$hyprkoolMod".to_owned(),
+        mods => format!("$hyprkoolMod {}", mods),
+    };
+    lines.push(format!("bind = {}, {}, exec, hyprkool {}", mods, key, args));
+}
+
+/// quotes the argument for the shell that hyprland's exec runs commands in
+fn quoted(arg: &str) -> String {
+    if arg.chars().all(|c| c.is_alphanumeric() || "-_".contains(c)) {
+        return arg.to_owned();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
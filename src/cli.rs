@@ -0,0 +1,535 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use clap::{arg, command, Parser};
+use hyprland::{data::Workspace, shared::HyprDataActive};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
+    signal::unix::{signal, SignalKind},
+    sync::Mutex,
+};
+use tracing::{error, info};
+
+use crate::{
+    backoff::Backoff,
+    binds,
+    command::{pick_workspace, picked_command, AliasCommand, Command, GridCommand},
+    completions,
+    config::{self, Config},
+    daemon::{self, DaemonLock, IpcDaemon, MouseDaemon},
+    exec,
+    gestures::GestureDaemon,
+    info::InfoOutputStream,
+    notify, profile,
+    session::Session,
+    snapshot::Snapshot,
+    state::{Animation, State, SwitchSource},
+    systemd,
+    target::{remap_target, Target},
+    Message,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(author, version, about)]
+pub struct Cli {
+    /// Specify a custom config directory
+    #[arg(short, long)]
+    pub config_dir: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// don't use daemon for this command even if one is active (mainly useful for debugging)
+    #[arg(long)]
+    pub force_no_daemon: bool,
+
+    /// keep the last hyprkool animation active after switching instead of restoring the
+    /// animation configured in hyprland.conf
+    #[arg(long)]
+    pub no_restore: bool,
+
+    /// animate the switches of this command with this style instead of the usual one
+    /// (needs the plugin, except for none)
+    #[arg(long, value_enum, global = true, conflicts_with = "no_animation")]
+    pub animation: Option<Animation>,
+
+    /// switch without animation. the same as --animation none
+    #[arg(long, global = true)]
+    pub no_animation: bool,
+
+    /// print how long each hyprland/plugin/daemon call took
+    #[arg(long)]
+    pub profile: bool,
+
+    /// log more. -v for debug and -vv for trace logs
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// append logs to this file instead of stderr
+    #[arg(long)]
+    pub log_file: Option<PathBuf>,
+
+    /// send logs to journald (useful when the daemon runs as a systemd service)
+    #[arg(long)]
+    pub journald: bool,
+}
+
+impl Cli {
+    pub fn config_path(&self) -> Option<PathBuf> {
+        self.config_dir
+            .clone()
+            .map(PathBuf::from)
+            .or(dirs::config_dir().map(|pb| pb.join("hypr")))
+            .map(|pb| pb.join("hyprkool.toml"))
+    }
+
+    /// animation set with --animation or --no-animation
+    pub fn animation_override(&self) -> Option<Animation> {
+        match self.no_animation {
+            true => Some(Animation::None),
+            false => self.animation,
+        }
+    }
+
+    pub fn config(&self) -> Result<Config> {
+        let mut config = self
+            .config_path()
+            .filter(|p| p.exists())
+            .map(std::fs::read_to_string)
+            .transpose()?
+            .map(|s| toml::from_str::<Config>(&s))
+            .transpose()?
+            .unwrap_or(Config::default());
+        config.resolve_templates()?;
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// runs the parsed command, either locally or through the daemon if one is running
+pub async fn run(cli: Cli) -> Result<()> {
+    let res = execute(cli.clone()).await;
+    if let Err(e) = &res {
+        notify_error(&cli, &e.to_string()).await;
+    }
+    res
+}
+
+/// also shows the error as a desktop notification if notify_errors is set
+async fn notify_error(cli: &Cli, message: &str) {
+    if cli.config().is_ok_and(|c| c.notify_errors) {
+        notify::error(message).await;
+    }
+}
+
+async fn execute(cli: Cli) -> Result<()> {
+    match cli.command.clone() {
+        Command::CheckConfig => {
+            match cli.config_path().filter(|p| p.exists()) {
+                Some(p) => println!("checking {:?}", p),
+                None => println!("no config file found. checking default config"),
+            }
+            match cli.config() {
+                Ok(_) => println!("config ok"),
+                Err(e) => {
+                    println!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Command::Completions { shell } => {
+            let config = cli.config().unwrap_or_default();
+            print!("{}", completions::generate(shell, &config));
+        }
+        Command::GenerateBinds { modifier } => {
+            println!("{}", binds::generate(&cli.config()?, &modifier));
+        }
+        Command::Rules { command } => {
+            println!("{}", command.execute(&cli.config()?)?);
+        }
+        Command::SaveSession { file } => {
+            let state = State::new(cli.config()?)?;
+            let file = file.map(Ok).unwrap_or_else(Session::default_path)?;
+            let session = Session::capture(&state).await?;
+            session.save(&file)?;
+            println!("saved {} windows to {:?}", session.windows.len(), file);
+        }
+        Command::RestoreSession { file, no_launch } => {
+            let file = file.map(Ok).unwrap_or_else(Session::default_path)?;
+            let mut session = Session::load(&file)?;
+            session.restore_open_windows().await?;
+            if !no_launch {
+                session.relaunch().await?;
+            }
+        }
+        Command::Daemon {
+            move_to_hyprkool_activity,
+            install_service: true,
+            ..
+        } => {
+            let dir = systemd::install_service(&cli, move_to_hyprkool_activity)?;
+            println!("wrote hyprkool.socket and hyprkool.service to {:?}", dir);
+            println!("enable them with: systemctl --user daemon-reload && systemctl --user enable --now hyprkool.socket");
+        }
+        Command::Daemon {
+            move_to_hyprkool_activity,
+            replace,
+            ..
+        } => {
+            if cli.force_no_daemon {
+                println!("--force-no-daemon not allowed with this command");
+                return Ok(());
+            }
+            let _lock = DaemonLock::acquire(replace).await?;
+
+            let mut state = match State::new(cli.config()?) {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("{}", e);
+                    return Ok(());
+                }
+            };
+            state.restore_animation = !cli.no_restore;
+            // one query for everything instead of every part asking hyprland on its own
+            let snapshot = Snapshot::take().await?;
+            state.warm_start(&snapshot);
+            if let Err(e) = state.apply_monitor_rules().await {
+                error!("could not apply monitor rules: {}", e);
+            }
+            match state
+                .collect_stale_activities_in(snapshot.clients.clone())
+                .await
+            {
+                Ok(0) => (),
+                Ok(n) => info!("moved {} windows out of stale activities", n),
+                Err(e) => error!("could not clean up stale activities: {}", e),
+            }
+            if state.config.daemon.session.autorestore {
+                if let Err(e) = state.start_session_restore().await {
+                    error!("could not restore session: {}", e);
+                }
+            }
+            let notify_errors = state.config.notify_errors;
+            let state = Arc::new(Mutex::new(state));
+            let mut md = MouseDaemon::from_snapshot(state.clone(), &snapshot).await?;
+            let mut gd = GestureDaemon::new(state.clone()).await;
+            // its own task, so that restarting it does not interrupt the other loops
+            let gestures = tokio::spawn(async move {
+                let mut backoff = Backoff::default();
+                loop {
+                    if let Err(e) = gd.run().await {
+                        error!("gesture loop failed. restarting it: {}", e);
+                        if notify_errors {
+                            notify::error(&format!("gesture loop failed: {}", e)).await;
+                        }
+                        backoff.wait().await;
+                    }
+                }
+            });
+            let id = IpcDaemon::new(state.clone()).await?;
+            let mut id_fut = std::pin::pin!(id.run());
+            let mut sigterm = signal(SignalKind::terminate())?;
+            let mut mouse_backoff = Backoff::default();
+            let mut ipc_backoff = Backoff::default();
+
+            loop {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {
+                        break;
+                    }
+                    _ = sigterm.recv() => {
+                        break;
+                    }
+                    mouse = md.run(move_to_hyprkool_activity) => {
+                        match mouse {
+                            Ok(_) => {
+                                break;
+                            }
+                            Err(e) => {
+                                error!("mouse loop failed. restarting it: {}", e);
+                                if notify_errors {
+                                    notify::error(&format!("mouse loop failed: {}", e)).await;
+                                }
+                                mouse_backoff.wait().await;
+                            }
+                        }
+                    }
+                    ipc = &mut id_fut => {
+                        match ipc {
+                            Ok(_) => {
+                                break;
+                            }
+                            Err(e) => {
+                                error!("ipc loop failed. restarting it: {}", e);
+                                if notify_errors {
+                                    notify::error(&format!("ipc loop failed: {}", e)).await;
+                                }
+                                ipc_backoff.wait().await;
+                                id_fut.set(id.run());
+                            }
+                        }
+                    }
+                }
+            }
+            // kills libinput
+            gestures.abort();
+            let mut s = state.lock().await;
+            if let Err(e) = s.restore_workspace_anim().await {
+                error!("could not restore animation: {}", e);
+            }
+            if let Err(e) = s.keywords.restore_all().await {
+                error!("could not restore keywords: {}", e);
+            }
+            if let Err(e) = s.freezer.thaw_all().await {
+                error!("could not thaw frozen activities: {}", e);
+            }
+            if let Err(e) = s.dimmer.restore_all().await {
+                error!("could not undim windows: {}", e);
+            }
+            // runs dnd_off if the session turned dnd on
+            s.focus.stop();
+            if s.config.daemon.session.autosave {
+                if let Err(e) = s.save_session().await {
+                    error!("could not save session: {}", e);
+                }
+            }
+            info!("exiting daemon");
+        }
+        Command::Info {
+            command,
+            monitor,
+            diff,
+        } => {
+            // monitoring commands keep going through daemon and hyprland restarts
+            let mut backoff = Backoff::default();
+            loop {
+                if !cli.force_no_daemon {
+                    if let Ok(sock) = daemon::connect().await {
+                        let mut sock = BufWriter::new(sock);
+                        sock.write_all(
+                            &Message::Command(Command::Info {
+                                command: command.clone(),
+                                monitor,
+                                diff,
+                            })
+                            .msg(),
+                        )
+                        .await?;
+                        sock.flush().await?;
+                        sock.shutdown().await?;
+
+                        let mut sock = BufReader::new(sock);
+                        loop {
+                            let mut line = String::new();
+                            let res = sock.read_line(&mut line).await;
+
+                            if !monitor && line.is_empty() {
+                                return Ok(());
+                            }
+                            if res.is_err() || line.is_empty() {
+                                break;
+                            }
+
+                            let command = serde_json::from_str(&line)?;
+                            match command {
+                                Message::IpcMessage(message) => {
+                                    println!("{}", message);
+                                }
+                                Message::IpcErr(message) => {
+                                    println!("{}", message);
+                                }
+                                _ => {
+                                    unreachable!();
+                                }
+                            }
+                        }
+                        error!("lost connection to the daemon. reconnecting");
+                        backoff.wait().await;
+                        continue;
+                    }
+
+                    let config = cli.config()?;
+                    if !config.daemon.fallback_commands {
+                        return Ok(());
+                    }
+                    dbg!("falling back to stateless commands");
+                }
+
+                let state = match State::new(cli.config()?) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        println!("{}", e);
+                        return Ok(());
+                    }
+                };
+                let res = command
+                    .execute(
+                        InfoOutputStream::Stdout,
+                        Arc::new(Mutex::new(state)),
+                        monitor,
+                        diff,
+                    )
+                    .await;
+                match res {
+                    Err(e) if monitor => {
+                        error!("info command failed. retrying: {}", e);
+                        backoff.wait().await;
+                    }
+                    res => return res,
+                }
+            }
+        }
+        Command::Overview { menu: Some(menu) } => {
+            let state = State::new(cli.config()?)?;
+            if let Some(name) = pick_workspace(&state, &menu).await? {
+                send_command(
+                    &cli,
+                    Command::SwitchToWorkspace {
+                        name,
+                        move_window: false,
+                    },
+                )
+                .await?;
+            }
+        }
+        Command::Exec {
+            workspace,
+            silent,
+            timeout,
+            command,
+            ..
+        } => {
+            Target::parse(&workspace, cli.config().is_ok_and(|c| c.flat_grid))?;
+            let token = exec::new_token();
+            // the daemon has to know the token before any window can open
+            send_command(
+                &cli,
+                Command::Exec {
+                    workspace,
+                    silent,
+                    timeout,
+                    token: Some(token.clone()),
+                    command: command.clone(),
+                },
+            )
+            .await?;
+            exec::launch(&command, &token)?;
+        }
+        Command::Alias {
+            command: AliasCommand::Set { name, save: true },
+        } => {
+            let path = cli.config_path().context("could not find the config dir")?;
+            State::new(cli.config()?)?.check_alias(&name)?;
+            let workspace = Workspace::get_active_async().await?.name;
+            send_command(&cli, cli.command.clone()).await?;
+            config::save_named_focus(&path, &name, Some(&workspace))?;
+        }
+        Command::Alias {
+            command: AliasCommand::Remove { name, save: true },
+        } => {
+            let path = cli.config_path().context("could not find the config dir")?;
+            send_command(&cli, cli.command.clone()).await?;
+            config::save_named_focus(&path, &name, None)?;
+        }
+        Command::Grid {
+            command: GridCommand::Resize { x, y, save: true },
+        } => {
+            let path = cli.config_path().context("could not find the config dir")?;
+            // targets in the file were written for the grid in the file
+            let config = cli.config()?;
+            send_command(&cli, cli.command.clone()).await?;
+            config::save_grid(&path, (x, y), |t| {
+                remap_target(t, config.workspaces, (x, y), config.flat_grid)
+            })?;
+        }
+        Command::Pick => {
+            let state = State::new(cli.config()?)?;
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            if line.trim().is_empty() {
+                return Ok(());
+            }
+            send_command(&cli, picked_command(&state, line.trim())?).await?;
+        }
+        comm => {
+            send_command(&cli, comm).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// sends the command to the daemon, or executes it here if the daemon can not be reached
+async fn send_command(cli: &Cli, comm: Command) -> Result<()> {
+    if !cli.force_no_daemon {
+        if let Ok(sock) = daemon::connect().await {
+            let mut sock = BufWriter::new(sock);
+            let message = match cli.animation_override() {
+                Some(animation) => Message::AnimatedCommand(comm.clone(), animation),
+                None => Message::Command(comm.clone()),
+            };
+            sock.write_all(&message.msg()).await?;
+            sock.flush().await?;
+            sock.shutdown().await?;
+
+            let sleep = tokio::time::sleep(Duration::from_millis(300));
+            let mut sock = BufReader::new(sock);
+            let mut line = String::new();
+            tokio::select! {
+                res = profile::span("daemon round trip", sock.read_line(&mut line)) => {
+                    res?;
+                    let command = serde_json::from_str(&line)?;
+                    match command {
+                        Message::IpcOk => {
+                            println!("Ok");
+                            return Ok(());
+                        }
+                        Message::IpcMessage(message) => {
+                            println!("{}", message);
+                            return Ok(());
+                        }
+                        Message::IpcErr(message) => {
+                            println!("{}", message);
+                            notify_error(cli, &message).await;
+                            return Ok(());
+                        }
+                        _ => {
+                            unreachable!();
+                        }
+                    }
+                }
+                _ = sleep => {
+                    println!("timeout. could not connect to hyprkool");
+                    notify_error(cli, "timeout. could not connect to the hyprkool daemon").await;
+                }
+            }
+        }
+
+        let config = cli.config()?;
+        if !config.daemon.fallback_commands {
+            return Ok(());
+        }
+        println!("falling back to stateless commands");
+    }
+
+    let mut state = match State::new(cli.config()?) {
+        Ok(s) => s,
+        Err(e) => {
+            println!("{}", e);
+            return Ok(());
+        }
+    };
+    state.restore_animation = !cli.no_restore;
+    let output = comm
+        .execute_from(
+            Arc::new(Mutex::new(state)),
+            false,
+            SwitchSource::Keyboard,
+            cli.animation_override(),
+        )
+        .await?;
+    if let Some(output) = output {
+        println!("{}", output);
+    }
+    Ok(())
+}
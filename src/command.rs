@@ -1,18 +1,34 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    path::PathBuf,
+    process::Stdio,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
 use clap::{arg, command, Subcommand};
 use hyprland::{
-    data::{Client, Clients, CursorPosition, Workspace},
-    dispatch::{Dispatch, DispatchType, WindowIdentifier},
+    data::{Client, Clients, CursorPosition, Workspace, Workspaces},
     shared::{HyprData, HyprDataActive, HyprDataActiveOptional},
 };
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
+use tokio::{io::AsyncWriteExt, sync::Mutex};
 
 use crate::{
+    batch::DispatchBatch,
+    completions::CompletionShell,
+    focus::FocusCommand,
     info::InfoCommand,
-    state::{is_plugin_running, Animation},
+    keywords::KeywordsCommand,
+    profile,
+    rules::RulesCommand,
+    state::{
+        activity_name, cell_workspace_name, finish_pending_switch, is_plugin_running,
+        nearest_empty, parse_cell, workspace_suffix, Animation, SwitchSource, WorkspaceName,
+    },
+    stats::StatsCommand,
     State,
 };
 
@@ -21,18 +37,100 @@ pub enum Command {
     Daemon {
         #[arg(long, short, default_value_t = false)]
         move_to_hyprkool_activity: bool,
+
+        /// take over from a daemon that is already running instead of failing
+        #[arg(long, default_value_t = false)]
+        replace: bool,
+
+        /// write systemd user units that start the daemon when the first command is sent,
+        /// instead of starting it
+        #[arg(long, default_value_t = false)]
+        install_service: bool,
     },
     DaemonQuit,
+    /// validate the config file and report all problems
+    CheckConfig,
+    /// print a shell completion script. activities and named focii are read from the config
+    Completions {
+        #[arg(value_enum)]
+        shell: CompletionShell,
+    },
+    /// print hyprland binds for the current config, ready to be sourced from hyprland.conf
+    GenerateBinds {
+        /// hyprland modifier used for all binds
+        #[arg(long, short, default_value = "SUPER")]
+        modifier: String,
+    },
+    Rules {
+        #[command(subcommand)]
+        command: RulesCommand,
+    },
     Info {
         #[command(subcommand)]
         command: InfoCommand,
 
-        #[arg(long, short, default_value_t = false)]
+        /// keep printing whenever something changes
+        #[arg(
+            long,
+            short,
+            visible_alias = "follow",
+            global = true,
+            default_value_t = false
+        )]
         monitor: bool,
+
+        /// only print json merge patches of what changed (with periodic full snapshots)
+        #[arg(
+            long,
+            short,
+            global = true,
+            default_value_t = false,
+            requires("monitor")
+        )]
+        diff: bool,
     },
+    /// focus a window by address, or jump to the activity:workspace of a window by class
     FocusWindow {
+        #[arg(long, short, required_unless_present("class"), conflicts_with("class"))]
+        address: Option<String>,
+
+        /// regex that is searched in window classes. prints candidates as json if
+        /// more than one window matches
+        #[arg(long)]
+        class: Option<String>,
+    },
+    /// launch an app and put its windows on the workspace, even if they open late
+    Exec {
+        /// workspace target like activity:(x y), activity:x,y or activity:n
         #[arg(long, short)]
-        address: String,
+        workspace: String,
+
+        /// do not switch to the workspace (needs daemon)
+        #[arg(long, short, default_value_t = false)]
+        silent: bool,
+
+        /// windows opening later than this many seconds are left alone
+        #[arg(long, default_value_t = 30)]
+        timeout: u64,
+
+        /// set by the cli for the daemon. the launched process has it in HYPRKOOL_EXEC
+        #[arg(skip)]
+        token: Option<String>,
+
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// switch to the closest workspace of the current activity that has no windows
+    GotoEmpty {
+        /// move focused window and move to workspace
+        #[arg(long, short = 'w', default_value_t = false)]
+        move_window: bool,
+    },
+    /// focus the next window of the current activity, switching workspaces as needed
+    CycleWindows {
+        /// focus the previous window instead
+        #[arg(long, short, default_value_t = false)]
+        prev: bool,
     },
     MoveRight {
         #[arg(long, short, default_value_t = false)]
@@ -41,6 +139,10 @@ pub enum Command {
         /// move focused window and move to workspace
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
+
+        /// past the edge of the grid, focus the monitor next to this one instead
+        #[arg(long, default_value_t = false)]
+        through_monitors: bool,
     },
     MoveLeft {
         #[arg(long, short, default_value_t = false)]
@@ -49,6 +151,10 @@ pub enum Command {
         /// move focused window and move to workspace
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
+
+        /// past the edge of the grid, focus the monitor next to this one instead
+        #[arg(long, default_value_t = false)]
+        through_monitors: bool,
     },
     MoveUp {
         #[arg(long, short, default_value_t = false)]
@@ -57,6 +163,10 @@ pub enum Command {
         /// move focused window and move to workspace
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
+
+        /// past the edge of the grid, focus the monitor next to this one instead
+        #[arg(long, default_value_t = false)]
+        through_monitors: bool,
     },
     MoveDown {
         #[arg(long, short, default_value_t = false)]
@@ -65,6 +175,10 @@ pub enum Command {
         /// move focused window and move to workspace
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
+
+        /// past the edge of the grid, focus the monitor next to this one instead
+        #[arg(long, default_value_t = false)]
+        through_monitors: bool,
     },
     NextActivity {
         #[arg(long, short, default_value_t = false)]
@@ -73,6 +187,10 @@ pub enum Command {
         /// move focused window and move to workspace
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
+
+        /// switch to the most recently used activity instead (needs daemon)
+        #[arg(long, default_value_t = false)]
+        mru: bool,
     },
     PrevActivity {
         #[arg(long, short, default_value_t = false)]
@@ -83,7 +201,7 @@ pub enum Command {
         move_window: bool,
     },
     SwitchToActivity {
-        /// <activity name>
+        /// <activity name> (or its icon or display name)
         #[arg(short, long)]
         name: String,
 
@@ -91,6 +209,14 @@ pub enum Command {
         #[arg(long, short = 'w', default_value_t = false)]
         move_window: bool,
     },
+    /// switch to the nth activity in the order they are configured in. starting at 1
+    SwitchToActivityIndex {
+        n: usize,
+
+        /// move focused window and move to workspace
+        #[arg(long, short = 'w', default_value_t = false)]
+        move_window: bool,
+    },
     SwitchToWorkspaceInActivity {
         /// <workspace name>
         #[arg(short, long)]
@@ -101,7 +227,7 @@ pub enum Command {
         move_window: bool,
     },
     SwitchToWorkspace {
-        /// <activity name>:<workspace name>
+        /// activity, activity:(x y), activity:x,y or activity:n
         #[arg(short, long)]
         name: String,
 
@@ -135,60 +261,597 @@ pub enum Command {
         name: String,
     },
     ToggleOverview,
+    /// go back to the previously focused hyprkool workspace, in any activity (needs daemon)
+    SwitchBack {
+        /// move focused window and move to workspace
+        #[arg(long, short = 'w', default_value_t = false)]
+        move_window: bool,
+    },
+    Grid {
+        #[command(subcommand)]
+        command: GridCommand,
+    },
+    Activity {
+        #[command(subcommand)]
+        command: ActivityCommand,
+    },
+    /// exchange all windows of the current workspace and another one of the activity
+    Swap {
+        /// left, right, up, down, x,y or any workspace target
+        #[arg(long, short)]
+        with: String,
+    },
+    /// hyprland keywords and rules hyprkool has changed (needs daemon)
+    Keywords {
+        #[command(subcommand)]
+        command: KeywordsCommand,
+    },
+    /// timed sessions on one activity, like a pomodoro (needs daemon)
+    Focus {
+        #[command(subcommand)]
+        command: FocusCommand,
+    },
+    /// notes on workspaces, shown in status tooltips and list outputs (needs daemon)
+    Label {
+        #[command(subcommand)]
+        command: LabelCommand,
+    },
+    /// names for workspaces. the same as named_focii in the config, so switch-to-workspace -n
+    /// and switch-named-focus take them (needs daemon)
+    Alias {
+        #[command(subcommand)]
+        command: AliasCommand,
+    },
+    /// go back and forward through visited workspaces like in a browser (needs daemon)
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+    /// how hyprkool workspaces are used (needs daemon)
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+    /// remember windows in slots and jump back to them (needs daemon)
+    Mark {
+        #[command(subcommand)]
+        command: MarkCommand,
+    },
+    /// turn switching workspaces on screen edges on/off (needs daemon)
+    ToggleEdgeSwitching,
+    /// the session is idle. pauses screen edges and gestures till wake (needs daemon)
+    Idle,
+    /// the session is back from idle. edges stay paused while it is locked (needs daemon)
+    Wake,
+    /// the session is locked. pauses screen edges and gestures till unlock (needs daemon)
+    Lock,
+    /// the session is unlocked. edges stay paused while it is idle (needs daemon)
+    Unlock,
+    /// the focused window follows you to every workspace and activity till toggled again (needs daemon)
+    TogglePin,
+    /// show activity a on the left monitor and activity b on the right one (needs daemon).
+    /// navigation stays on the focused monitor's activity till unpaired
+    PairActivities {
+        a: String,
+        b: String,
+    },
+    UnpairActivities,
+    /// list activities, workspaces and named focii. one per line, ready to be piped into a menu
+    List {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// read a line printed by 'hyprkool list' from stdin and switch to it
+    Pick,
+    /// move windows from activities of older dates ("{date}" in activity names) to the current one
+    Gc {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// rename workspaces named with an older scheme ("activity:n", or cells from before
+    /// flat_grid was changed) to the current one
+    MigrateNames {
+        /// only print what would be renamed
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// move windows on plain numbered workspaces ("1", "2", ...) into the grid of the first
+    /// activity. the workspaces are renamed to free cells in order, so they keep their monitor
+    /// and layout
+    Adopt {
+        /// only print what would be renamed
+        #[arg(long, default_value_t = false)]
+        dry_run: bool,
+    },
+    /// check if the daemon and the plugin are running
+    Ping {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// list activities with the number of windows in each. the focused one is marked with '*'
+    ListActivities {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// show the grid of the current activity and switch to the picked cell.
+    /// uses the plugin overview unless a menu command is given
+    Overview {
+        /// dmenu like command that reads choices on stdin and prints the picked one (eg. "rofi -dmenu")
+        #[arg(long, short)]
+        menu: Option<String>,
+    },
+    /// save windows on hyprkool workspaces and their launch commands
+    SaveSession {
+        /// defaults to ~/.local/share/hyprkool/session.json
+        #[arg(long, short)]
+        file: Option<PathBuf>,
+    },
+    /// move open windows back to the workspaces they were on in a saved session
+    /// and launch the ones that are not open
+    RestoreSession {
+        /// defaults to ~/.local/share/hyprkool/session.json
+        #[arg(long, short)]
+        file: Option<PathBuf>,
+
+        /// only move open windows. do not launch missing ones
+        #[arg(long, default_value_t = false)]
+        no_launch: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum GridCommand {
+    /// change the grid size till the daemon restarts (or --save). windows, aliases, rules and labels
+    /// outside the new grid are moved to the closest workspace inside it (needs daemon)
+    Resize {
+        x: u32,
+        y: u32,
+        /// also write the size and the moved aliases and rules to the config
+        #[arg(long, default_value_t = false)]
+        save: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ActivityCommand {
+    /// move every window of activity src to the same workspace in activity dst
+    Merge {
+        src: String,
+        dst: String,
+        /// move them all to the first workspace of dst instead
+        #[arg(long, default_value_t = false)]
+        collapse: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum LabelCommand {
+    /// label a workspace till the daemon restarts
+    Set {
+        /// x,y in the current activity or any workspace target. defaults to the current workspace
+        #[arg(long, short)]
+        pos: Option<String>,
+        text: String,
+    },
+    Clear {
+        /// x,y in the current activity or any workspace target. defaults to the current workspace
+        #[arg(long, short)]
+        pos: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum AliasCommand {
+    /// name the current workspace
+    Set {
+        name: String,
+        /// also write it to named_focii in the config
+        #[arg(long, default_value_t = false)]
+        save: bool,
+    },
+    Remove {
+        name: String,
+        /// also remove it from named_focii in the config
+        #[arg(long, default_value_t = false)]
+        save: bool,
+    },
+    /// every alias and the workspace it points to
+    List {
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum HistoryCommand {
+    Back,
+    Forward,
+    /// visited workspaces, oldest first. the current one is marked with '*'
+    List {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MarkCommand {
+    /// remember the focused window in this slot
+    Set { slot: String },
+    /// focus the window in this slot, switching to its workspace
+    Goto { slot: String },
 }
 
 impl Command {
-    pub async fn execute(self, state: Arc<Mutex<State>>, stateful: bool) -> Result<()> {
-        let mut state = state.lock().await;
+    /// subcommand name as typed on the command line. eg. "move-left"
+    pub fn name(&self) -> String {
+        let variant = match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => name,
+            Ok(serde_json::Value::Object(map)) => map.keys().next().cloned().unwrap_or_default(),
+            _ => String::new(),
+        };
+        let mut name = String::new();
+        for (i, c) in variant.chars().enumerate() {
+            if c.is_uppercase() && i > 0 {
+                name.push('-');
+            }
+            name.push(c.to_ascii_lowercase());
+        }
+        name
+    }
+
+    /// commands that the cli runs itself instead of sending them to the daemon. these can't
+    /// be run from inside the daemon (edge actions, rpc)
+    pub fn runs_locally(&self) -> bool {
+        matches!(
+            self,
+            Command::Daemon { .. }
+                | Command::DaemonQuit
+                | Command::CheckConfig
+                | Command::GenerateBinds { .. }
+                | Command::Completions { .. }
+                | Command::Rules { .. }
+                | Command::Overview { menu: Some(_) }
+                | Command::Pick
+                | Command::Exec { .. }
+                | Command::SaveSession { .. }
+                | Command::RestoreSession { .. }
+                | Command::Alias {
+                    command: AliasCommand::Set { save: true, .. }
+                        | AliasCommand::Remove { save: true, .. }
+                }
+        )
+    }
+
+    /// returns output that should be shown to the user.
+    /// source: what caused this command. reported in switch events.
+    /// animation: used for every switch of this command instead of the command's own
+    pub async fn execute_from(
+        self,
+        state: Arc<Mutex<State>>,
+        stateful: bool,
+        source: SwitchSource,
+        animation: Option<Animation>,
+    ) -> Result<Option<String>> {
+        let mut s = state.lock().await;
+        s.switch_source = source;
+        s.switch_command = Some(self.name());
+        s.animation_override = animation;
+        let res = self.run(&mut s, stateful).await;
+        // switches that don't come from commands (like edges) use their own animation again
+        s.animation_override = None;
+        drop(s);
+        let output = res?;
+        finish_pending_switch(&state).await?;
+        Ok(output)
+    }
+
+    async fn run(mut self, state: &mut State, stateful: bool) -> Result<Option<String>> {
+        let in_daemon = stateful;
         let stateful = state.config.daemon.remember_activity_focus && stateful;
 
+        if let Command::SwitchToActivityIndex { n, move_window } = self {
+            let name = n
+                .checked_sub(1)
+                .and_then(|i| state.activities.get(i))
+                .with_context(|| {
+                    format!(
+                        "no activity {}. there are {} activities",
+                        n,
+                        state.activities.len()
+                    )
+                })?;
+            self = Command::SwitchToActivity {
+                name: name.clone(),
+                move_window,
+            };
+        }
+        if let Command::SwitchToActivity { name, .. } = &mut self {
+            if let Some(a) = state.activity_by_alias(name) {
+                *name = a.to_owned();
+            }
+        }
+
         if stateful {
-            let workspace = Workspace::get_active_async().await?;
+            let workspace =
+                profile::span("query active workspace", Workspace::get_active_async()).await?;
             let a = match &self {
                 Command::SwitchToActivity { name, move_window } => {
                     state.remember_workspace(&workspace);
                     Some((name.clone(), *move_window))
                 }
-                Command::NextActivity { cycle, move_window } => {
-                    let i = state
-                        .activities
+                Command::NextActivity {
+                    cycle,
+                    move_window,
+                    mru,
+                } => {
+                    let current = state.workspace_activity_index(&workspace.name);
+                    let allowed = state.monitor_activities(&workspace.monitor);
+                    let recent = state
+                        .activity_history
                         .iter()
-                        .position(|a| workspace.name.starts_with(a))
-                        .map(|i| {
-                            let mut i = i;
-                            let n = state.activities.len();
-                            if *cycle {
-                                i = (i + 1) % n;
-                            } else {
-                                i = (i + 1).min(n - 1);
-                            }
-                            i
-                        })
-                        .unwrap_or(0);
+                        .rev()
+                        .filter(|_| *mru)
+                        .filter_map(|a| state.get_activity_index(a))
+                        .filter(|i| allowed.contains(i))
+                        .find(|&i| Some(i) != current);
+                    let i = recent.unwrap_or_else(|| {
+                        state.stepped_activity(current, &workspace.monitor, 1, *cycle)
+                    });
                     let a = state.activities[i].clone();
                     state.remember_workspace(&workspace);
                     Some((a, *move_window))
                 }
                 Command::PrevActivity { cycle, move_window } => {
-                    let i = state
-                        .activities
-                        .iter()
-                        .position(|a| workspace.name.starts_with(a))
-                        .map(|i| {
-                            let mut i = i as isize;
-                            let n = state.activities.len();
-                            if *cycle {
-                                i = (n as isize + i - 1) % n as isize;
-                            } else {
-                                i = (i - 1).max(0);
-                            }
-                            i as usize
-                        })
-                        .unwrap_or(0);
+                    let current = state.workspace_activity_index(&workspace.name);
+                    let i = state.stepped_activity(current, &workspace.monitor, -1, *cycle);
                     let a = state.activities[i].clone();
                     state.remember_workspace(&workspace);
                     Some((a, *move_window))
                 }
+                Command::PairActivities { a, b } => {
+                    state.remember_workspace(&workspace);
+                    state.pair_activities(a, b).await?;
+                    return Ok(None);
+                }
+                Command::UnpairActivities => {
+                    state.paired = None;
+                    return Ok(None);
+                }
+                Command::Stats { command } => {
+                    return Ok(Some(command.execute(state)?));
+                }
+                Command::Keywords { command } => {
+                    return command.execute(&mut state.keywords).await;
+                }
+                Command::Focus {
+                    command:
+                        FocusCommand::Start {
+                            activity,
+                            minutes,
+                            lock,
+                            dnd,
+                        },
+                } => {
+                    let activity = state
+                        .activity_by_alias(activity)
+                        .with_context(|| format!("unknown activity '{}'", activity))?
+                        .to_owned();
+                    // the old session's lock should not keep this one from starting
+                    state.focus.stop();
+                    state.remember_workspace(&workspace);
+                    let target = state.resolve_target(&activity)?;
+                    state
+                        .move_to_workspace(target, false, Animation::Fade)
+                        .await?;
+                    // only once the switch worked. finish_pending_switch stops it if the
+                    // pre-switch hook vetoes the switch
+                    state.focus.start(&activity, *minutes, *lock, *dnd);
+                    return Ok(None);
+                }
+                Command::Focus {
+                    command: FocusCommand::Stop,
+                } => {
+                    if !state.focus.stop() {
+                        return Err(anyhow!("no focus session running"));
+                    }
+                    return Ok(None);
+                }
+                Command::Focus {
+                    command: FocusCommand::Status { json },
+                } => {
+                    let status = state.focus.status();
+                    if *json {
+                        return Ok(Some(serde_json::to_string(&status)?));
+                    }
+                    return Ok(status.map(|s| {
+                        format!(
+                            "{} {}:{:02}",
+                            &s.activity,
+                            s.remaining / 60,
+                            s.remaining % 60
+                        )
+                    }));
+                }
+                Command::Mark {
+                    command: MarkCommand::Set { slot },
+                } => {
+                    let Some(window) = Client::get_active_async().await? else {
+                        return Err(anyhow!("no focused window"));
+                    };
+                    state.marks.insert(slot.clone(), window.address.to_string());
+                    return Ok(None);
+                }
+                Command::Mark {
+                    command: MarkCommand::Goto { slot },
+                } => {
+                    let Some(address) = state.marks.get(slot).cloned() else {
+                        return Err(anyhow!("no window marked in slot '{}'", slot));
+                    };
+                    let Some(window) = Clients::get_async()
+                        .await?
+                        .into_iter()
+                        .find(|c| c.address.to_string() == address)
+                    else {
+                        state.marks.remove(slot);
+                        return Err(anyhow!("window in slot '{}' is closed", slot));
+                    };
+                    state.remember_workspace(&workspace);
+                    let mut then = DispatchBatch::new();
+                    then.focus_window(&address);
+                    state
+                        .move_to_workspace_then(
+                            &window.workspace.name,
+                            false,
+                            Animation::Fade,
+                            then,
+                        )
+                        .await?;
+                    return Ok(None);
+                }
+                Command::Label {
+                    command: LabelCommand::Set { pos, text },
+                } => {
+                    let w = labeled_workspace(state, &workspace.name, pos.as_deref())?;
+                    state.labels.insert(w, text.clone());
+                    return Ok(None);
+                }
+                Command::Label {
+                    command: LabelCommand::Clear { pos },
+                } => {
+                    let w = labeled_workspace(state, &workspace.name, pos.as_deref())?;
+                    state.labels.remove(&w);
+                    return Ok(None);
+                }
+                Command::Alias {
+                    command: AliasCommand::Set { name, .. },
+                } => {
+                    state.check_alias(name)?;
+                    state
+                        .named_focii
+                        .insert(name.clone(), workspace.name.clone());
+                    return Ok(None);
+                }
+                Command::Alias {
+                    command: AliasCommand::Remove { name, .. },
+                } => {
+                    if state.named_focii.remove(name).is_none() {
+                        return Err(anyhow!("no alias named '{}'", name));
+                    }
+                    return Ok(None);
+                }
+                Command::Alias {
+                    command: AliasCommand::List { json },
+                } => {
+                    let aliases = state.named_focii.iter().collect::<BTreeMap<_, _>>();
+                    if *json {
+                        return Ok(Some(serde_json::to_string(&aliases)?));
+                    }
+                    let lines = aliases
+                        .into_iter()
+                        .map(|(name, workspace)| format!("{} -> {}", name, workspace))
+                        .collect::<Vec<_>>();
+                    return Ok(Some(lines.join("\n")));
+                }
+                Command::Grid {
+                    command: GridCommand::Resize { x, y, .. },
+                } => {
+                    state.resize_grid(*x, *y).await?;
+                    // TODO: same hack as set-named-focus to make info commands update
+                    state
+                        .move_to_workspace("hyprkool:T-T", false, Animation::None)
+                        .await?;
+                    let name = state
+                        .resolve_target(&workspace.name)
+                        .ok()
+                        .or_else(|| {
+                            parse_cell(&workspace.name, state.config.flat_grid).map(|(cx, cy)| {
+                                cell_workspace_name(
+                                    activity_name(&workspace.name, state.config.flat_grid),
+                                    cx.min(*x),
+                                    cy.min(*y),
+                                    state.config.flat_grid,
+                                )
+                            })
+                        })
+                        .unwrap_or(workspace.name.clone());
+                    state
+                        .move_to_workspace(name, false, Animation::None)
+                        .await?;
+                    return Ok(None);
+                }
+                Command::History { command } => {
+                    let steps = match command {
+                        HistoryCommand::Back => -1,
+                        HistoryCommand::Forward => 1,
+                        HistoryCommand::List { json: true } => {
+                            let history = HistoryListing {
+                                workspaces: &state.workspace_history,
+                                position: state.history_pos,
+                            };
+                            return Ok(Some(serde_json::to_string(&history)?));
+                        }
+                        HistoryCommand::List { json: false } => {
+                            let lines = state
+                                .workspace_history
+                                .iter()
+                                .enumerate()
+                                .map(|(i, w)| {
+                                    let marker = if i == state.history_pos { "*" } else { " " };
+                                    format!("{} {}", marker, w)
+                                })
+                                .collect::<Vec<_>>();
+                            return Ok(Some(lines.join("\n")));
+                        }
+                    };
+                    let Some(target) = state.history_step(steps) else {
+                        return Err(anyhow!("no more history in that direction"));
+                    };
+                    state.remember_workspace(&workspace);
+                    state
+                        .move_to_workspace(target, false, Animation::Fade)
+                        .await?;
+                    return Ok(None);
+                }
+                Command::SwitchBack { move_window } => {
+                    let Some(previous) = state.last_workspaces.0.clone() else {
+                        return Err(anyhow!("no previous workspace"));
+                    };
+                    state.remember_workspace(&workspace);
+                    state
+                        .move_to_workspace(previous, *move_window, Animation::Fade)
+                        .await?;
+                    return Ok(None);
+                }
+                Command::ToggleEdgeSwitching => {
+                    state.edge_switching = !state.edge_switching;
+                    state.edge_switching_toggled.notify_one();
+                    return Ok(None);
+                }
+                Command::Idle | Command::Wake => {
+                    state.idle = matches!(self, Command::Idle);
+                    state.edge_switching_toggled.notify_one();
+                    return Ok(None);
+                }
+                Command::Lock | Command::Unlock => {
+                    state.locked = matches!(self, Command::Lock);
+                    state.edge_switching_toggled.notify_one();
+                    return Ok(None);
+                }
+                Command::TogglePin => {
+                    let Some(window) = Client::get_active_async().await? else {
+                        return Err(anyhow!("no focused window"));
+                    };
+                    let address = window.address.to_string();
+                    if !state.following.remove(&address) {
+                        state.following.insert(address);
+                    }
+                    return Ok(None);
+                }
                 Command::SetNamedFocus { name } => {
                     let workspace = Workspace::get_active_async().await?;
                     if state
@@ -210,7 +873,7 @@ impl Command {
                     state
                         .move_to_workspace(workspace.name, false, Animation::None)
                         .await?;
-                    return Ok(());
+                    return Ok(None);
                 }
                 Command::SwitchNamedFocus { name, move_window } => {
                     if let Some(name) = state.named_focii.get(name) {
@@ -229,17 +892,14 @@ impl Command {
                     state
                         .move_to_workspace(&w, move_window, Animation::Fade)
                         .await?;
-                    return Ok(());
+                    return Ok(None);
                 }
             }
         }
 
         match self {
             Command::SwitchToWorkspace { name, move_window } => {
-                let (activity_index, workspace_index) =
-                    state.get_indices(&name).context("activity not found")?;
-                let workspace_index = workspace_index.context("workspace not found")?;
-                let new_workspace = &state.workspaces[activity_index][workspace_index];
+                let new_workspace = state.resolve_target(&name)?;
                 state
                     .move_to_workspace(new_workspace, move_window, Animation::Fade)
                     .await?;
@@ -247,10 +907,14 @@ impl Command {
             Command::SwitchToWorkspaceInActivity { name, move_window } => {
                 let workspace = Workspace::get_active_async().await?;
                 let activity_index = state
-                    .get_activity_index(&workspace.name)
+                    .workspace_activity_index(&workspace.name)
                     .context("could not get current activity")?;
                 let activity = &state.activities[activity_index];
                 let new_workspace = format!("{activity}:{name}");
+                // cells are named differently with flat_grid
+                let new_workspace = WorkspaceName::parse(&new_workspace, state.config.flat_grid)
+                    .map(|w| w.to_string())
+                    .unwrap_or(new_workspace);
                 state
                     .move_to_workspace(&new_workspace, move_window, Animation::Fade)
                     .await?;
@@ -262,49 +926,32 @@ impl Command {
                 let workspace = Workspace::get_active_async().await?;
                 if state.get_activity_index(&name).is_none() {
                     state.activities.push(name.clone());
-                    let w = state.workspaces[0]
-                        .iter()
-                        .flat_map(|w| w.split(':').skip(1))
-                        .map(|w| format!("{}:{}", &name, w))
-                        .collect();
-                    state.workspaces.push(w);
-                }
-                if let Some(activity_index) = state.get_activity_index(&workspace.name) {
-                    let activity = &state.activities[activity_index];
-                    let id = workspace
-                        .name
-                        .strip_prefix(activity)
-                        .expect("just checked this");
-                    name.push_str(id);
-                } else {
-                    name.push_str("(1 1)");
+                }
+                name = match state
+                    .workspace_activity_index(&workspace.name)
+                    .and_then(|_| WorkspaceName::parse(&workspace.name, state.config.flat_grid))
+                {
+                    Some(w) => w.in_activity(name).to_string(),
+                    None => cell_workspace_name(&name, 1, 1, state.config.flat_grid),
                 };
                 state
                     .move_to_workspace(&name, move_window, Animation::Fade)
                     .await?;
             }
-            Command::NextActivity { cycle, move_window } => {
+            Command::NextActivity {
+                cycle, move_window, ..
+            } => {
                 let workspace = Workspace::get_active_async().await?;
-                let activity_index = state.get_activity_index(&workspace.name);
-                let new_activity_index = activity_index
-                    .map(|i| {
-                        let mut i = i;
-                        let n = state.activities.len();
-                        if cycle {
-                            i = (i + 1) % n;
-                        } else {
-                            i = (i + 1).min(n - 1);
-                        }
-                        i
-                    })
-                    .unwrap_or(0);
-                let id =
-                    activity_index.and_then(|i| workspace.name.strip_prefix(&state.activities[i]));
-                let mut name = state.activities[new_activity_index].clone();
-                if let Some(id) = id {
-                    name.push_str(id);
-                } else {
-                    name = state.workspaces[new_activity_index][0].clone();
+                let activity_index = state.workspace_activity_index(&workspace.name);
+                let new_activity_index =
+                    state.stepped_activity(activity_index, &workspace.monitor, 1, cycle);
+                let cell = activity_index
+                    .and_then(|_| WorkspaceName::parse(&workspace.name, state.config.flat_grid));
+                let name = match cell {
+                    Some(w) => w
+                        .in_activity(&state.activities[new_activity_index])
+                        .to_string(),
+                    None => state.workspace_name(new_activity_index, 0),
                 };
                 state
                     .move_to_workspace(&name, move_window, Animation::Fade)
@@ -312,54 +959,73 @@ impl Command {
             }
             Command::PrevActivity { cycle, move_window } => {
                 let workspace = Workspace::get_active_async().await?;
-                let activity_index = state.get_activity_index(&workspace.name);
-                let new_activity_index = activity_index
-                    .map(|i| {
-                        let mut i = i as isize;
-                        let n = state.activities.len();
-                        if cycle {
-                            i = (n as isize + i - 1) % n as isize;
-                        } else {
-                            i = (i - 1).max(0);
-                        }
-                        i as usize
-                    })
-                    .unwrap_or(0);
-                let id =
-                    activity_index.and_then(|i| workspace.name.strip_prefix(&state.activities[i]));
-                let activity_index = new_activity_index;
-                let mut name = state.activities[activity_index].clone();
-                if let Some(id) = id {
-                    name.push_str(id);
-                } else {
-                    name = state.workspaces[activity_index][0].clone();
+                let activity_index = state.workspace_activity_index(&workspace.name);
+                let new_activity_index =
+                    state.stepped_activity(activity_index, &workspace.monitor, -1, cycle);
+                let cell = activity_index
+                    .and_then(|_| WorkspaceName::parse(&workspace.name, state.config.flat_grid));
+                let name = match cell {
+                    Some(w) => w
+                        .in_activity(&state.activities[new_activity_index])
+                        .to_string(),
+                    None => state.workspace_name(new_activity_index, 0),
                 };
                 state
                     .move_to_workspace(&name, move_window, Animation::Fade)
                     .await?;
             }
-            Command::MoveRight { cycle, move_window } => {
-                let workspace = state.moved_workspace(1, 0, cycle).await?;
+            Command::MoveRight {
+                cycle,
+                move_window,
+                through_monitors,
+            } => {
                 state
-                    .move_to_workspace(workspace, move_window, Animation::Right)
+                    .move_in_direction(
+                        (1, 0),
+                        cycle,
+                        move_window,
+                        through_monitors,
+                        Animation::Right,
+                    )
                     .await?;
             }
-            Command::MoveLeft { cycle, move_window } => {
-                let workspace = state.moved_workspace(-1, 0, cycle).await?;
+            Command::MoveLeft {
+                cycle,
+                move_window,
+                through_monitors,
+            } => {
                 state
-                    .move_to_workspace(workspace, move_window, Animation::Left)
+                    .move_in_direction(
+                        (-1, 0),
+                        cycle,
+                        move_window,
+                        through_monitors,
+                        Animation::Left,
+                    )
                     .await?;
             }
-            Command::MoveUp { cycle, move_window } => {
-                let workspace = state.moved_workspace(0, -1, cycle).await?;
+            Command::MoveUp {
+                cycle,
+                move_window,
+                through_monitors,
+            } => {
                 state
-                    .move_to_workspace(workspace, move_window, Animation::Up)
+                    .move_in_direction((0, -1), cycle, move_window, through_monitors, Animation::Up)
                     .await?;
             }
-            Command::MoveDown { cycle, move_window } => {
-                let workspace = state.moved_workspace(0, 1, cycle).await?;
+            Command::MoveDown {
+                cycle,
+                move_window,
+                through_monitors,
+            } => {
                 state
-                    .move_to_workspace(workspace, move_window, Animation::Down)
+                    .move_in_direction(
+                        (0, 1),
+                        cycle,
+                        move_window,
+                        through_monitors,
+                        Animation::Down,
+                    )
                     .await?;
             }
             Command::ToggleSpecialWorkspace {
@@ -371,7 +1037,7 @@ impl Command {
                     state
                         .toggle_special_workspace(name, Animation::Fade)
                         .await?;
-                    return Ok(());
+                    return Ok(None);
                 }
                 let window = Client::get_active_async()
                     .await?
@@ -410,31 +1076,351 @@ impl Command {
                     }
                 };
             }
-            Command::FocusWindow { address } => {
+            Command::Exec {
+                workspace,
+                silent,
+                timeout,
+                token,
+                ..
+            } => {
+                let target = state.resolve_target(&workspace)?;
+                match token {
+                    Some(token) if in_daemon => {
+                        state
+                            .spawns
+                            .expect(token, target.clone(), Duration::from_secs(timeout));
+                    }
+                    _ if silent => {
+                        return Err(anyhow!("exec --silent needs the daemon"));
+                    }
+                    // the app opens on the focused workspace
+                    _ => {}
+                }
+                if !silent {
+                    state
+                        .move_to_workspace(target, false, Animation::Fade)
+                        .await?;
+                }
+            }
+            Command::GotoEmpty { move_window } => {
+                let workspace = state.current_cell().await?;
+                let Some((activity, Some(current))) = state.get_indices(&workspace) else {
+                    return Err(anyhow!("not on a hyprkool workspace"));
+                };
+                let occupied = Workspaces::get_async()
+                    .await?
+                    .into_iter()
+                    .filter(|w| w.windows > 0)
+                    .map(|w| w.name)
+                    .collect::<HashSet<_>>();
+                let target = nearest_empty(current, state.config.workspaces, |i| {
+                    occupied.contains(&state.workspace_name(activity, i))
+                })
+                .context("no empty workspace in this activity")?;
+                let target = state.workspace_name(activity, target);
+                state
+                    .move_to_workspace(target, move_window, Animation::Fade)
+                    .await?;
+            }
+            Command::CycleWindows { prev } => {
+                let workspace = state.current_cell().await?;
+                let activity = state
+                    .workspace_activity_index(&workspace)
+                    .context("not on a hyprkool workspace")?;
+                // stable order (cells in row major order, then windows top left first)
+                // so that repeated cycling visits every window
+                let mut windows = Clients::get_async()
+                    .await?
+                    .into_iter()
+                    .filter(|w| w.mapped)
+                    .filter_map(|w| match state.get_indices(&w.workspace.name) {
+                        Some((a, Some(i))) if a == activity => Some((i, w)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                windows.sort_by_key(|(i, w)| (*i, w.at.1, w.at.0));
+                if windows.is_empty() {
+                    return Ok(None);
+                }
+                let active = Client::get_active_async().await?.map(|w| w.address);
+                let current = windows
+                    .iter()
+                    .position(|(_, w)| Some(&w.address) == active.as_ref());
+                let n = windows.len();
+                let i = match (current, prev) {
+                    (Some(i), false) => (i + 1) % n,
+                    (Some(i), true) => (i + n - 1) % n,
+                    (None, false) => 0,
+                    (None, true) => n - 1,
+                };
+                let w = &windows[i].1;
+                let mut then = DispatchBatch::new();
+                then.focus_window(w.address.to_string());
+                if w.workspace.name == workspace {
+                    // not through move_to_workspace_then so back_and_forth does not kick in
+                    then.send().await?;
+                } else {
+                    state
+                        .move_to_workspace_then(&w.workspace.name, false, Animation::Fade, then)
+                        .await?;
+                }
+            }
+            Command::FocusWindow {
+                address: Some(address),
+                ..
+            } => {
                 let windows = Clients::get_async().await?;
                 let cursor = CursorPosition::get_async().await?;
                 for w in windows {
                     if w.address.to_string() == address {
-                        Dispatch::call_async(DispatchType::FocusWindow(WindowIdentifier::Address(
-                            w.address,
-                        )))
-                        .await?;
-                        Dispatch::call_async(DispatchType::MoveCursor(cursor.x, cursor.y)).await?;
+                        DispatchBatch::new()
+                            .focus_window(&address)
+                            .move_cursor(cursor.x, cursor.y)
+                            .send()
+                            .await?;
                         break;
                     }
                 }
             }
+            Command::FocusWindow {
+                class: Some(class), ..
+            } => {
+                let re =
+                    Regex::new(&class).with_context(|| format!("invalid regex '{}'", class))?;
+                let candidates = Clients::get_async()
+                    .await?
+                    .into_iter()
+                    .filter(|w| re.is_match(&w.class))
+                    .filter(|w| matches!(state.get_indices(&w.workspace.name), Some((_, Some(_)))))
+                    .collect::<Vec<_>>();
+                match &candidates[..] {
+                    [] => {
+                        return Err(anyhow!(
+                            "no window on a hyprkool workspace matches '{}'",
+                            class
+                        ));
+                    }
+                    [w] => {
+                        let mut then = DispatchBatch::new();
+                        then.focus_window(w.address.to_string());
+                        state
+                            .move_to_workspace_then(&w.workspace.name, false, Animation::Fade, then)
+                            .await?;
+                    }
+                    candidates => {
+                        let candidates = candidates
+                            .iter()
+                            .map(|w| WindowCandidate {
+                                address: w.address.to_string(),
+                                class: w.class.clone(),
+                                title: w.title.clone(),
+                                workspace: w.workspace.name.clone(),
+                            })
+                            .collect::<Vec<_>>();
+                        return Ok(Some(serde_json::to_string(&candidates)?));
+                    }
+                }
+            }
             Command::SwitchNamedFocus { name, move_window } => {
-                if let Some(nf) = state.named_focii.get(&name) {
+                if let Some(nf) = state.named_focii.get(&name).cloned() {
                     state
                         .move_to_workspace(nf, move_window, Animation::Fade)
                         .await?;
                 }
             }
-            Command::SetNamedFocus { .. } => {
+            Command::SetNamedFocus { .. }
+            | Command::PairActivities { .. }
+            | Command::UnpairActivities
+            | Command::TogglePin
+            | Command::ToggleEdgeSwitching
+            | Command::Idle
+            | Command::Wake
+            | Command::Lock
+            | Command::Unlock
+            | Command::Mark { .. }
+            | Command::Stats { .. }
+            | Command::SwitchBack { .. }
+            | Command::Grid { .. }
+            | Command::Label { .. }
+            | Command::Alias { .. }
+            | Command::Focus { .. }
+            | Command::Keywords { .. }
+            | Command::History { .. } => {
                 println!("ERROR: please use hyprkool daemon for this feature");
             }
-            Command::ToggleOverview => {
+            Command::List { json } => {
+                let listing = list(state).await?;
+                if json {
+                    return Ok(Some(serde_json::to_string(&listing)?));
+                }
+                let mut lines = Vec::new();
+                for a in &listing.activities {
+                    let marker = if a.focused { "*" } else { " " };
+                    lines.push(format!("{} {} {} windows", &a.name, marker, a.windows));
+                }
+                for a in &listing.activities {
+                    for w in &a.workspaces {
+                        let marker = if w.focused { "*" } else { " " };
+                        let label = w
+                            .label
+                            .as_ref()
+                            .map(|l| format!(" - {}", l))
+                            .unwrap_or_default();
+                        lines.push(format!(
+                            "{} {} {} windows{}",
+                            &w.name, marker, w.windows, label
+                        ));
+                    }
+                }
+                for (name, workspace) in &listing.named_focii {
+                    lines.push(format!("@{} -> {}", name, workspace));
+                }
+                return Ok(Some(lines.join("\n")));
+            }
+            Command::Activity {
+                command: ActivityCommand::Merge { src, dst, collapse },
+            } => {
+                let moved = state.merge_activity(&src, &dst, collapse).await?;
+                return Ok(Some(format!("moved {} windows", moved)));
+            }
+            Command::Swap { with } => {
+                let current = state.current_cell().await?;
+                let direction = match with.as_str() {
+                    "left" => Some((-1, 0)),
+                    "right" => Some((1, 0)),
+                    "up" => Some((0, -1)),
+                    "down" => Some((0, 1)),
+                    _ => None,
+                };
+                let other = match direction {
+                    Some((x, y)) => state.moved_workspace_from(&current, x, y, false)?,
+                    None => labeled_workspace(state, &current, Some(&with))?,
+                };
+                if other == current {
+                    return Err(anyhow!("no workspace {} of {}", with, current));
+                }
+                state.swap_workspaces(&current, &other).await?;
+            }
+            Command::Gc { json } => {
+                let moved = state.collect_stale_activities().await?;
+                if json {
+                    return Ok(Some(serde_json::to_string(&GcResult { moved })?));
+                }
+                return Ok(Some(format!("moved {} windows", moved)));
+            }
+            Command::MigrateNames { dry_run } => {
+                let workspaces = Workspaces::get_async().await?;
+                let mut taken = workspaces
+                    .iter()
+                    .map(|w| w.name.clone())
+                    .collect::<HashSet<_>>();
+                let mut batch = DispatchBatch::new();
+                let mut lines = Vec::new();
+                for w in workspaces.iter() {
+                    let Some(name) = state.migrated_name(&w.name) else {
+                        continue;
+                    };
+                    if !taken.insert(name.clone()) {
+                        lines.push(format!("{} -> {} (skipped, already exists)", w.name, name));
+                        continue;
+                    }
+                    batch.dispatch("renameworkspace", format!("{} {}", w.id, name));
+                    lines.push(format!("{} -> {}", w.name, name));
+                }
+                if lines.is_empty() {
+                    return Ok(Some("nothing to migrate".into()));
+                }
+                if !dry_run {
+                    batch.send().await?;
+                }
+                return Ok(Some(lines.join("\n")));
+            }
+            Command::Adopt { dry_run } => {
+                let workspaces = Workspaces::get_async().await?;
+                let taken = workspaces
+                    .iter()
+                    .map(|w| w.name.clone())
+                    .collect::<HashSet<_>>();
+                let mut numbered = workspaces
+                    .iter()
+                    .filter(|w| w.windows > 0)
+                    .filter_map(|w| w.name.parse::<u32>().ok().map(|n| (n, w)))
+                    .collect::<Vec<_>>();
+                numbered.sort_by_key(|(n, _)| *n);
+                let mut free = state.workspace_names(0).filter(|w| !taken.contains(w));
+
+                let mut batch = DispatchBatch::new();
+                let mut lines = Vec::new();
+                for (_, w) in numbered {
+                    let Some(name) = free.next() else {
+                        lines.push(format!("{} (skipped, no free cell left)", w.name));
+                        continue;
+                    };
+                    batch.dispatch("renameworkspace", format!("{} {}", w.id, name));
+                    lines.push(format!("{} -> {}", w.name, name));
+                }
+                if lines.is_empty() {
+                    return Ok(Some("no windows on numbered workspaces".into()));
+                }
+                if !dry_run {
+                    batch.send().await?;
+                }
+                return Ok(Some(lines.join("\n")));
+            }
+            Command::Ping { json } => {
+                let plugin = is_plugin_running().await.unwrap_or_default();
+                if json {
+                    let status = PingStatus {
+                        daemon: in_daemon,
+                        plugin,
+                    };
+                    return Ok(Some(serde_json::to_string(&status)?));
+                }
+                let running = |r| if r { "running" } else { "not running" };
+                return Ok(Some(format!(
+                    "daemon: {}\nplugin: {}",
+                    running(in_daemon),
+                    running(plugin)
+                )));
+            }
+            Command::ListActivities { json } => {
+                let listing = list(state).await?;
+                if json {
+                    let activities = listing
+                        .activities
+                        .iter()
+                        .map(|a| ActivitySummary {
+                            name: &a.name,
+                            icon: a.icon.as_deref(),
+                            display: a.display.as_deref(),
+                            focused: a.focused,
+                            windows: a.windows,
+                        })
+                        .collect::<Vec<_>>();
+                    return Ok(Some(serde_json::to_string(&activities)?));
+                }
+                let lines = listing
+                    .activities
+                    .iter()
+                    .map(|a| {
+                        let marker = if a.focused { "*" } else { " " };
+                        let label = [a.icon.as_deref(), a.display.as_deref()]
+                            .into_iter()
+                            .flatten()
+                            .collect::<Vec<_>>()
+                            .join(" ");
+                        match label.is_empty() {
+                            true => format!("{} {} ({} windows)", marker, &a.name, a.windows),
+                            false => format!(
+                                "{} {} - {} ({} windows)",
+                                marker, &a.name, label, a.windows
+                            ),
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                return Ok(Some(lines.join("\n")));
+            }
+            Command::ToggleOverview | Command::Overview { menu: None } => {
                 let workspace = Workspace::get_active_async().await?;
                 match workspace.name.strip_suffix(":overview") {
                     Some(name) => {
@@ -464,6 +1450,218 @@ impl Command {
             }
         }
 
-        Ok(())
+        Ok(None)
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct Listing {
+    activities: Vec<ActivityListing>,
+    /// named focus -> workspace
+    named_focii: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug)]
+struct ActivityListing {
+    name: String,
+    icon: Option<String>,
+    display: Option<String>,
+    focused: bool,
+    windows: u16,
+    workspaces: Vec<WorkspaceListing>,
+}
+
+/// gc --json
+#[derive(Serialize, Debug)]
+struct GcResult {
+    /// windows moved out of stale activities
+    moved: usize,
+}
+
+/// ping --json. whether they are running
+#[derive(Serialize, Debug)]
+struct PingStatus {
+    daemon: bool,
+    plugin: bool,
+}
+
+/// history list --json
+#[derive(Serialize, Debug)]
+struct HistoryListing<'a> {
+    workspaces: &'a [String],
+    /// index of the current workspace in workspaces
+    position: usize,
+}
+
+/// an activity in list-activities --json
+#[derive(Serialize, Debug)]
+struct ActivitySummary<'a> {
+    name: &'a str,
+    icon: Option<&'a str>,
+    display: Option<&'a str>,
+    focused: bool,
+    windows: u16,
+}
+
+#[derive(Serialize, Debug)]
+struct WorkspaceListing {
+    name: String,
+    focused: bool,
+    windows: u16,
+    named_focus: Vec<String>,
+    label: Option<String>,
+}
+
+async fn list(state: &State) -> Result<Listing> {
+    let active = Workspace::get_active_async().await?;
+    let windows = Workspaces::get_async()
+        .await?
+        .into_iter()
+        .map(|w| (w.name, w.windows))
+        .collect::<HashMap<_, _>>();
+    let activities = state
+        .activities
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let workspaces = state
+                .workspace_names(i)
+                .map(|w| WorkspaceListing {
+                    focused: w == active.name,
+                    windows: windows.get(&w).copied().unwrap_or(0),
+                    named_focus: state
+                        .named_focii
+                        .iter()
+                        .filter(|(_, nw)| **nw == w)
+                        .map(|(n, _)| n.clone())
+                        .collect(),
+                    label: state.labels.get(&w).cloned(),
+                    name: w,
+                })
+                .collect::<Vec<_>>();
+            let (icon, display) = state.activity_meta(name);
+            ActivityListing {
+                name: name.clone(),
+                icon,
+                display,
+                focused: workspaces.iter().any(|w| w.focused),
+                windows: workspaces.iter().map(|w| w.windows).sum(),
+                workspaces,
+            }
+        })
+        .collect();
+    Ok(Listing {
+        activities,
+        named_focii: state.named_focii.clone().into_iter().collect(),
+    })
+}
+
+/// workspace that a label or swap command is about. pos is x,y in the current activity or a target
+fn labeled_workspace(state: &State, current: &str, pos: Option<&str>) -> Result<String> {
+    match pos {
+        None => Ok(current.to_owned()),
+        Some(pos) if pos.contains(':') => state.resolve_target(pos),
+        Some(pos) => state.resolve_target(&format!(
+            "{}:{}",
+            activity_name(current, state.config.flat_grid),
+            pos
+        )),
+    }
+}
+
+/// parses a line printed by 'hyprkool list' into the command that switches to it
+pub fn picked_command(state: &State, line: &str) -> Result<Command> {
+    let Some(target) = line.split_whitespace().next() else {
+        return Err(anyhow!("nothing picked"));
+    };
+    if let Some(name) = target.strip_prefix('@') {
+        return Ok(Command::SwitchNamedFocus {
+            name: name.to_owned(),
+            move_window: false,
+        });
+    }
+    // workspace names contain a space. "activity:(x y)"
+    let Some(workspace) = (0..state.activities.len())
+        .flat_map(|i| state.workspace_names(i))
+        .find(|w| line.starts_with(w.as_str()))
+    else {
+        if state.get_activity_index(target).is_some() && !target.contains(':') {
+            return Ok(Command::SwitchToActivity {
+                name: target.to_owned(),
+                move_window: false,
+            });
+        }
+        return Err(anyhow!("unknown pick: {}", line));
+    };
+    Ok(Command::SwitchToWorkspace {
+        name: workspace,
+        move_window: false,
+    })
+}
+
+/// lets the user pick a workspace of the current activity using a dmenu like command
+pub async fn pick_workspace(state: &State, menu: &str) -> Result<Option<String>> {
+    let workspace = Workspace::get_active_async().await?;
+    let Some(activity_index) = state.workspace_activity_index(&workspace.name) else {
+        return Err(anyhow!("not on a hyprkool activity"));
+    };
+    let clients = Clients::get_async().await?;
+    let workspaces = state.workspace_names(activity_index).collect::<Vec<_>>();
+    let choices = workspaces
+        .iter()
+        .map(|w| {
+            let classes = clients
+                .iter()
+                .filter(|c| &c.workspace.name == w)
+                .map(|c| c.class.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let marker = if w == &workspace.name { "*" } else { " " };
+            format!(
+                "{} {} {}",
+                workspace_suffix(w)
+                    .unwrap_or_default()
+                    .trim_start_matches(':'),
+                marker,
+                classes
+            )
+        })
+        .collect::<Vec<_>>();
+    let Some(picked) = pick_with_menu(menu, &choices).await? else {
+        return Ok(None);
+    };
+    let Some(i) = choices.iter().position(|c| c == &picked) else {
+        return Err(anyhow!("menu returned an unknown choice: {}", picked));
+    };
+    Ok(Some(workspaces[i].clone()))
+}
+
+/// pipes choices through a dmenu like command. returns None if nothing was picked
+async fn pick_with_menu(menu: &str, choices: &[String]) -> Result<Option<String>> {
+    let mut child = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(menu)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("could not run menu command '{}'", menu))?;
+    let mut stdin = child.stdin.take().context("menu stdin not available")?;
+    stdin.write_all(choices.join("\n").as_bytes()).await?;
+    drop(stdin);
+    let out = child.wait_with_output().await?;
+    let picked = String::from_utf8_lossy(&out.stdout)
+        .trim_end_matches('\n')
+        .to_owned();
+    if picked.is_empty() {
+        return Ok(None);
     }
+    Ok(Some(picked))
+}
+
+#[derive(Serialize, Debug)]
+struct WindowCandidate {
+    address: String,
+    class: String,
+    title: String,
+    workspace: String,
 }
@@ -0,0 +1,60 @@
+use clap::{builder::PossibleValuesParser, CommandFactory, ValueEnum};
+use clap_complete::Shell;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::Cli, config::Config};
+
+#[derive(ValueEnum, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// completion script for the shell. activity names and named focii from the config are
+/// completed too
+pub fn generate(shell: CompletionShell, config: &Config) -> String {
+    let mut activities = config.activities.clone();
+    if activities.is_empty() {
+        activities.push("default".into());
+    }
+    let mut named_focii = config.named_focii.keys().cloned().collect::<Vec<_>>();
+    named_focii.sort();
+
+    let with_values = |values: &[String]| {
+        let parser = PossibleValuesParser::new(values.to_vec());
+        move |arg: clap::Arg| arg.value_parser(parser)
+    };
+    let mut cmd = Cli::command()
+        .mut_subcommand("switch-to-activity", |c| {
+            c.mut_arg("name", with_values(&activities))
+        })
+        .mut_subcommand("pair-activities", |c| {
+            c.mut_arg("a", with_values(&activities))
+                .mut_arg("b", with_values(&activities))
+        })
+        .mut_subcommand("activity", |c| {
+            c.mut_subcommand("merge", |c| {
+                c.mut_arg("src", with_values(&activities))
+                    .mut_arg("dst", with_values(&activities))
+            })
+        })
+        .mut_subcommand("alias", |c| {
+            c.mut_subcommand("remove", |c| c.mut_arg("name", with_values(&named_focii)))
+        })
+        .mut_subcommand("switch-named-focus", |c| {
+            c.mut_arg("name", with_values(&named_focii))
+        })
+        .mut_subcommand("set-named-focus", |c| {
+            c.mut_arg("name", with_values(&named_focii))
+        });
+
+    let shell = match shell {
+        CompletionShell::Bash => Shell::Bash,
+        CompletionShell::Zsh => Shell::Zsh,
+        CompletionShell::Fish => Shell::Fish,
+    };
+    let mut out = Vec::new();
+    clap_complete::generate(shell, &mut cmd, "hyprkool", &mut out);
+    String::from_utf8_lossy(&out).into_owned()
+}
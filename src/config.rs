@@ -1,7 +1,133 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::ErrorKind, path::Path};
 
+use anyhow::{anyhow, Context, Result};
 use serde::Deserialize;
 
+use crate::{
+    rules::{full_match_regex, new_activity_prefix},
+    target::Target,
+};
+
+/// sets (or removes if workspace is None) a named focus in the config file. the rest of the
+/// file is kept as is, comments included
+pub fn save_named_focus(path: &Path, name: &str, workspace: Option<&str>) -> Result<()> {
+    let mut doc = read_config_doc(path)?;
+    let named_focii = doc
+        .entry("named_focii")
+        .or_insert(toml_edit::table())
+        .as_table_like_mut()
+        .context("named_focii in the config is not a table")?;
+    match workspace {
+        Some(workspace) => {
+            named_focii.insert(name, toml_edit::value(workspace));
+        }
+        None => {
+            named_focii.remove(name);
+        }
+    }
+    std::fs::write(path, doc.to_string()).with_context(|| format!("could not write {:?}", path))
+}
+
+/// sets the grid size in the config file and rewrites the named_focii and rule targets that
+/// remap changes for it. the rest of the file is kept as is
+pub fn save_grid(
+    path: &Path,
+    (x, y): (u32, u32),
+    remap: impl Fn(&str) -> Option<String>,
+) -> Result<()> {
+    let mut doc = read_config_doc(path)?;
+    doc["workspaces"] = toml_edit::value(toml_edit::Array::from_iter([x as i64, y as i64]));
+    // comments next to the values stay where they were
+    let remap_value = |v: &mut toml_edit::Value| {
+        if let Some(target) = v.as_str().and_then(&remap) {
+            let decor = v.decor().clone();
+            *v = target.into();
+            *v.decor_mut() = decor;
+        }
+    };
+    if let Some(named_focii) = doc
+        .get_mut("named_focii")
+        .and_then(|n| n.as_table_like_mut())
+    {
+        for w in named_focii.iter_mut().filter_map(|(_, w)| w.as_value_mut()) {
+            remap_value(w);
+        }
+    }
+    match doc.get_mut("rules") {
+        Some(toml_edit::Item::ArrayOfTables(rules)) => {
+            let rules = rules.iter_mut().filter_map(|r| r.get_mut("workspace"));
+            for w in rules.filter_map(|w| w.as_value_mut()) {
+                remap_value(w);
+            }
+        }
+        Some(toml_edit::Item::Value(toml_edit::Value::Array(rules))) => {
+            let rules = rules.iter_mut().filter_map(|r| r.as_inline_table_mut());
+            for w in rules.filter_map(|r| r.get_mut("workspace")) {
+                remap_value(w);
+            }
+        }
+        _ => {}
+    }
+    std::fs::write(path, doc.to_string()).with_context(|| format!("could not write {:?}", path))
+}
+
+fn read_config_doc(path: &Path) -> Result<toml_edit::DocumentMut> {
+    let text = match std::fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e).with_context(|| format!("could not read {:?}", path)),
+    };
+    text.parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("could not parse {:?}", path))
+}
+
+/// values of the template variables used in templates. variables that none of them
+/// use are not looked up
+pub fn template_vars<'a>(
+    templates: impl IntoIterator<Item = &'a str>,
+) -> Result<Vec<(&'static str, String)>> {
+    let templates = templates.into_iter().collect::<Vec<_>>();
+    let used = |var: &str| {
+        let var = format!("{{{}}}", var);
+        templates.iter().any(|t| t.contains(&var))
+    };
+    let mut vars = Vec::new();
+    if used("hostname") {
+        let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+            .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+            .context("could not find hostname")?;
+        // only the first label of a fqdn, dots don't belong in activity names
+        let hostname = hostname.trim().split('.').next().unwrap_or_default();
+        vars.push(("hostname", hostname.to_owned()));
+    }
+    if used("user") {
+        vars.push(("user", std::env::var("USER").unwrap_or_default()));
+    }
+    if used("date") {
+        vars.push(("date", today()?));
+    }
+    Ok(vars)
+}
+
+/// the local date as yyyy-mm-dd
+fn today() -> Result<String> {
+    // SAFETY: time accepts null and localtime_r only writes to tm
+    let tm = unsafe {
+        let now = libc::time(std::ptr::null_mut());
+        let mut tm = std::mem::zeroed::<libc::tm>();
+        if libc::localtime_r(&now, &mut tm).is_null() {
+            return Err(anyhow!("could not get the date"));
+        }
+        tm
+    };
+    Ok(format!(
+        "{:04}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday
+    ))
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(default, deny_unknown_fields)]
 pub struct DaemonConfig {
@@ -15,6 +141,35 @@ pub struct DaemonConfig {
     pub remember_activity_focus: bool,
 
     pub mouse: MouseConfig,
+
+    /// touchpad swipes
+    pub gestures: GesturesConfig,
+
+    /// command to run after every workspace/activity switch
+    pub on_switch_feedback: SwitchFeedback,
+
+    /// command that can cancel switches
+    pub pre_switch: PreSwitchHook,
+
+    /// stop processes of activities that have not been used for a while
+    pub freeze: FreezeConfig,
+
+    pub session: SessionConfig,
+
+    /// dim windows of activities that are not focused (visible in overviews)
+    pub dim: DimConfig,
+
+    /// briefly show the grid after every switch
+    pub minimap: MinimapConfig,
+
+    /// desktop notification with the activity and cell after every switch
+    pub switch_notification: SwitchNotificationConfig,
+
+    /// command that sets the wallpaper of activities
+    pub wallpaper: WallpaperConfig,
+
+    /// commands for focus sessions
+    pub focus: FocusConfig,
 }
 impl Default for DaemonConfig {
     fn default() -> Self {
@@ -22,6 +177,209 @@ impl Default for DaemonConfig {
             remember_activity_focus: true,
             fallback_commands: true,
             mouse: Default::default(),
+            gestures: Default::default(),
+            on_switch_feedback: Default::default(),
+            pre_switch: Default::default(),
+            freeze: Default::default(),
+            session: Default::default(),
+            dim: Default::default(),
+            minimap: Default::default(),
+            switch_notification: Default::default(),
+            wallpaper: Default::default(),
+            focus: Default::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct FocusConfig {
+    /// command and its arguments that turns on do not disturb for 'focus start --dnd'
+    pub dnd_on: Vec<String>,
+    /// turns it off again when the session ends
+    pub dnd_off: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SessionConfig {
+    /// save the session every minute and when the daemon exits
+    pub autosave: bool,
+    /// move windows back to their saved workspaces as they open after the daemon starts
+    pub autorestore: bool,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DimConfig {
+    pub enable: bool,
+    /// opacity of dimmed windows
+    pub opacity: f32,
+    /// hyprland tag given to dimmed windows
+    pub tag: String,
+}
+impl Default for DimConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            opacity: 0.6,
+            tag: "hyprkool-dim".into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct MinimapConfig {
+    /// command that shows the grid. the rendered grid is passed as the last argument.
+    /// empty disables the minimap
+    pub command: Vec<String>,
+    /// ms after which the command is killed if it is still running
+    pub timeout: u64,
+    /// passed to the command in $HYPRKOOL_MINIMAP_POSITION
+    pub position: MinimapPosition,
+    /// how the grid is drawn. same options as [status]
+    pub theme: StatusConfig,
+}
+impl Default for MinimapConfig {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            timeout: 800,
+            position: Default::default(),
+            theme: Default::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum MinimapPosition {
+    TopLeft,
+    Top,
+    TopRight,
+    #[default]
+    Center,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+impl MinimapPosition {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TopLeft => "top-left",
+            Self::Top => "top",
+            Self::TopRight => "top-right",
+            Self::Center => "center",
+            Self::BottomLeft => "bottom-left",
+            Self::Bottom => "bottom",
+            Self::BottomRight => "bottom-right",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct FreezeConfig {
+    pub enable: bool,
+    /// minutes an activity has to be unfocused before it is frozen
+    pub after: u64,
+    /// window classes that are never frozen
+    pub blocklist: Vec<String>,
+}
+impl Default for FreezeConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            after: 10,
+            blocklist: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwitchFeedback {
+    /// command and its arguments. empty disables feedback
+    pub command: Vec<String>,
+    /// extra arguments appended to the command depending on the switch direction
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub up: Vec<String>,
+    pub down: Vec<String>,
+    /// switches without a direction (activity switches, jumps)
+    pub other: Vec<String>,
+    /// minimum time between two feedback commands in ms
+    pub min_interval: u64,
+}
+impl Default for SwitchFeedback {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            left: Vec::new(),
+            right: Vec::new(),
+            up: Vec::new(),
+            down: Vec::new(),
+            other: Vec::new(),
+            min_interval: 100,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwitchNotificationConfig {
+    pub enable: bool,
+    /// ms the notification is shown for
+    pub timeout: u32,
+    /// minimum time between two notifications in ms
+    pub min_interval: u64,
+    /// commands (like "move-left") whose switches are not notified. "edge" for screen edges
+    pub skip: Vec<String>,
+}
+impl Default for SwitchNotificationConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            timeout: 1000,
+            min_interval: 100,
+            skip: Vec::new(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WallpaperConfig {
+    /// command and its arguments. {wallpaper} is replaced with the activity's wallpaper.
+    /// empty disables it
+    pub command: Vec<String>,
+    /// ms to wait for more activity switches before setting the wallpaper
+    pub debounce_ms: u64,
+}
+impl Default for WallpaperConfig {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            debounce_ms: 200,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreSwitchHook {
+    /// command and its arguments. it gets HYPRKOOL_FROM and HYPRKOOL_TO workspace names in
+    /// its environment and a non zero exit cancels the switch. empty disables the hook
+    pub command: Vec<String>,
+    /// switch anyway if the command takes longer than this in ms
+    pub timeout: u64,
+}
+impl Default for PreSwitchHook {
+    fn default() -> Self {
+        Self {
+            command: Vec::new(),
+            timeout: 200,
         }
     }
 }
@@ -34,8 +392,30 @@ pub struct MouseConfig {
     pub polling_rate: u64,
     /// number of pixels to consider as edge
     pub edge_width: u64,
+    /// edge_width of single edges
+    pub edge_widths: EdgeWidths,
     /// push cursor inside margin when it loops
     pub edge_margin: u64,
+    /// edge_margin for the left and right edges
+    pub edge_margin_x: Option<u64>,
+    /// edge_margin for the top and bottom edges
+    pub edge_margin_y: Option<u64>,
+    /// animation to use when cursor hits a corner and both x and y change
+    pub diagonal_animation: DiagonalAnimation,
+    /// what to do when the cursor touches each edge
+    pub edges: EdgeActions,
+    /// what to do when the cursor touches an edge that another monitor is next to
+    pub shared_edges: SharedEdges,
+    /// edges that trigger anything at all
+    pub enabled_edges: EnabledEdges,
+    /// how long the cursor has to stay at an edge before it triggers in ms
+    pub edge_dwell_ms: u64,
+    /// edges do nothing while the focused window is fullscreen
+    pub ignore_fullscreen: bool,
+    /// edges do nothing while a window of one of these classes is focused
+    pub ignore_classes: Vec<String>,
+    /// carry a window that is being dragged to the next workspace
+    pub drag_windows: bool,
 }
 impl Default for MouseConfig {
     fn default() -> Self {
@@ -43,27 +423,694 @@ impl Default for MouseConfig {
             switch_workspace_on_edge: true,
             polling_rate: 300,
             edge_width: 0,
+            edge_widths: Default::default(),
             edge_margin: 2,
+            edge_margin_x: None,
+            edge_margin_y: None,
+            diagonal_animation: Default::default(),
+            edges: Default::default(),
+            shared_edges: Default::default(),
+            enabled_edges: Default::default(),
+            edge_dwell_ms: 0,
+            ignore_fullscreen: true,
+            ignore_classes: Vec::new(),
+            drag_windows: false,
         }
     }
 }
 
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct GesturesConfig {
+    pub enable: bool,
+    /// swipes with this many fingers move in the grid
+    pub grid_fingers: u32,
+    /// swipes with this many fingers switch activities
+    pub activity_fingers: u32,
+    /// how far the fingers have to move for a swipe to switch, in libinput's units
+    pub distance: f64,
+    /// the fingers drag the grid along, so swiping left goes right
+    pub natural: bool,
+    /// wrap around at the edges of the grid and the ends of the activity list
+    pub cycle: bool,
+    /// command and its arguments that prints libinput events
+    pub command: Vec<String>,
+}
+impl Default for GesturesConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            grid_fingers: 3,
+            activity_fingers: 4,
+            distance: 100.0,
+            natural: true,
+            cycle: false,
+            command: vec!["libinput".into(), "debug-events".into()],
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EdgeActions {
+    pub left: EdgeAction,
+    pub right: EdgeAction,
+    pub top: EdgeAction,
+    pub bottom: EdgeAction,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct EdgeWidths {
+    pub left: Option<u64>,
+    pub right: Option<u64>,
+    pub top: Option<u64>,
+    pub bottom: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnabledEdges {
+    pub left: bool,
+    pub right: bool,
+    pub top: bool,
+    pub bottom: bool,
+}
+impl Default for EnabledEdges {
+    fn default() -> Self {
+        Self {
+            left: true,
+            right: true,
+            top: true,
+            bottom: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct SharedEdges {
+    pub left: SharedEdge,
+    pub right: SharedEdge,
+    pub top: SharedEdge,
+    pub bottom: SharedEdge,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SharedEdge {
+    /// let the cursor move to the other monitor
+    #[default]
+    CrossMonitor,
+    /// treat it like an outer edge
+    SwitchWorkspace,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum EdgeAction {
+    /// "grid" moves to the next workspace in that direction. "none" does nothing
+    Builtin(BuiltinEdgeAction),
+    /// hyprkool cli arguments. eg. ["next-activity", "-c"]
+    Command(Vec<String>),
+}
+impl Default for EdgeAction {
+    fn default() -> Self {
+        Self::Builtin(BuiltinEdgeAction::Grid)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StatusStyle {
+    /// draw the grid with the glyphs
+    #[default]
+    Grid,
+    /// describe the focused cell in words, for screen readers and braille displays.
+    /// "Activity work, row 2 of 3, column 1 of 3, 4 windows"
+    Text,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuiltinEdgeAction {
+    Grid,
+    None,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagonalAnimation {
+    #[default]
+    Fade,
+    None,
+    /// slide in the horizontal direction
+    Horizontal,
+    /// slide in the vertical direction
+    Vertical,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExternalWorkspaces {
+    /// fail
+    #[default]
+    Error,
+    /// act as if on the last focused hyprkool cell (needs daemon. the first cell without it)
+    LastCell,
+    /// rename the workspace to an empty cell next to the last focused one, keeping its
+    /// windows. empty workspaces are handled like last-cell
+    Adopt,
+}
+
 #[derive(Deserialize, Debug, Clone)]
 #[serde(default, deny_unknown_fields)]
 pub struct Config {
     pub activities: Vec<String>,
     /// number of workspaces in x and y dimensions
     pub workspaces: (u32, u32),
+    /// mirror the grid horizontally. (1 1) is the right most column (for rtl layouts)
+    pub invert_horizontal: bool,
+    /// switching to the focused workspace goes back to the previous one instead (needs daemon)
+    pub back_and_forth: bool,
+    /// show failed commands as desktop notifications
+    pub notify_errors: bool,
     pub named_focii: HashMap<String, String>,
+    /// window classes that mark the workspaces they are on as pinned in info commands
+    pub pinned_apps: Vec<String>,
+    /// move newly opened windows to activities/workspaces (needs daemon)
+    pub rules: Vec<WindowRule>,
+    /// per activity settings
+    pub activity: HashMap<String, ActivityConfig>,
+    /// no activities. workspaces are named "(x y)". needs activities to be empty
+    pub flat_grid: bool,
+    /// what grid commands do on workspaces that are not hyprkool cells (like hyprland's "1")
+    pub external_workspaces: ExternalWorkspaces,
+    /// monitor name -> activities that stay on it. next/prev activity on a monitor only go
+    /// through its activities
+    pub monitor_rules: HashMap<String, Vec<String>>,
+    /// named sets of hyprland keywords that activities can share
+    pub keyword_profiles: HashMap<String, HashMap<String, toml::Value>>,
+    /// how waybar-activity-status renders the grid
+    pub status: StatusConfig,
+    /// how each kind of switch is animated
+    pub animation: AnimationConfig,
     pub daemon: DaemonConfig,
+    /// shell commands run on switches (needs daemon)
+    pub hooks: HooksConfig,
+
+    /// (template, resolved name) of activities that use {hostname}, {user} or {date}
+    #[serde(skip)]
+    pub activity_templates: Vec<(String, String)>,
 }
 impl Default for Config {
     fn default() -> Self {
         Self {
             activities: vec!["default".into()],
             workspaces: (2, 2),
+            invert_horizontal: false,
+            back_and_forth: false,
+            notify_errors: false,
             named_focii: Default::default(),
+            pinned_apps: Default::default(),
+            rules: Default::default(),
+            activity: Default::default(),
+            flat_grid: false,
+            external_workspaces: Default::default(),
+            monitor_rules: Default::default(),
+            keyword_profiles: Default::default(),
+            status: Default::default(),
+            animation: Default::default(),
             daemon: Default::default(),
+            hooks: Default::default(),
+            activity_templates: Default::default(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    pub on_workspace_change: Option<String>,
+    pub on_activity_change: Option<String>,
+    /// run when entering the activity. keys are activity names
+    pub on_enter: HashMap<String, String>,
+    /// run when leaving the activity. keys are activity names
+    pub on_leave: HashMap<String, String>,
+    /// hooks still running after this many ms are killed
+    pub timeout_ms: u64,
+    /// hooks are skipped while this many are still running
+    pub max_running: usize,
+    /// run hooks with only a few basic environment variables (PATH, HOME, wayland/hyprland/dbus ones)
+    pub clean_env: bool,
+}
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            on_workspace_change: None,
+            on_activity_change: None,
+            on_enter: HashMap::new(),
+            on_leave: HashMap::new(),
+            timeout_ms: 5000,
+            max_running: 8,
+            clean_env: false,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnimationConfig {
+    /// move-left/right and the left/right screen edges
+    pub horizontal: SwitchAnimation,
+    /// move-up/down and the top/bottom screen edges
+    pub vertical: SwitchAnimation,
+    /// screen corners
+    pub diagonal: SwitchAnimation,
+    /// activity switches and jumps to any other workspace
+    pub activity: SwitchAnimation,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SwitchAnimation {
+    /// hyprland workspace animation style or "none". hyprkool's own if unset
+    pub style: Option<String>,
+    /// bezier defined in hyprland.conf
+    pub curve: Option<String>,
+    /// in ds, like hyprland's animation speed
+    pub duration: Option<f32>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusConfig {
+    pub style: StatusStyle,
+    /// the focused cell
+    pub active: String,
+    /// cells with windows
+    pub occupied: String,
+    /// cells without windows
+    pub empty: String,
+    pub cell_separator: String,
+    pub row_separator: String,
+    /// render all rows in one line, separated by inline_row_separator (for horizontal bars)
+    pub single_line: bool,
+    pub inline_row_separator: String,
+}
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            style: StatusStyle::Grid,
+            active: "   ".into(),
+            occupied: "███".into(),
+            empty: "███".into(),
+            cell_separator: " ".into(),
+            row_separator: "\n".into(),
+            single_line: false,
+            inline_row_separator: "  ".into(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ActivityConfig {
+    /// shown by bars instead of the name. also accepted by switch-to-activity
+    pub icon: Option<String>,
+    /// human readable name. also accepted by switch-to-activity
+    pub display: Option<String>,
+    /// can windows opening in this activity switch to it while another activity is focused (needs daemon)
+    pub focus_steal: FocusSteal,
+    /// hyprland keywords set while this activity is focused and restored when leaving it (needs daemon).
+    /// eg. "input:natural_scroll" = true
+    pub keywords: HashMap<String, toml::Value>,
+    /// keyword profile applied along with keywords. keywords win over the profile
+    pub profile: Option<String>,
+    /// wallpaper set by daemon.wallpaper.command while this activity is focused (needs daemon)
+    pub wallpaper: Option<String>,
+    /// hyprland layout used in this activity (needs daemon)
+    pub layout: Option<Layout>,
+    /// layouts of single workspaces. keys are cells like "x,y", "(x y)" or "n"
+    pub cell_layouts: HashMap<String, Layout>,
+}
+
+impl ActivityConfig {
+    /// keyword values (including the profile's) as hyprland expects them
+    pub fn keyword_values(
+        &self,
+        profiles: &HashMap<String, HashMap<String, toml::Value>>,
+    ) -> HashMap<String, String> {
+        let profile = self.profile.as_ref().and_then(|p| profiles.get(p));
+        profile
+            .into_iter()
+            .flatten()
+            .chain(self.keywords.iter())
+            .map(|(k, v)| {
+                let v = match v {
+                    toml::Value::String(s) => s.clone(),
+                    v => v.to_string(),
+                };
+                (k.clone(), v)
+            })
+            .collect()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Layout {
+    Dwindle,
+    Master,
+}
+impl Layout {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dwindle => "dwindle",
+            Self::Master => "master",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusSteal {
+    #[default]
+    Allow,
+    Deny,
+    /// only windows that ask for attention (urgent) can steal focus
+    UrgentOnly,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WindowRule {
+    /// when multiple rules match, the one with highest priority wins.
+    /// ties are won by the rule defined first
+    #[serde(default)]
+    pub priority: i32,
+    /// regex that must match the entire window class
+    pub class: Option<String>,
+    /// regex that must match the entire window title
+    pub title: Option<String>,
+    /// workspace target. activity, activity:(x y), activity:x,y or activity:n.
+    /// "new-activity:<prefix>" opens every window in a new activity named <prefix>-N
+    pub workspace: String,
+}
+
+impl Config {
+    /// resolves template variables in activity names and in everything that refers to them
+    pub fn resolve_templates(&mut self) -> Result<()> {
+        if !self.activities.iter().any(|a| a.contains('{')) {
+            return Ok(());
+        }
+        let vars = template_vars(self.activities.iter().map(|a| a.as_str()))?;
+        let resolve = |s: &str| {
+            vars.iter().fold(s.to_owned(), |s, (k, v)| {
+                s.replace(&format!("{{{}}}", k), v)
+            })
+        };
+
+        self.activity_templates = self
+            .activities
+            .iter()
+            .filter(|a| a.contains('{'))
+            .map(|a| (a.clone(), resolve(a)))
+            .collect();
+        self.activities = self.activities.iter().map(|a| resolve(a)).collect();
+        for w in self.named_focii.values_mut() {
+            *w = resolve(w);
+        }
+        for r in self.rules.iter_mut() {
+            r.workspace = resolve(&r.workspace);
+        }
+        self.activity = std::mem::take(&mut self.activity)
+            .into_iter()
+            .map(|(k, v)| (resolve(&k), v))
+            .collect();
+        Ok(())
+    }
+
+    /// checks for configs that would otherwise cause panics or silent misbehaviour
+    pub fn validate(&self) -> Result<()> {
+        let mut errors = Vec::new();
+
+        match self.workspaces {
+            (0, _) | (_, 0) => {
+                errors.push("Use non zero workspace grid dimentions in config".to_owned());
+            }
+            _ => (),
+        }
+        if self.daemon.mouse.polling_rate == 0 {
+            errors.push("daemon.mouse.polling_rate must be non zero".to_owned());
+        }
+        let gestures = &self.daemon.gestures;
+        if gestures.enable {
+            if gestures.grid_fingers == gestures.activity_fingers {
+                errors.push(
+                    "daemon.gestures.grid_fingers and activity_fingers must be different"
+                        .to_owned(),
+                );
+            }
+            if gestures.distance <= 0.0 {
+                errors.push("daemon.gestures.distance must be more than 0".to_owned());
+            }
+            if gestures.command.is_empty() {
+                errors.push("daemon.gestures.command can not be empty".to_owned());
+            }
+        }
+
+        for (i, a) in self.activities.iter().enumerate() {
+            if a.is_empty() {
+                errors.push("Activity names can not be empty".to_owned());
+            }
+            if let Some(c) = a
+                .chars()
+                .find(|&c| !c.is_alphanumeric() && !"-_".contains(c))
+            {
+                errors.push(format!(
+                    "Activity names can only contain a-z A-Z 0-9 - and _ characters. char '{}' in '{}' is not allowed",
+                    c, a,
+                ));
+            }
+            for b in self.activities.iter().skip(i + 1) {
+                if a == b {
+                    errors.push(format!("Activity '{}' is defined more than once", a));
+                }
+            }
+        }
+
+        // State falls back to a "default" activity if none are configured
+        let default_activity = ["default".to_owned()];
+        let activities = match self.activities.is_empty() {
+            true => &default_activity[..],
+            false => &self.activities[..],
+        };
+        let check_target = |target: &str| -> Result<()> {
+            let t = Target::parse(target, self.flat_grid)?;
+            let known = activities.iter().any(|a| a == t.activity)
+                || self.activity.values().any(|a| {
+                    a.icon.as_deref() == Some(t.activity)
+                        || a.display.as_deref() == Some(t.activity)
+                });
+            if !known {
+                return Err(anyhow!("'{}' is not in any activity", target));
+            }
+            t.cell_index(self.workspaces)?;
+            Ok(())
+        };
+        for (name, workspace) in self.named_focii.iter() {
+            if let Err(e) = check_target(workspace) {
+                errors.push(format!("named focus '{}': {}", name, e));
+            }
+        }
+
+        for rule in self.rules.iter() {
+            for re in rule.class.iter().chain(rule.title.iter()) {
+                if let Err(e) = full_match_regex(re) {
+                    errors.push(format!("{:#}", e));
+                }
+            }
+            if let Some(prefix) = new_activity_prefix(&rule.workspace) {
+                if prefix.is_empty()
+                    || prefix
+                        .chars()
+                        .any(|c| !c.is_alphanumeric() && !"-_".contains(c))
+                {
+                    errors.push(format!(
+                        "rule target '{}' needs a prefix made of a-z A-Z 0-9 - and _ characters",
+                        &rule.workspace
+                    ));
+                }
+            } else if let Err(e) = check_target(&rule.workspace) {
+                errors.push(format!("rule target: {}", e));
+            }
+        }
+
+        for (name, a) in self.activity.iter() {
+            if !activities.iter().any(|a| a == name) {
+                errors.push(format!(
+                    "settings for activity '{}' which is not in activities",
+                    name
+                ));
+            }
+            if let Some(profile) = &a.profile {
+                if !self.keyword_profiles.contains_key(profile) {
+                    errors.push(format!(
+                        "activity '{}' uses keyword profile '{}' which is not in keyword_profiles",
+                        name, profile
+                    ));
+                }
+            }
+            for cell in a.cell_layouts.keys() {
+                let res = Target::parse(&format!("{}:{}", name, cell), self.flat_grid)
+                    .and_then(|t| t.cell_index(self.workspaces));
+                if let Err(e) = res {
+                    errors.push(format!("cell_layouts of activity '{}': {}", name, e));
+                }
+            }
+        }
+
+        for (kind, animation) in self.animation.all() {
+            if let Err(e) = animation.check() {
+                errors.push(format!("animation.{}: {}", kind, e));
+            }
+        }
+
+        if self.flat_grid && !self.activities.is_empty() {
+            errors.push("flat_grid needs activities = []".to_owned());
+        }
+
+        let mut pinned = HashMap::new();
+        for (monitor, names) in self.monitor_rules.iter() {
+            for name in names {
+                if !activities.iter().any(|a| a == name) {
+                    errors.push(format!(
+                        "monitor rule for activity '{}' which is not in activities",
+                        name
+                    ));
+                }
+                if let Some(other) = pinned.insert(name, monitor) {
+                    errors.push(format!(
+                        "activity '{}' is in the monitor rules of both {} and {}",
+                        name, other, monitor
+                    ));
+                }
+            }
+        }
+
+        for name in self.hooks.on_enter.keys().chain(self.hooks.on_leave.keys()) {
+            if !activities.iter().any(|a| a == name) {
+                errors.push(format!(
+                    "hook for activity '{}' which is not in activities",
+                    name
+                ));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("invalid config:\n  {}", errors.join("\n  ")))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<Config> {
+        let config = toml::from_str::<Config>(toml)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn parses_a_config() {
+        let config = parse(
+            r#"
+            activities = ["work", "play"]
+            workspaces = [3, 2]
+            named_focii = { mail = "work:3" }
+
+            [activity.play]
+            icon = "P"
+
+            [[rules]]
+            class = "steam"
+            workspace = "P:(1 1)"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.activities, ["work", "play"]);
+        assert_eq!(config.workspaces, (3, 2));
+        assert_eq!(config.activity["play"].icon.as_deref(), Some("P"));
+        // the rest keeps its defaults
+        assert!(config.daemon.mouse.switch_workspace_on_edge);
+
+        assert!(parse("activites = []").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_configs() {
+        for toml in [
+            "workspaces = [0, 2]",
+            r#"activities = ["a b"]"#,
+            r#"activities = ["a", "a"]"#,
+            r#"activities = ["a"]
+            flat_grid = true"#,
+            r#"named_focii = { mail = "chat:1" }"#,
+            r#"named_focii = { mail = "default:(3 1)" }"#,
+            r#"activity.chat = {}"#,
+            r#"monitor_rules = { DP-1 = ["default"], DP-2 = ["default"] }"#,
+        ] {
+            assert!(parse(toml).is_err(), "{}", toml);
         }
     }
+
+    #[test]
+    fn saves_a_resized_grid() {
+        let path = std::env::temp_dir().join(format!("hyprkool-grid-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"# my grid
+activities = ["work"]
+workspaces = [3, 3]
+named_focii = { mail = "work:(3 3)", notes = "work:1" }
+
+[[rules]]
+class = "steam"
+workspace = "work:3,1" # games
+"#,
+        )
+        .unwrap();
+        save_grid(&path, (2, 2), |t| {
+            t.contains('3').then(|| "work:(2 2)".into())
+        })
+        .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(text.starts_with("# my grid\n"));
+        assert!(text.contains(r#"workspace = "work:(2 2)" # games"#));
+        let config = parse(&text).unwrap();
+        assert_eq!(config.workspaces, (2, 2));
+        assert_eq!(config.named_focii["mail"], "work:(2 2)");
+        assert_eq!(config.named_focii["notes"], "work:1");
+        assert_eq!(config.rules[0].workspace, "work:(2 2)");
+    }
+
+    #[test]
+    fn looks_up_only_used_template_vars() {
+        let vars = template_vars(["notes-{date}", "{user}"]).unwrap();
+        let names = vars.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(names, ["user", "date"]);
+        let date = &vars[1].1;
+        assert!(regex::Regex::new(r"^\d{4}-\d{2}-\d{2}$")
+            .unwrap()
+            .is_match(date));
+
+        assert!(template_vars(["work", "play"]).unwrap().is_empty());
+    }
 }
@@ -1,144 +1,648 @@
-use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
 use hyprland::{
-    data::{Client, CursorPosition, FullscreenMode, Monitor, Workspace},
-    dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial},
+    data::{CursorPosition, FullscreenMode, Monitor},
     event_listener::EventListener,
-    shared::{HyprData, HyprDataActive, HyprDataActiveOptional},
+    shared::WorkspaceType,
 };
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
     net::{UnixListener, UnixStream},
     sync::Mutex,
 };
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    animation::SwitchKind,
+    backend::{Hyprland, HyprlandApi},
+    backoff::Backoff,
+    batch::DispatchBatch,
+    cli::Cli,
+    command::Command,
+    config::{BuiltinEdgeAction, Config, DiagonalAnimation, EdgeAction, EdgeActions, SharedEdge},
+    info::InfoOutputStream,
+    notify, rpc,
+    session::move_window,
+    snapshot::Snapshot,
+    state::{activity_name, finish_pending_switch, Animation, SwitchSource},
+    systemd, Message, State,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Debug, Clone)]
+enum EdgeTarget {
+    Grid,
+    Nothing,
+    Command(Command),
+}
+impl EdgeTarget {
+    fn new(action: &EdgeAction) -> Result<Self> {
+        let args = match action {
+            EdgeAction::Builtin(BuiltinEdgeAction::Grid) => return Ok(Self::Grid),
+            EdgeAction::Builtin(BuiltinEdgeAction::None) => return Ok(Self::Nothing),
+            EdgeAction::Command(args) => args,
+        };
+        let cli =
+            Cli::try_parse_from(std::iter::once("hyprkool").chain(args.iter().map(|a| a.as_str())))
+                .with_context(|| format!("invalid edge action {:?}", args))?;
+        match cli.command {
+            command if command.runs_locally() || matches!(command, Command::Info { .. }) => Err(
+                anyhow!("'{}' cannot be used as an edge action", args.join(" ")),
+            ),
+            command => Ok(Self::Command(command)),
+        }
+    }
+}
 
-use crate::{state::Animation, Command, Config, InfoOutputStream, Message, State};
+#[derive(Debug, Clone)]
+struct EdgeTargets {
+    left: EdgeTarget,
+    right: EdgeTarget,
+    top: EdgeTarget,
+    bottom: EdgeTarget,
+}
+impl EdgeTargets {
+    fn new(edges: &EdgeActions) -> Result<Self> {
+        Ok(Self {
+            left: EdgeTarget::new(&edges.left)?,
+            right: EdgeTarget::new(&edges.right)?,
+            top: EdgeTarget::new(&edges.top)?,
+            bottom: EdgeTarget::new(&edges.bottom)?,
+        })
+    }
 
-pub struct MouseDaemon {
+    fn get(&self, edge: Edge) -> &EdgeTarget {
+        match edge {
+            Edge::Left => &self.left,
+            Edge::Right => &self.right,
+            Edge::Top => &self.top,
+            Edge::Bottom => &self.bottom,
+        }
+    }
+}
+
+/// area of a monitor in layout coordinates
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Rect {
+    pub x: i64,
+    pub y: i64,
+    pub w: i64,
+    pub h: i64,
+}
+impl Rect {
+    pub fn of(m: &Monitor) -> Self {
+        let scale = if m.scale > 0.0 { m.scale } else { 1.0 };
+        Self {
+            x: m.x as i64,
+            y: m.y as i64,
+            w: (m.width as f32 / scale).round() as i64,
+            h: (m.height as f32 / scale).round() as i64,
+        }
+    }
+
+    fn contains(&self, c: &CursorPosition) -> bool {
+        self.x <= c.x && c.x < self.x + self.w && self.y <= c.y && c.y < self.y + self.h
+    }
+}
+
+/// focused window and cursor at the last poll
+#[derive(Debug, Clone)]
+struct DragSample {
+    address: String,
+    at: (i16, i16),
+    cursor: (i64, i64),
+}
+
+/// what the mouse loop should do after a poll
+#[derive(Debug, Clone)]
+pub enum MouseTick {
+    /// the cursor is not at an edge that does something
+    Idle,
+    /// move the cursor away from the edge and run the edge action
+    Command {
+        command: Command,
+        cursor: (i64, i64),
+    },
+    /// switch workspace and move the cursor to the opposite edge
+    Switch {
+        to: String,
+        move_window: bool,
+        kind: SwitchKind,
+        anim: Animation,
+        cursor: (i64, i64),
+    },
+}
+
+pub struct MouseDaemon<H: HyprlandApi = Hyprland> {
     state: Arc<Mutex<State>>,
+    api: H,
 
-    // TODO: multi monitor setup yaaaaaaaaaaaaaaaaa
+    /// monitor under the cursor
     monitor: Monitor,
+    /// every monitor. used to find edges shared between monitors
+    monitors: Vec<Monitor>,
 
     config: Config,
+    edges: EdgeTargets,
+
+    /// monitor is turned off (dpms) or disabled
+    monitor_off: bool,
+    /// ignore edges till then to not switch on a waking up screen
+    resume_at: Option<Instant>,
+    /// when monitor info was last refreshed
+    monitor_checked: Instant,
+    /// edges the cursor is at and since when
+    dwelling: Option<(Option<Edge>, Option<Edge>, Instant)>,
+    drag_sample: Option<DragSample>,
+    /// when the focused window was last seen moving with the cursor
+    dragged_at: Option<Instant>,
+    /// focused workspace at the last poll, as kept up to date by workspace events
+    workspace: Option<String>,
 }
 impl MouseDaemon {
-    pub async fn new(state: Arc<Mutex<State>>) -> Result<Self> {
+    /// uses the monitors of the snapshot instead of asking hyprland again
+    pub async fn from_snapshot(state: Arc<Mutex<State>>, snapshot: &Snapshot) -> Result<Self> {
+        let monitor = snapshot
+            .active_monitor()
+            .context("hyprland has no monitors")?
+            .clone();
+        Self::with_monitors(state, Hyprland, monitor, snapshot.monitors.clone()).await
+    }
+}
+
+impl<H: HyprlandApi> MouseDaemon<H> {
+    const WAKE_UP_GRACE: Duration = Duration::from_secs(1);
+    /// how often to check if the monitor was turned off or disabled
+    const MONITOR_REFRESH: Duration = Duration::from_secs(2);
+
+    /// the monitor under the cursor starts out as the focused one
+    #[cfg(test)]
+    pub async fn with_api(state: Arc<Mutex<State>>, api: H) -> Result<Self> {
+        let monitors = api.monitors().await?;
+        let monitor = monitors
+            .iter()
+            .find(|m| m.focused)
+            .or(monitors.first())
+            .context("hyprland has no monitors")?
+            .clone();
+        Self::with_monitors(state, api, monitor, monitors).await
+    }
+
+    async fn with_monitors(
+        state: Arc<Mutex<State>>,
+        api: H,
+        monitor: Monitor,
+        monitors: Vec<Monitor>,
+    ) -> Result<Self> {
         let s = state.lock().await;
-        let monitor = Monitor::get_active_async().await?;
         let config = s.config.clone();
         drop(s);
 
+        let edges = EdgeTargets::new(&config.daemon.mouse.edges)?;
+
         Ok(Self {
             config,
             monitor,
+            monitors,
             state,
+            api,
+            edges,
+            monitor_off: false,
+            resume_at: None,
+            monitor_checked: Instant::now(),
+            dwelling: None,
+            drag_sample: None,
+            dragged_at: None,
+            workspace: None,
         })
     }
 
-    pub async fn run(&mut self, move_to_hyprkool_activity: bool) -> Result<()> {
-        let workspace = Workspace::get_active_async().await?;
+    /// refreshes monitor info and returns true if edge switching should be paused
+    async fn monitor_paused(&mut self) -> Result<bool> {
+        if self.monitor_checked.elapsed() < Self::MONITOR_REFRESH {
+            return Ok(self.monitor_off || self.resume_at.is_some_and(|t| t > Instant::now()));
+        }
+        self.monitor_checked = Instant::now();
+        self.monitors = self.api.monitors().await?;
+        let monitor = self
+            .monitors
+            .iter()
+            .find(|m| m.name == self.monitor.name)
+            .cloned();
+        let off = monitor.as_ref().map(|m| !m.dpms_status).unwrap_or(true);
+        if let Some(m) = monitor {
+            self.monitor = m;
+        }
 
-        {
-            let state = self.state.lock().await;
+        if off && !self.monitor_off {
+            info!(
+                "monitor {} is off. pausing edge switching",
+                &self.monitor.name
+            );
+        } else if !off && self.monitor_off {
+            info!(
+                "monitor {} is back. resuming edge switching",
+                &self.monitor.name
+            );
+            self.resume_at = Some(Instant::now() + Self::WAKE_UP_GRACE);
+            self.state.lock().await.reconcile_focus().await?;
+        }
+        self.monitor_off = off;
 
-            fn hacky_activity_check(name: &str) -> bool {
-                // yeh exactly. :(
-                // activity_name:(x y)
-                name.contains(":(")
+        if off {
+            return Ok(true);
+        }
+        match self.resume_at {
+            Some(t) if t > Instant::now() => Ok(true),
+            _ => {
+                self.resume_at = None;
+                Ok(false)
             }
+        }
+    }
 
-            if move_to_hyprkool_activity
-                && (state.get_indices(&workspace.name).is_none()
-                    || !hacky_activity_check(&workspace.name))
+    /// pixels from the border of the screen that count as this edge
+    fn edge_width(&self, edge: Edge) -> i64 {
+        let mouse = &self.config.daemon.mouse;
+        let width = match edge {
+            Edge::Left => mouse.edge_widths.left,
+            Edge::Right => mouse.edge_widths.right,
+            Edge::Top => mouse.edge_widths.top,
+            Edge::Bottom => mouse.edge_widths.bottom,
+        };
+        width.unwrap_or(mouse.edge_width) as i64
+    }
+
+    /// how far from this edge the cursor is put when it is moved away from it
+    fn edge_margin(&self, edge: Edge) -> i64 {
+        let mouse = &self.config.daemon.mouse;
+        let margin = match edge {
+            Edge::Left | Edge::Right => mouse.edge_margin_x,
+            Edge::Top | Edge::Bottom => mouse.edge_margin_y,
+        };
+        margin.unwrap_or(mouse.edge_margin) as i64
+    }
+
+    /// pushes the cursor inside the screen, away from the edge
+    fn pushed_inside(&self, mut c: CursorPosition, edge: Edge) -> CursorPosition {
+        let w = self.edge_width(edge);
+        let m = self.edge_margin(edge);
+        let r = Rect::of(&self.monitor);
+        match edge {
+            Edge::Left => c.x = r.x + w + m,
+            Edge::Right => c.x = r.x + r.w - 1 - w - m,
+            Edge::Top => c.y = r.y + w + m,
+            Edge::Bottom => c.y = r.y + r.h - 1 - w - m,
+        }
+        c
+    }
+
+    fn is_enabled(&self, edge: Edge) -> bool {
+        let enabled = &self.config.daemon.mouse.enabled_edges;
+        match edge {
+            Edge::Left => enabled.left,
+            Edge::Right => enabled.right,
+            Edge::Top => enabled.top,
+            Edge::Bottom => enabled.bottom,
+        }
+    }
+
+    /// true once the cursor has been at the same edges for the configured dwell time
+    fn dwelled(&mut self, hor_edge: Option<Edge>, vert_edge: Option<Edge>) -> bool {
+        if hor_edge.is_none() && vert_edge.is_none() {
+            self.dwelling = None;
+            return false;
+        }
+        let since = match self.dwelling {
+            Some((h, v, since)) if h == hor_edge && v == vert_edge => since,
+            _ => {
+                let now = Instant::now();
+                self.dwelling = Some((hor_edge, vert_edge, now));
+                now
+            }
+        };
+        let dwell = Duration::from_millis(self.config.daemon.mouse.edge_dwell_ms);
+        if since.elapsed() < dwell {
+            return false;
+        }
+        self.dwelling = None;
+        true
+    }
+
+    /// remembers if the focused window moves along with the cursor, which means it is
+    /// being dragged
+    async fn sample_drag(&mut self, c: &CursorPosition) -> Result<()> {
+        let Some(window) = self.api.active_window().await? else {
+            self.drag_sample = None;
+            return Ok(());
+        };
+        let sample = DragSample {
+            address: window.address.to_string(),
+            at: window.at,
+            cursor: (c.x, c.y),
+        };
+        if let Some(prev) = self.drag_sample.replace(sample) {
+            let window_delta = (
+                window.at.0 as i64 - prev.at.0 as i64,
+                window.at.1 as i64 - prev.at.1 as i64,
+            );
+            let cursor_delta = (c.x - prev.cursor.0, c.y - prev.cursor.1);
+            if prev.address == window.address.to_string()
+                && window_delta != (0, 0)
+                && window_delta == cursor_delta
             {
-                Dispatch::call_async(DispatchType::Workspace(
-                    WorkspaceIdentifierWithSpecial::Name(&state.workspaces[0][0]),
-                ))
-                .await?;
-            };
+                self.dragged_at = Some(Instant::now());
+            }
         }
+        Ok(())
+    }
 
-        let w = self.config.daemon.mouse.edge_width as i64;
-        let m = self.config.daemon.mouse.edge_margin as i64;
-        let enabled = self.config.daemon.mouse.switch_workspace_on_edge;
+    /// a window counts as dragged for a few polls after it last moved, as the cursor
+    /// stops at the edge
+    fn is_dragging(&self) -> bool {
+        let grace = Duration::from_millis(self.config.daemon.mouse.polling_rate * 3);
+        self.dragged_at.is_some_and(|t| t.elapsed() < grace)
+    }
 
-        let mut sleep_duration =
-            std::time::Duration::from_millis(self.config.daemon.mouse.polling_rate);
+    /// true if the focused window is fullscreen or has an ignored class
+    async fn focused_window_blocks(&self) -> Result<bool> {
+        let mouse = &self.config.daemon.mouse;
+        if !mouse.ignore_fullscreen && mouse.ignore_classes.is_empty() {
+            return Ok(false);
+        }
+        let Some(window) = self.api.active_window().await? else {
+            return Ok(false);
+        };
+        // should i use window.fullscreen or window.fullscreen_client ?
+        let fullscreen = window.fullscreen as u8 > FullscreenMode::Maximized as u8;
+        Ok((mouse.ignore_fullscreen && fullscreen) || mouse.ignore_classes.contains(&window.class))
+    }
 
-        if !enabled {
-            sleep_duration = std::time::Duration::from_secs(10000000);
+    /// true if the cursor should move on to the monitor next to this edge instead of
+    /// triggering the edge
+    fn crosses_monitor(&self, edge: Edge, c: &CursorPosition) -> bool {
+        let shared = &self.config.daemon.mouse.shared_edges;
+        let mode = match edge {
+            Edge::Left => shared.left,
+            Edge::Right => shared.right,
+            Edge::Top => shared.top,
+            Edge::Bottom => shared.bottom,
+        };
+        if mode != SharedEdge::CrossMonitor {
+            return false;
         }
 
+        let r = Rect::of(&self.monitor);
+        self.monitors
+            .iter()
+            .filter(|m| m.name != self.monitor.name)
+            .map(Rect::of)
+            .any(|o| {
+                let beside = o.y <= c.y && c.y < o.y + o.h;
+                let above_below = o.x <= c.x && c.x < o.x + o.w;
+                match edge {
+                    Edge::Left => beside && o.x + o.w == r.x,
+                    Edge::Right => beside && o.x == r.x + r.w,
+                    Edge::Top => above_below && o.y + o.h == r.y,
+                    Edge::Bottom => above_below && o.y == r.y + r.h,
+                }
+            })
+    }
+
+    pub async fn run(&mut self, move_to_hyprkool_activity: bool) -> Result<()> {
+        let workspace = self.api.active_workspace().await?;
+
+        {
+            let state = self.state.lock().await;
+
+            if move_to_hyprkool_activity
+                && !matches!(state.get_indices(&workspace), Some((_, Some(_))))
+            {
+                let mut batch = DispatchBatch::new();
+                batch.workspace(state.workspace_name(0, 0));
+                self.api.dispatch(batch).await?;
+            };
+        }
+
+        let sleep_duration = Duration::from_millis(self.config.daemon.mouse.polling_rate);
+        let toggled = self.state.lock().await.edge_switching_toggled.clone();
+
         loop {
-            tokio::time::sleep(sleep_duration).await;
-            if !enabled {
+            let paused = {
+                let state = self.state.lock().await;
+                !state.edge_switching || state.away()
+            };
+            if paused {
+                // don't poll at all till edge switching is turned back on or the session wakes up
+                toggled.notified().await;
+                // the cursor might be anywhere after unlocking
+                self.resume_at = Some(Instant::now() + Self::WAKE_UP_GRACE);
+                self.dwelling = None;
                 continue;
             }
+            tokio::time::sleep(sleep_duration).await;
 
-            let nx = self.config.workspaces.0 as usize;
-            let ny = self.config.workspaces.1 as usize;
-            let mut c = CursorPosition::get_async().await?;
-            let mut y = 0;
-            let mut x = 0;
-            let mut anim = Animation::Fade;
-            if c.x <= w {
-                x += nx - 1;
-                c.x = self.monitor.width as i64 - m;
-                anim = Animation::Left;
-            } else if c.x >= self.monitor.width as i64 - 1 - w {
-                x += 1;
-                c.x = m;
-                anim = Animation::Right;
-            }
-            if c.y <= w {
-                y += ny - 1;
-                c.y = self.monitor.height as i64 - m;
-                anim = Animation::Up;
-            } else if c.y >= self.monitor.height as i64 - 1 - w {
-                y += 1;
-                c.y = m;
-                anim = Animation::Down;
+            match self.tick().await? {
+                MouseTick::Idle => {}
+                MouseTick::Command { command, cursor } => {
+                    let mut batch = DispatchBatch::new();
+                    batch.move_cursor(cursor.0, cursor.1);
+                    self.api.dispatch(batch).await?;
+                    let res = command
+                        .execute_from(self.state.clone(), true, SwitchSource::Mouse, None)
+                        .await;
+                    if let Err(e) = res {
+                        error!("error in edge action: {}", e);
+                        if self.config.notify_errors {
+                            notify::error(&format!("error in edge action: {}", e)).await;
+                        }
+                    }
+                }
+                MouseTick::Switch {
+                    to,
+                    move_window,
+                    kind,
+                    anim,
+                    cursor,
+                } => {
+                    let mut state = self.state.lock().await;
+                    state.switch_source = SwitchSource::Mouse;
+                    state.switch_command = Some("edge".into());
+                    let mut then = DispatchBatch::new();
+                    then.move_cursor(cursor.0, cursor.1);
+                    state
+                        .move_to_workspace_as(&to, move_window, Some(kind), anim, then)
+                        .await?;
+                    drop(state);
+                    if let Err(e) = finish_pending_switch(&self.state).await {
+                        error!("could not switch to {}: {}", &to, e);
+                    }
+                }
             }
+        }
+    }
 
-            if x + y == 0 {
-                continue;
-            }
+    /// polls the cursor once and decides what the cursor at an edge should do
+    pub async fn tick(&mut self) -> Result<MouseTick> {
+        if self.monitor_paused().await? {
+            return Ok(MouseTick::Idle);
+        }
 
-            if x > 0 && y > 0 {
-                anim = Animation::Fade;
+        let workspace = self.state.lock().await.last_workspaces.1.clone();
+        // the cursor might have been dwelling at an edge while a keyboard switch happened.
+        // start over so that it does not fire in the new workspace right away
+        if workspace != self.workspace {
+            self.workspace = workspace;
+            self.dwelling = None;
+            self.drag_sample = None;
+        }
+        let mut c = self.api.cursor_position().await?;
+        if self.config.daemon.mouse.drag_windows {
+            self.sample_drag(&c).await?;
+        }
+        if !Rect::of(&self.monitor).contains(&c) {
+            if let Some(mon) = self.monitors.iter().find(|m| Rect::of(m).contains(&c)) {
+                self.monitor = mon.clone();
             }
+        }
+        let r = Rect::of(&self.monitor);
 
-            let workspace = Workspace::get_active_async().await?;
+        let hor_edge = if c.x <= r.x + self.edge_width(Edge::Left) {
+            Some(Edge::Left)
+        } else if c.x >= r.x + r.w - 1 - self.edge_width(Edge::Right) {
+            Some(Edge::Right)
+        } else {
+            None
+        };
+        let vert_edge = if c.y <= r.y + self.edge_width(Edge::Top) {
+            Some(Edge::Top)
+        } else if c.y >= r.y + r.h - 1 - self.edge_width(Edge::Bottom) {
+            Some(Edge::Bottom)
+        } else {
+            None
+        };
+        let usable = |e: &Edge| self.is_enabled(*e) && !self.crosses_monitor(*e, &c);
+        let hor_edge = hor_edge.filter(usable);
+        let vert_edge = vert_edge.filter(usable);
+        if !self.dwelled(hor_edge, vert_edge) {
+            return Ok(MouseTick::Idle);
+        }
+        if self.focused_window_blocks().await? {
+            debug!("ignoring edge as the focused window blocks edge switching");
+            return Ok(MouseTick::Idle);
+        }
 
-            let state = self.state.lock().await;
+        let command =
+            [hor_edge, vert_edge]
+                .into_iter()
+                .flatten()
+                .find_map(|e| match self.edges.get(e) {
+                    EdgeTarget::Command(command) => Some((e, command.clone())),
+                    _ => None,
+                });
+        if let Some((edge, command)) = command {
+            debug!("running edge action for {:?} edge: {:?}", edge, &command);
+            let c = self.pushed_inside(c, edge);
+            return Ok(MouseTick::Command {
+                command,
+                cursor: (c.x, c.y),
+            });
+        }
 
-            let Some((current_activity_index, Some(current_workspace_index))) =
-                state.get_indices(&workspace.name)
-            else {
-                println!("unknown workspace {}", workspace.name);
-                continue;
-            };
+        let is_grid = |e: &Edge| matches!(self.edges.get(*e), EdgeTarget::Grid);
+        let hor_edge = hor_edge.filter(is_grid);
+        let vert_edge = vert_edge.filter(is_grid);
 
-            if let Some(window) = Client::get_active_async().await? {
-                // should i use window.fullscreen or window.fullscreen_client ?
-                if window.fullscreen as u8 > FullscreenMode::Maximized as u8 {
-                    continue;
-                }
+        // visual direction. the navigation policy flips it with invert_horizontal
+        let mut y = 0;
+        let mut x = 0;
+        let mut hor_anim = None;
+        let mut vert_anim = None;
+        // the cursor comes out of the opposite edge
+        match hor_edge {
+            Some(Edge::Left) => {
+                x = -1;
+                c.x = r.x + r.w - self.edge_width(Edge::Right) - self.edge_margin(Edge::Right);
+                hor_anim = Some(Animation::Left);
             }
+            Some(Edge::Right) => {
+                x = 1;
+                c.x = r.x + self.edge_width(Edge::Left) + self.edge_margin(Edge::Left);
+                hor_anim = Some(Animation::Right);
+            }
+            _ => {}
+        }
+        match vert_edge {
+            Some(Edge::Top) => {
+                y = -1;
+                c.y = r.y + r.h - self.edge_width(Edge::Bottom) - self.edge_margin(Edge::Bottom);
+                vert_anim = Some(Animation::Up);
+            }
+            Some(Edge::Bottom) => {
+                y = 1;
+                c.y = r.y + self.edge_width(Edge::Top) + self.edge_margin(Edge::Top);
+                vert_anim = Some(Animation::Down);
+            }
+            _ => {}
+        }
 
-            y += current_workspace_index / nx;
-            y %= ny;
-            x += current_workspace_index % nx;
-            x %= nx;
-
-            let new_workspace = &state.workspaces[current_activity_index][y * nx + x];
-            if new_workspace != &workspace.name {
-                state.move_to_workspace(new_workspace, false, anim).await?;
-                Dispatch::call_async(DispatchType::MoveCursor(c.x, c.y)).await?;
+        let (kind, anim) = match (hor_anim, vert_anim) {
+            (None, None) => {
+                return Ok(MouseTick::Idle);
             }
+            (Some(a), None) => (SwitchKind::Horizontal, a),
+            (None, Some(a)) => (SwitchKind::Vertical, a),
+            // [animation.diagonal] style wins over this
+            (Some(h), Some(v)) => {
+                let anim = match self.config.daemon.mouse.diagonal_animation {
+                    DiagonalAnimation::Fade => Animation::Fade,
+                    DiagonalAnimation::None => Animation::None,
+                    DiagonalAnimation::Horizontal => h,
+                    DiagonalAnimation::Vertical => v,
+                };
+                (SwitchKind::Diagonal, anim)
+            }
+        };
+
+        let workspace = self.api.active_workspace().await?;
+        let move_window = self.is_dragging();
+
+        let state = self.state.lock().await;
+        if !matches!(state.get_indices(&workspace), Some((_, Some(_)))) {
+            warn!("unknown workspace {}", workspace);
+            return Ok(MouseTick::Idle);
+        }
+        // edges always wrap around
+        let new_workspace = state.moved_workspace_from(&workspace, x, y, true)?;
+        debug!(
+            "edge switch from {} to {} (dragging window: {})",
+            &workspace, &new_workspace, move_window
+        );
+        if new_workspace == workspace {
+            return Ok(MouseTick::Idle);
         }
+        Ok(MouseTick::Switch {
+            to: new_workspace,
+            move_window,
+            kind,
+            anim,
+            cursor: (c.x, c.y),
+        })
     }
 }
 
@@ -153,41 +657,17 @@ impl IpcDaemon {
         let config = s.config.clone();
         drop(s);
 
-        let sock_path = get_socket_path()?;
-
-        // - [Unix sockets, the basics in Rust - Emmanuel Bosquet](https://emmanuelbosquet.com/2022/whatsaunixsocket/)
-        // send a quit message to any daemon that might be running. ignore all errors
-        if let Ok(sock) = UnixStream::connect(&sock_path).await {
-            let mut sock = BufWriter::new(sock);
-            let _ = sock
-                .write_all(&Message::Command(Command::DaemonQuit).msg())
-                .await;
-            let _ = sock.write_all("\n".as_bytes()).await;
-            let _ = sock.flush().await;
-            let _ = sock.shutdown().await;
-
-            let sleep = tokio::time::sleep(Duration::from_millis(300));
-            let mut sock = BufReader::new(sock);
-            let mut line = String::new();
-            tokio::select! {
-                res = sock.read_line(&mut line) => {
-                    let _ = res;
-                    if let Ok(command) = serde_json::from_str(&line) {
-                        match command {
-                            Message::IpcOk => { }
-                            Message::IpcErr(message) => {
-                                println!("{}", message);
-                            }
-                            _ => {
-                                unreachable!();
-                            }
-                        }
-                    }
-                }
-                _ = sleep => { }
-            }
+        if let Some(sock) = systemd::activated_listener()? {
+            info!("listening on the socket passed by systemd");
+            return Ok(Self {
+                sock,
+                _config: config,
+                state,
+            });
         }
 
+        // DaemonLock makes sure that this is the only daemon, so the socket is a leftover
+        let sock_path = get_socket_path()?;
         if std::fs::metadata(&sock_path).is_ok() {
             std::fs::remove_file(&sock_path)
                 .with_context(|| format!("could not delete previous socket at {:?}", &sock_path))?;
@@ -207,16 +687,39 @@ impl IpcDaemon {
                     let mut sock = BufReader::new(stream);
                     let mut line = String::new();
                     sock.read_line(&mut line).await?;
-                    let message = serde_json::from_str::<Message>(&line)?;
+                    let message = match serde_json::from_str::<Message>(&line) {
+                        Ok(message) => message,
+                        Err(_) => {
+                            // anything else is a json rpc client
+                            let state = self.state.clone();
+                            tokio::spawn(async move {
+                                if let Err(e) = rpc::serve(state, sock, line).await {
+                                    warn!("rpc client failed: {:?}", e);
+                                }
+                            });
+                            continue;
+                        }
+                    };
+                    let (message, animation) = match message {
+                        Message::AnimatedCommand(command, animation) => {
+                            (Message::Command(command), Some(animation))
+                        }
+                        message => (message, None),
+                    };
                     match message {
                         Message::Command(Command::DaemonQuit) => {
                             sock.write_all(&Message::IpcOk.msg()).await?;
                             return Ok(());
                         }
-                        Message::Command(Command::Info { command, monitor }) => {
+                        Message::Command(Command::Info {
+                            command,
+                            monitor,
+                            diff,
+                        }) => {
                             let state = self.state.clone();
                             tokio::spawn(async move {
                                 let sock = Arc::new(Mutex::new(sock.into_inner()));
+                                let mut backoff = Backoff::default();
                                 loop {
                                     let state = state.clone();
                                     let res = command
@@ -224,6 +727,7 @@ impl IpcDaemon {
                                             InfoOutputStream::Stream(sock.clone()),
                                             state,
                                             monitor,
+                                            diff,
                                         )
                                         .await;
                                     match res {
@@ -232,7 +736,8 @@ impl IpcDaemon {
                                         }
                                         Err(e) => {
                                             // TODO: maybe also try to write this in the socket
-                                            println!("error in info command: {}", e);
+                                            error!("error in info command: {}", e);
+                                            backoff.wait().await;
                                         }
                                     }
                                 }
@@ -240,11 +745,24 @@ impl IpcDaemon {
                             continue;
                         }
                         Message::Command(command) => {
-                            match command.execute(self.state.clone(), true).await {
-                                Ok(_) => {
+                            debug!("ipc command: {:?}", &command);
+                            let res = command
+                                .execute_from(
+                                    self.state.clone(),
+                                    true,
+                                    SwitchSource::Keyboard,
+                                    animation,
+                                )
+                                .await;
+                            match res {
+                                Ok(Some(output)) => {
+                                    sock.write_all(&Message::IpcMessage(output).msg()).await?;
+                                }
+                                Ok(None) => {
                                     sock.write_all(&Message::IpcOk.msg()).await?;
                                 }
                                 Err(e) => {
+                                    warn!("ipc command failed: {:?}", e);
                                     sock.write_all(
                                         &Message::IpcErr(format!("error: {:?}", e)).msg(),
                                     )
@@ -258,32 +776,161 @@ impl IpcDaemon {
                     }
                     sock.flush().await?;
                 }
-                Err(e) => println!("{:?}", e),
+                Err(e) => error!("{:?}", e),
             }
         }
     }
 
-    async fn update(_state: Arc<Mutex<State>>) -> Result<()> {
+    async fn update(state: Arc<Mutex<State>>) -> Result<()> {
         let mut el = EventListener::new();
 
-        // let s = state.clone();
-        // el.add_workspace_change_handler(move |e| {
-        //     let s = s.clone();
-        //     tokio::spawn(async move {
-        //         let name = match e {
-        //             WorkspaceType::Regular(n) => n,
-        //             WorkspaceType::Special(n) => n.unwrap_or("special".into()),
-        //         };
-        //         let mut state = s.lock().await;
-        //         let f = state.current_focus.clone();
-        //         state.focii.insert(f, name);
-        //     });
-        // });
+        let s = state.clone();
+        el.add_workspace_change_handler(move |e| {
+            let s = s.clone();
+            tokio::spawn(async move {
+                let name = match e {
+                    WorkspaceType::Regular(n) => n,
+                    WorkspaceType::Special(..) => return,
+                };
+                let mut state = s.lock().await;
+                if matches!(state.get_indices(&name), Some((_, Some(_)))) {
+                    let from = state.last_workspaces.1.clone();
+                    state.hooks.switched(from.as_deref(), &name);
+                }
+                state.stats.focused(&name);
+                state.remember_activity_visit(&name);
+                state.remember_workspace_visit(&name);
+                if let Err(e) = state.apply_workspace_keywords(&name).await {
+                    error!("could not apply activity keywords: {}", e);
+                }
+                if state.workspace_activity_index(&name).is_some() {
+                    let activity = activity_name(&name, state.config.flat_grid).to_owned();
+                    if let Err(e) = state.freezer.visit(&activity).await {
+                        error!("could not thaw activity: {}", e);
+                    }
+                    let s = &mut *state;
+                    if let Err(e) = s.dimmer.enter(&activity, &mut s.keywords).await {
+                        error!("could not dim background activities: {}", e);
+                    }
+                    let wallpaper = state
+                        .config
+                        .activity
+                        .get(&activity)
+                        .and_then(|a| a.wallpaper.clone());
+                    state.wallpaper.enter(wallpaper.as_deref());
+                }
+                if let Err(e) = state.move_following_windows(&name).await {
+                    error!("could not move pinned windows: {}", e);
+                }
+                let flat = state.config.flat_grid;
+                state.focus_steal.workspace_changed(name, flat);
+                if let Err(e) = state.revert_focus_steal().await {
+                    error!("could not revert focus steal: {}", e);
+                }
+            });
+        });
+
+        let s = state.clone();
+        el.add_window_open_handler(move |e| {
+            let s = s.clone();
+            tokio::spawn(async move {
+                let mut state = s.lock().await;
+                state.focus_steal.window_opened(e.workspace_name.clone());
+                if let Err(e) = state.revert_focus_steal().await {
+                    error!("could not revert focus steal: {}", e);
+                }
+                let address = e.window_address.to_string();
+                match state.spawns.workspace_of(&address).await {
+                    Ok(Some(w)) if w != e.workspace_name => {
+                        if let Err(e) = move_window(e.window_address, &w).await {
+                            error!("could not move launched window: {}", e);
+                        }
+                        return;
+                    }
+                    Ok(Some(_)) => return,
+                    Ok(None) => (),
+                    Err(e) => error!("could not check launched windows: {}", e),
+                }
+                let res = state
+                    .restore_session_window(
+                        e.window_address.clone(),
+                        &e.window_class,
+                        &e.window_title,
+                        &e.workspace_name,
+                    )
+                    .await;
+                // rules still apply to restored windows
+                let workspace = match res {
+                    Ok(restored) => restored.unwrap_or(e.workspace_name),
+                    Err(err) => {
+                        error!("could not restore session window: {}", err);
+                        e.workspace_name
+                    }
+                };
+                let res = state
+                    .apply_window_rules(
+                        e.window_address,
+                        &e.window_class,
+                        &e.window_title,
+                        &workspace,
+                    )
+                    .await;
+                if let Err(e) = res {
+                    error!("error in window rules: {}", e);
+                }
+            });
+        });
+
+        let s = state.clone();
+        el.add_window_close_handler(move |address| {
+            let s = s.clone();
+            tokio::spawn(async move {
+                let res = s.lock().await.window_closed(&address.to_string()).await;
+                if let Err(e) = res {
+                    error!("could not check the activity of a closed window: {}", e);
+                }
+            });
+        });
+
+        let s = state.clone();
+        el.add_urgent_state_handler(move |address| {
+            let s = s.clone();
+            tokio::spawn(async move {
+                s.lock().await.focus_steal.urgent(address.to_string());
+            });
+        });
 
         el.start_listener_async().await?;
         Ok(())
     }
 
+    /// freezes idle activities every now and then
+    async fn freeze_loop(state: Arc<Mutex<State>>) -> Result<()> {
+        if !state.lock().await.config.daemon.freeze.enable {
+            return std::future::pending().await;
+        }
+        loop {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            let mut state = state.lock().await;
+            if let Err(e) = state.freezer.freeze_idle().await {
+                error!("could not freeze activities: {}", e);
+            }
+        }
+    }
+
+    /// saves the session every now and then
+    async fn autosave_loop(state: Arc<Mutex<State>>) -> Result<()> {
+        if !state.lock().await.config.daemon.session.autosave {
+            return std::future::pending().await;
+        }
+        loop {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            if let Err(e) = state.lock().await.save_session().await {
+                error!("could not save session: {}", e);
+            }
+        }
+    }
+
     pub async fn run(&self) -> Result<()> {
         let s = self.state.clone();
 
@@ -291,10 +938,135 @@ impl IpcDaemon {
             listen = self.listen_loop() => {
                 listen
             }
-            update = Self::update(s) => {
+            update = Self::update(s.clone()) => {
                 update
             }
+            freeze = Self::freeze_loop(s.clone()) => {
+                freeze
+            }
+            autosave = Self::autosave_loop(s) => {
+                autosave
+            }
+        }
+    }
+}
+
+/// only one daemon can run per hyprland instance, or they would fight over the cursor and
+/// workspaces. held till dropped or the process dies
+pub struct DaemonLock {
+    _file: File,
+}
+impl DaemonLock {
+    /// how long a replaced daemon gets to clean up and quit
+    const QUIT_TIMEOUT: Duration = Duration::from_secs(3);
+
+    /// errors if another daemon is running, unless replace is set. it is then asked to quit
+    /// (and terminated if it does not)
+    pub async fn acquire(replace: bool) -> Result<Self> {
+        let mut path = get_socket_dir()?;
+        path.push("daemon.lock");
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("could not open lock file {:?}", &path))?;
+
+        if !Self::try_lock(&file)? {
+            let pid = fs::read_to_string(&path)
+                .ok()
+                .and_then(|p| p.trim().parse::<i32>().ok())
+                .filter(|&p| p > 0);
+            let running = match pid {
+                Some(pid) => format!("the hyprkool daemon (pid {})", pid),
+                None => "the hyprkool daemon".to_owned(),
+            };
+            if !replace {
+                return Err(anyhow!(
+                    "{} is already running. use --replace to take over",
+                    running
+                ));
+            }
+            info!("replacing {}", running);
+            quit_running_daemon().await;
+            if !Self::wait_for_lock(&file).await? {
+                if let Some(pid) = pid {
+                    warn!("{} did not quit. terminating it", running);
+                    if let Err(e) = Self::terminate(pid) {
+                        warn!("{}", e);
+                    }
+                }
+                if !Self::wait_for_lock(&file).await? {
+                    return Err(anyhow!("could not replace {}", running));
+                }
+            }
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(Self { _file: file })
+    }
+
+    /// sends SIGTERM to pid if it is still alive. the lock file can name a pid that is
+    /// long gone
+    fn terminate(pid: i32) -> Result<()> {
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid, 0) == 0 && libc::kill(pid, libc::SIGTERM) == 0 } {
+            return Ok(());
+        }
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error() == Some(libc::ESRCH) {
+            return Ok(());
+        }
+        Err(e).with_context(|| format!("could not terminate pid {}", pid))
+    }
+
+    fn try_lock(file: &File) -> Result<bool> {
+        match file.try_lock() {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) => Ok(false),
+            Err(TryLockError::Error(e)) => Err(e).context("could not lock the daemon lock file"),
+        }
+    }
+
+    async fn wait_for_lock(file: &File) -> Result<bool> {
+        let deadline = Instant::now() + Self::QUIT_TIMEOUT;
+        while Instant::now() < deadline {
+            if Self::try_lock(file)? {
+                return Ok(true);
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(false)
+    }
+}
+
+/// sends a quit message to the running daemon. ignores all errors
+async fn quit_running_daemon() {
+    // - [Unix sockets, the basics in Rust - Emmanuel Bosquet](https://emmanuelbosquet.com/2022/whatsaunixsocket/)
+    let Ok(sock) = connect().await else {
+        return;
+    };
+    let mut sock = BufWriter::new(sock);
+    let _ = sock
+        .write_all(&Message::Command(Command::DaemonQuit).msg())
+        .await;
+    let _ = sock.write_all("\n".as_bytes()).await;
+    let _ = sock.flush().await;
+    let _ = sock.shutdown().await;
+
+    let sleep = tokio::time::sleep(Duration::from_millis(300));
+    let mut sock = BufReader::new(sock);
+    let mut line = String::new();
+    tokio::select! {
+        res = sock.read_line(&mut line) => {
+            let _ = res;
+            if let Ok(Message::IpcErr(message)) = serde_json::from_str(&line) {
+                println!("{}", message);
+            }
         }
+        _ = sleep => { }
     }
 }
 
@@ -314,8 +1086,275 @@ pub fn get_socket_path() -> Result<PathBuf> {
     sock_path.push("kool.sock");
     Ok(sock_path)
 }
+/// connects to the running daemon. falls back to the systemd socket, which starts the daemon
+/// if it is not running
+pub async fn connect() -> Result<UnixStream> {
+    let err = match UnixStream::connect(get_socket_path()?).await {
+        Ok(sock) => return Ok(sock),
+        Err(e) => e,
+    };
+    match systemd::activation_socket_path().filter(|p| p.exists()) {
+        Some(path) => Ok(UnixStream::connect(path).await?),
+        None => Err(err.into()),
+    }
+}
+
 pub fn get_plugin_socket_path() -> Result<PathBuf> {
     let mut sock_path = get_socket_dir()?;
     sock_path.push("plugin.sock");
     Ok(sock_path)
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::backend::mock::{MockHyprland, MockState};
+
+    /// a monitor as hyprctl prints it
+    fn monitor(name: &str, x: i32, focused: bool) -> Monitor {
+        serde_json::from_value(json!({
+            "id": 0,
+            "name": name,
+            "description": "",
+            "make": "",
+            "model": "",
+            "serial": "",
+            "width": 1920,
+            "height": 1080,
+            "refreshRate": 60.0,
+            "x": x,
+            "y": 0,
+            "activeWorkspace": { "id": 1, "name": "default:(1 1)" },
+            "specialWorkspace": { "id": 0, "name": "" },
+            "reserved": [0, 0, 0, 0],
+            "scale": 1.0,
+            "transform": 0,
+            "focused": focused,
+            "dpmsStatus": true,
+            "vrr": false,
+            "activelyTearing": false,
+            "disabled": false,
+            "currentFormat": "XRGB8888",
+            "availableModes": [],
+        }))
+        .unwrap()
+    }
+
+    /// a 3x3 grid on one monitor, or two monitors side by side
+    async fn daemon(
+        monitors: Vec<Monitor>,
+        config: impl FnOnce(&mut Config),
+    ) -> (MouseDaemon<MockHyprland>, MockHyprland) {
+        let mut c = Config {
+            workspaces: (3, 3),
+            ..Default::default()
+        };
+        config(&mut c);
+        let state = State::new(c).unwrap();
+        let api = MockHyprland::new(MockState {
+            workspace: "default:(2 2)".into(),
+            monitors,
+            ..Default::default()
+        });
+        let d = MouseDaemon::with_api(Arc::new(Mutex::new(state)), api.clone())
+            .await
+            .unwrap();
+        (d, api)
+    }
+
+    async fn tick_at(
+        d: &mut MouseDaemon<MockHyprland>,
+        api: &MockHyprland,
+        x: i64,
+        y: i64,
+    ) -> MouseTick {
+        api.state().cursor = (x, y);
+        d.tick().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn switches_at_edges() {
+        let (mut d, api) = daemon(vec![monitor("DP-1", 0, true)], |_| {}).await;
+        let MouseTick::Switch {
+            to,
+            kind,
+            anim,
+            cursor,
+            move_window,
+        } = tick_at(&mut d, &api, 1919, 500).await
+        else {
+            panic!("no switch at the right edge");
+        };
+        assert_eq!(to, "default:(3 2)");
+        assert_eq!((kind, anim), (SwitchKind::Horizontal, Animation::Right));
+        // comes out of the left edge
+        assert_eq!(cursor, (2, 500));
+        assert!(!move_window);
+
+        let MouseTick::Switch { to, kind, anim, .. } = tick_at(&mut d, &api, 500, 0).await else {
+            panic!("no switch at the top edge");
+        };
+        assert_eq!(to, "default:(2 1)");
+        assert_eq!((kind, anim), (SwitchKind::Vertical, Animation::Up));
+
+        let MouseTick::Switch { to, kind, .. } = tick_at(&mut d, &api, 0, 1079).await else {
+            panic!("no switch at the bottom left corner");
+        };
+        assert_eq!(to, "default:(1 3)");
+        assert_eq!(kind, SwitchKind::Diagonal);
+
+        // edges wrap around
+        api.state().workspace = "default:(3 2)".into();
+        let MouseTick::Switch { to, .. } = tick_at(&mut d, &api, 1919, 500).await else {
+            panic!("no switch at the right edge");
+        };
+        assert_eq!(to, "default:(1 2)");
+
+        assert!(matches!(
+            tick_at(&mut d, &api, 960, 540).await,
+            MouseTick::Idle
+        ));
+    }
+
+    #[tokio::test]
+    async fn switches_in_flat_and_activity_grids_side_by_side() {
+        let (mut flat, flat_api) = daemon(vec![monitor("DP-1", 0, true)], |c| {
+            c.activities = vec![];
+            c.flat_grid = true;
+        })
+        .await;
+        let (mut grid, grid_api) = daemon(vec![monitor("DP-1", 0, true)], |_| {}).await;
+        flat_api.state().workspace = "(2 2)".into();
+
+        // each loop names workspaces by its own config
+        for _ in 0..2 {
+            let MouseTick::Switch { to, .. } = tick_at(&mut flat, &flat_api, 1919, 500).await
+            else {
+                panic!("no switch in the flat grid");
+            };
+            assert_eq!(to, "(3 2)");
+            let MouseTick::Switch { to, .. } = tick_at(&mut grid, &grid_api, 1919, 500).await
+            else {
+                panic!("no switch in the activity grid");
+            };
+            assert_eq!(to, "default:(3 2)");
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_invert_horizontal() {
+        let (mut d, api) = daemon(vec![monitor("DP-1", 0, true)], |c| {
+            c.invert_horizontal = true
+        })
+        .await;
+        let MouseTick::Switch { to, anim, .. } = tick_at(&mut d, &api, 1919, 500).await else {
+            panic!("no switch at the right edge");
+        };
+        assert_eq!(to, "default:(1 2)");
+        assert_eq!(anim, Animation::Right);
+    }
+
+    #[tokio::test]
+    async fn shared_and_disabled_edges() {
+        let monitors = vec![monitor("DP-1", 0, true), monitor("DP-2", 1920, false)];
+        let (mut d, api) = daemon(monitors.clone(), |c| {
+            c.daemon.mouse.enabled_edges.top = false
+        })
+        .await;
+        // the cursor moves on to DP-2 instead
+        assert!(matches!(
+            tick_at(&mut d, &api, 1919, 500).await,
+            MouseTick::Idle
+        ));
+        assert!(matches!(
+            tick_at(&mut d, &api, 500, 0).await,
+            MouseTick::Idle
+        ));
+        assert!(matches!(
+            tick_at(&mut d, &api, 500, 1079).await,
+            MouseTick::Switch { .. }
+        ));
+
+        let (mut d, api) = daemon(monitors, |c| {
+            c.daemon.mouse.shared_edges.right = SharedEdge::SwitchWorkspace
+        })
+        .await;
+        assert!(matches!(
+            tick_at(&mut d, &api, 1919, 500).await,
+            MouseTick::Switch { .. }
+        ));
+        // the cursor is on DP-2 now, whose left edge is shared
+        assert!(matches!(
+            tick_at(&mut d, &api, 1920, 500).await,
+            MouseTick::Idle
+        ));
+    }
+
+    #[tokio::test]
+    async fn edge_actions_and_unknown_workspaces() {
+        let (mut d, api) = daemon(vec![monitor("DP-1", 0, true)], |c| {
+            c.daemon.mouse.edges.left = EdgeAction::Command(vec!["next-activity".into()]);
+            c.daemon.mouse.edges.top = EdgeAction::Builtin(BuiltinEdgeAction::None);
+        })
+        .await;
+        let MouseTick::Command { command, cursor } = tick_at(&mut d, &api, 0, 500).await else {
+            panic!("no command at the left edge");
+        };
+        assert_eq!(command.name(), "next-activity");
+        assert_eq!(cursor, (2, 500));
+        assert!(matches!(
+            tick_at(&mut d, &api, 500, 0).await,
+            MouseTick::Idle
+        ));
+
+        api.state().workspace = "1".into();
+        assert!(matches!(
+            tick_at(&mut d, &api, 1919, 500).await,
+            MouseTick::Idle
+        ));
+    }
+
+    #[tokio::test]
+    async fn run_moves_into_the_grid() {
+        let (mut d, api) = daemon(vec![monitor("DP-1", 0, true)], |c| {
+            c.daemon.mouse.switch_workspace_on_edge = false
+        })
+        .await;
+        d.state.lock().await.edge_switching = false;
+        api.state().workspace = "1".into();
+        // waits for edge switching to be turned on forever
+        let res = tokio::time::timeout(Duration::from_millis(50), d.run(true)).await;
+        assert!(res.is_err());
+        assert_eq!(
+            api.state().dispatched,
+            vec!["dispatch workspace name:default:(1 1)".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn wake_while_locked_keeps_edges_paused() {
+        let (mut d, api) = daemon(vec![monitor("DP-1", 0, true)], |c| {
+            c.daemon.mouse.polling_rate = 1
+        })
+        .await;
+        {
+            // lock, idle, then wake from idle as hypridle does
+            let mut state = d.state.lock().await;
+            state.locked = true;
+            state.idle = true;
+            state.idle = false;
+            assert!(state.away());
+        }
+
+        // sitting on the right edge switches nothing while still locked
+        api.state().cursor = (1919, 500);
+        let res = tokio::time::timeout(Duration::from_millis(50), d.run(true)).await;
+        assert!(res.is_err());
+        assert!(api.state().dispatched.is_empty());
+
+        d.state.lock().await.locked = false;
+        assert!(!d.state.lock().await.away());
+    }
+}
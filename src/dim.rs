@@ -0,0 +1,95 @@
+use std::collections::HashSet;
+
+use anyhow::Result;
+use hyprland::{data::Clients, shared::HyprData};
+
+use crate::{
+    batch::DispatchBatch,
+    config::DimConfig,
+    keywords::KeywordLedger,
+    state::{activity_name, parse_cell},
+};
+
+/// tags windows of activities that are not focused so that a window rule can dim them
+#[derive(Debug, Default)]
+pub struct Dimmer {
+    config: DimConfig,
+    /// workspaces are named without the activity
+    flat_grid: bool,
+    /// addresses of windows that are tagged
+    dimmed: HashSet<String>,
+    rule_added: bool,
+}
+
+impl Dimmer {
+    pub fn new(config: DimConfig, flat_grid: bool) -> Self {
+        Self {
+            config,
+            flat_grid,
+            ..Default::default()
+        }
+    }
+
+    /// dims windows of every activity except this one and restores the ones in it
+    pub async fn enter(&mut self, activity: &str, keywords: &mut KeywordLedger) -> Result<()> {
+        if !self.config.enable {
+            return Ok(());
+        }
+        let mut batch = DispatchBatch::new();
+        let rule = format!(
+            "opacity {} override {} override,tag:{}",
+            self.config.opacity, self.config.opacity, &self.config.tag
+        );
+        if !self.rule_added {
+            batch.keyword("windowrulev2", &rule);
+        }
+
+        let clients = Clients::get_async().await?;
+        let open = clients
+            .iter()
+            .map(|c| c.address.to_string())
+            .collect::<HashSet<_>>();
+        self.dimmed.retain(|a| open.contains(a));
+        for c in clients
+            .iter()
+            .filter(|c| parse_cell(&c.workspace.name, self.flat_grid).is_some())
+        {
+            let address = c.address.to_string();
+            let background = activity_name(&c.workspace.name, self.flat_grid) != activity;
+            if background && self.dimmed.insert(address.clone()) {
+                batch.dispatch(
+                    "tagwindow",
+                    format!("+{} address:{}", &self.config.tag, address),
+                );
+            } else if !background && self.dimmed.remove(&address) {
+                batch.dispatch(
+                    "tagwindow",
+                    format!("-{} address:{}", &self.config.tag, address),
+                );
+            }
+        }
+        batch.send().await?;
+        if !self.rule_added {
+            keywords.rule_added("windowrulev2", &rule, "dim");
+        }
+        self.rule_added = true;
+        Ok(())
+    }
+
+    /// removes the tag from every dimmed window. should be called before the daemon exits
+    pub async fn restore_all(&mut self) -> Result<()> {
+        let clients = Clients::get_async().await?;
+        let mut batch = DispatchBatch::new();
+        for address in clients.iter().map(|c| c.address.to_string()) {
+            if !self.dimmed.remove(&address) {
+                continue;
+            }
+            batch.dispatch(
+                "tagwindow",
+                format!("-{} address:{}", &self.config.tag, address),
+            );
+        }
+        self.dimmed.clear();
+        batch.send().await
+    }
+}
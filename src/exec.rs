@@ -0,0 +1,99 @@
+use std::{
+    os::unix::process::CommandExt,
+    process::Stdio,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Context, Result};
+use hyprland::{data::Clients, shared::HyprData};
+
+/// environment variable that tags processes launched by 'hyprkool exec'. children inherit
+/// it, so windows of apps that fork or open late are still recognized
+const TOKEN_VAR: &str = "HYPRKOOL_EXEC";
+
+#[derive(Debug, Clone)]
+struct PendingSpawn {
+    token: String,
+    workspace: String,
+    until: Instant,
+}
+
+/// windows of launched apps that are still expected, and where they go
+#[derive(Debug, Default)]
+pub struct PendingSpawns {
+    pending: Vec<PendingSpawn>,
+}
+
+impl PendingSpawns {
+    /// windows of processes with this token that open in the next timeout go to the workspace
+    pub fn expect(&mut self, token: String, workspace: String, timeout: Duration) {
+        self.pending.push(PendingSpawn {
+            token,
+            workspace,
+            until: Instant::now() + timeout,
+        });
+    }
+
+    /// workspace that the window belongs on if it was launched by 'hyprkool exec'
+    pub async fn workspace_of(&mut self, address: &str) -> Result<Option<String>> {
+        let now = Instant::now();
+        self.pending.retain(|p| p.until > now);
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+        let Some(client) = Clients::get_async()
+            .await?
+            .into_iter()
+            .find(|c| c.address.to_string() == address)
+        else {
+            return Ok(None);
+        };
+        let Some(token) = process_token(client.pid) else {
+            return Ok(None);
+        };
+        Ok(self
+            .pending
+            .iter()
+            .find(|p| p.token == token)
+            .map(|p| p.workspace.clone()))
+    }
+}
+
+/// a token that is unique enough for the apps launched on this machine
+pub fn new_token() -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    format!("{}-{}", std::process::id(), nanos)
+}
+
+/// launches the command with the token in its environment. it keeps running after hyprkool exits
+pub fn launch(command: &[String], token: &str) -> Result<()> {
+    let Some((cmd, args)) = command.split_first() else {
+        return Err(anyhow!("nothing to launch"));
+    };
+    std::process::Command::new(cmd)
+        .args(args)
+        .env(TOKEN_VAR, token)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        // not killed with the terminal's process group
+        .process_group(0)
+        .spawn()
+        .with_context(|| format!("could not launch {:?}", command))?;
+    Ok(())
+}
+
+fn process_token(pid: i32) -> Option<String> {
+    if pid <= 0 {
+        return None;
+    }
+    let environ = std::fs::read(format!("/proc/{}/environ", pid)).ok()?;
+    let prefix = format!("{}=", TOKEN_VAR);
+    environ
+        .split(|&b| b == 0)
+        .map(String::from_utf8_lossy)
+        .find_map(|var| var.strip_prefix(&prefix).map(|t| t.to_owned()))
+}
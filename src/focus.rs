@@ -0,0 +1,184 @@
+use std::{
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use clap::{Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, error, warn};
+
+use crate::{config::FocusConfig, notify};
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum FocusCommand {
+    /// switch to the activity and stay on it for a while
+    Start {
+        #[arg(long, short)]
+        activity: String,
+        #[arg(long, short, default_value_t = 25)]
+        minutes: u64,
+        /// soft notifies when switching to other activities, hard refuses to switch
+        #[arg(long, short, value_enum)]
+        lock: Option<FocusLock>,
+        /// run the dnd_on command from [daemon.focus] for the session
+        #[arg(long, default_value_t = false)]
+        dnd: bool,
+    },
+    /// end the focus session early
+    Stop,
+    /// activity and remaining time of the focus session. prints nothing if there is none
+    Status {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+#[derive(ValueEnum, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FocusLock {
+    Soft,
+    Hard,
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct FocusStatus {
+    pub activity: String,
+    /// seconds left
+    pub remaining: u64,
+    pub lock: Option<FocusLock>,
+}
+
+#[derive(Debug, Clone)]
+struct FocusSession {
+    activity: String,
+    until: Instant,
+    lock: Option<FocusLock>,
+    dnd: bool,
+}
+
+/// a timed session on one activity (like a pomodoro)
+#[derive(Debug, Default)]
+pub struct FocusTimer {
+    config: FocusConfig,
+    session: Option<FocusSession>,
+    /// bumped on every start and stop so that only the timer of the current session ends it
+    generation: Arc<AtomicU64>,
+}
+
+impl FocusTimer {
+    pub fn new(config: FocusConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn start(&mut self, activity: &str, minutes: u64, lock: Option<FocusLock>, dnd: bool) {
+        self.stop();
+        if dnd {
+            if self.config.dnd_on.is_empty() {
+                warn!("no dnd_on command in [daemon.focus]");
+            }
+            run(&self.config.dnd_on);
+        }
+        let duration = Duration::from_secs(minutes * 60);
+        self.session = Some(FocusSession {
+            activity: activity.to_owned(),
+            until: Instant::now() + duration,
+            lock,
+            dnd,
+        });
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let latest = self.generation.clone();
+        let dnd_off = match dnd {
+            true => self.config.dnd_off.clone(),
+            false => Vec::new(),
+        };
+        let message = format!("focus session on {} is over", activity);
+        tokio::spawn(async move {
+            tokio::time::sleep(duration).await;
+            if latest.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            run(&dnd_off);
+            notify::show(&message).await;
+        });
+    }
+
+    /// ends the running session. false if there was none
+    pub fn stop(&mut self) -> bool {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let Some(session) = self.session.take().filter(|s| s.until > Instant::now()) else {
+            return false;
+        };
+        if session.dnd {
+            run(&self.config.dnd_off);
+        }
+        true
+    }
+
+    pub fn status(&self) -> Option<FocusStatus> {
+        let session = self.session.as_ref()?;
+        let remaining = session.until.checked_duration_since(Instant::now())?;
+        Some(FocusStatus {
+            activity: session.activity.clone(),
+            remaining: remaining.as_secs(),
+            lock: session.lock,
+        })
+    }
+
+    /// false if switching from one activity to another breaks a hard lock. both kinds of
+    /// locks notify when the switch leaves the session's activity
+    pub fn check_switch(&self, from: &str, to: &str) -> bool {
+        let Some(status) = self.status() else {
+            return true;
+        };
+        if from == to || status.activity == to || status.lock.is_none() {
+            return true;
+        }
+        let allowed = status.lock != Some(FocusLock::Hard);
+        // moving around outside of it (after a soft lock let you out) is not nagged about
+        if status.activity != from && allowed {
+            return true;
+        }
+        let message = format!(
+            "focus session on {}: {} min left",
+            &status.activity,
+            status.remaining.div_ceil(60)
+        );
+        tokio::spawn(async move { notify::show(&message).await });
+        allowed
+    }
+}
+
+fn run(command: &[String]) {
+    let Some((cmd, args)) = command.split_first() else {
+        return;
+    };
+    debug!("running focus command: {:?}", command);
+    let child = tokio::process::Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            tokio::spawn(async move {
+                match child.wait().await {
+                    Ok(status) if !status.success() => {
+                        error!("focus command failed with {}", status)
+                    }
+                    Ok(_) => {}
+                    Err(e) => error!("focus command failed: {}", e),
+                }
+            });
+        }
+        Err(e) => error!("could not run focus command: {}", e),
+    }
+}
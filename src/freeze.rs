@@ -0,0 +1,129 @@
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use hyprland::{
+    data::{Client, Clients},
+    shared::HyprData,
+};
+
+use crate::{config::FreezeConfig, state::activity_name};
+
+/// stops processes of activities that have not been focused for a while
+#[derive(Debug, Default)]
+pub struct Freezer {
+    config: FreezeConfig,
+    /// workspaces are named without the activity
+    flat_grid: bool,
+    /// activity that is currently focused
+    current: Option<String>,
+    /// when each unfocused activity was left
+    left_at: HashMap<String, Instant>,
+    /// pids stopped in each frozen activity
+    frozen: HashMap<String, Vec<i32>>,
+}
+
+impl Freezer {
+    pub fn new(config: FreezeConfig, flat_grid: bool) -> Self {
+        Self {
+            config,
+            flat_grid,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_frozen(&self, activity: &str) -> bool {
+        self.frozen.contains_key(activity)
+    }
+
+    /// called when an activity gets focus. thaws it if it was frozen
+    pub async fn visit(&mut self, activity: &str) -> Result<()> {
+        if self.current.as_deref() == Some(activity) {
+            return Ok(());
+        }
+        if let Some(prev) = self.current.replace(activity.to_owned()) {
+            self.left_at.insert(prev, Instant::now());
+        }
+        self.left_at.remove(activity);
+        if let Some(pids) = self.frozen.remove(activity) {
+            signal(&pids, libc::SIGCONT).1?;
+        }
+        Ok(())
+    }
+
+    /// freezes every activity that has been unfocused for longer than configured
+    pub async fn freeze_idle(&mut self) -> Result<()> {
+        if !self.config.enable {
+            return Ok(());
+        }
+        let after = Duration::from_secs(self.config.after * 60);
+        let idle = self
+            .left_at
+            .iter()
+            .filter(|(a, t)| t.elapsed() >= after && !self.frozen.contains_key(*a))
+            .map(|(a, _)| a.clone())
+            .collect::<Vec<_>>();
+        if idle.is_empty() {
+            return Ok(());
+        }
+
+        let clients = Clients::get_async().await?;
+        for activity in idle {
+            let inside = |c: &&Client| {
+                activity_name(&c.workspace.name, self.flat_grid) == activity
+                    && c.workspace.name.contains(':')
+            };
+            // a process with a window anywhere else (or a blocklisted one) is still in use
+            let busy = clients
+                .iter()
+                .filter(|c| !inside(c) || self.config.blocklist.contains(&c.class))
+                .map(|c| c.pid)
+                .collect::<HashSet<_>>();
+            let mut pids = clients
+                .iter()
+                .filter(inside)
+                .map(|c| c.pid)
+                .filter(|&pid| pid > 0 && !busy.contains(&pid))
+                .collect::<Vec<_>>();
+            pids.sort();
+            pids.dedup();
+            // the ones that did stop have to be continued later, even if others failed
+            let (stopped, res) = signal(&pids, libc::SIGSTOP);
+            self.frozen.insert(activity, stopped);
+            res?;
+        }
+        Ok(())
+    }
+
+    /// continues all stopped processes. should be called before the daemon exits
+    pub async fn thaw_all(&mut self) -> Result<()> {
+        let pids = self.frozen.drain().flat_map(|(_, p)| p).collect::<Vec<_>>();
+        signal(&pids, libc::SIGCONT).1
+    }
+}
+
+/// sends sig to every pid. processes that already exited are skipped. returns the pids
+/// that got it, along with an error for the ones that did not
+fn signal(pids: &[i32], sig: libc::c_int) -> (Vec<i32>, Result<()>) {
+    let mut sent = Vec::new();
+    let mut failed = Vec::new();
+    for &pid in pids {
+        // SAFETY: kill has no memory safety requirements
+        if unsafe { libc::kill(pid, sig) } == 0 {
+            sent.push(pid);
+            continue;
+        }
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            failed.push(format!("{} ({})", pid, e));
+        }
+    }
+    if !failed.is_empty() {
+        let e = anyhow!("could not send signal {} to {}", sig, failed.join(", "));
+        return (sent, Err(e));
+    }
+    (sent, Ok(()))
+}
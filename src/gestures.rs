@@ -0,0 +1,371 @@
+use std::{process::Stdio, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    process::Command as Process,
+    sync::Mutex,
+};
+use tracing::{debug, error};
+
+use crate::{command::Command, config::GesturesConfig, notify, state::SwitchSource, State};
+
+/// a touchpad swipe as printed by 'libinput debug-events'
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SwipeEvent {
+    Begin {
+        fingers: u32,
+    },
+    /// movement since the last update
+    Update {
+        fingers: u32,
+        dx: f64,
+        dy: f64,
+    },
+    End {
+        fingers: u32,
+        cancelled: bool,
+    },
+}
+
+impl SwipeEvent {
+    /// parses lines like
+    /// " event7   GESTURE_SWIPE_UPDATE    +2.350s  3 -2.41/ 0.00 (-6.03/ 0.00 unaccelerated)".
+    /// None for anything that is not a swipe
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let kind = parts.find(|p| p.starts_with("GESTURE_SWIPE_"))?;
+        let _time = parts.next()?;
+        let fingers = parts.next()?.parse().ok()?;
+        let rest = parts.collect::<Vec<_>>().join(" ");
+        match kind {
+            "GESTURE_SWIPE_BEGIN" => Some(Self::Begin { fingers }),
+            "GESTURE_SWIPE_UPDATE" => {
+                // the accelerated deltas come first, the unaccelerated ones are in ()
+                let accelerated = rest.split('(').next()?;
+                let (dx, dy) = accelerated.split_once('/')?;
+                Some(Self::Update {
+                    fingers,
+                    dx: dx.trim().parse().ok()?,
+                    dy: dy.trim().parse().ok()?,
+                })
+            }
+            "GESTURE_SWIPE_END" => Some(Self::End {
+                fingers,
+                cancelled: rest.contains("cancelled"),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// switches workspaces and activities on touchpad swipes. reads them from libinput, as
+/// hyprland does not tell anyone about gestures
+pub struct GestureDaemon {
+    state: Arc<Mutex<State>>,
+    config: GesturesConfig,
+    notify_errors: bool,
+    /// (fingers, dx, dy) of the swipe going on right now
+    swipe: Option<(u32, f64, f64)>,
+}
+
+impl GestureDaemon {
+    pub async fn new(state: Arc<Mutex<State>>) -> Self {
+        let s = state.lock().await;
+        let config = s.config.daemon.gestures.clone();
+        let notify_errors = s.config.notify_errors;
+        drop(s);
+        Self {
+            state,
+            config,
+            notify_errors,
+            swipe: None,
+        }
+    }
+
+    /// never returns if gestures are not enabled
+    pub async fn run(&mut self) -> Result<()> {
+        if !self.config.enable {
+            return std::future::pending().await;
+        }
+        let (cmd, args) = self
+            .config
+            .command
+            .split_first()
+            .context("daemon.gestures.command is empty")?;
+        let mut child = Process::new(cmd)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .with_context(|| format!("could not run {:?}", &self.config.command))?;
+        let stdout = child.stdout.take().context("no stdout")?;
+        let mut lines = BufReader::new(stdout).lines();
+        self.swipe = None;
+
+        while let Some(line) = lines.next_line().await? {
+            let Some(event) = SwipeEvent::parse(&line) else {
+                continue;
+            };
+            let Some(command) = self.handle(event) else {
+                continue;
+            };
+            if self.state.lock().await.away() {
+                continue;
+            }
+            debug!("swipe: {}", command.name());
+            let res = command
+                .execute_from(self.state.clone(), true, SwitchSource::Gesture, None)
+                .await;
+            if let Err(e) = res {
+                error!("error in gesture: {}", e);
+                if self.notify_errors {
+                    notify::error(&format!("error in gesture: {}", e)).await;
+                }
+            }
+        }
+        let status = child.wait().await?;
+        Err(anyhow!(
+            "{:?} exited ({}). is the user in the input group?",
+            &self.config.command,
+            status
+        ))
+    }
+
+    /// the command to run once a swipe ends
+    pub fn handle(&mut self, event: SwipeEvent) -> Option<Command> {
+        match event {
+            SwipeEvent::Begin { fingers } => {
+                self.swipe = Some((fingers, 0.0, 0.0));
+                None
+            }
+            SwipeEvent::Update { fingers, dx, dy } => {
+                if let Some((f, x, y)) = &mut self.swipe {
+                    if *f == fingers {
+                        *x += dx;
+                        *y += dy;
+                    }
+                }
+                None
+            }
+            SwipeEvent::End {
+                cancelled: true, ..
+            } => {
+                self.swipe = None;
+                None
+            }
+            SwipeEvent::End { .. } => {
+                let (fingers, x, y) = self.swipe.take()?;
+                self.command(fingers, x, y)
+            }
+        }
+    }
+
+    fn command(&self, fingers: u32, x: f64, y: f64) -> Option<Command> {
+        let g = &self.config;
+        if x.abs().max(y.abs()) < g.distance {
+            return None;
+        }
+        // with natural swipes the fingers drag the grid along, so swiping left goes right
+        let (x, y) = match g.natural {
+            true => (-x, -y),
+            false => (x, y),
+        };
+        let horizontal = x.abs() >= y.abs();
+        let cycle = g.cycle;
+        let move_window = false;
+        let through_monitors = false;
+        if fingers == g.activity_fingers {
+            let forward = match horizontal {
+                true => x > 0.0,
+                false => y > 0.0,
+            };
+            return Some(match forward {
+                true => Command::NextActivity {
+                    cycle,
+                    move_window,
+                    mru: false,
+                },
+                false => Command::PrevActivity { cycle, move_window },
+            });
+        }
+        if fingers != g.grid_fingers {
+            return None;
+        }
+        Some(match (horizontal, x > 0.0, y > 0.0) {
+            (true, true, _) => Command::MoveRight {
+                cycle,
+                move_window,
+                through_monitors,
+            },
+            (true, false, _) => Command::MoveLeft {
+                cycle,
+                move_window,
+                through_monitors,
+            },
+            (false, _, true) => Command::MoveDown {
+                cycle,
+                move_window,
+                through_monitors,
+            },
+            (false, _, false) => Command::MoveUp {
+                cycle,
+                move_window,
+                through_monitors,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+
+    fn daemon() -> GestureDaemon {
+        let mut config = Config::default();
+        config.daemon.gestures.enable = true;
+        GestureDaemon {
+            config: config.daemon.gestures.clone(),
+            state: Arc::new(Mutex::new(State::new(config).unwrap())),
+            notify_errors: false,
+            swipe: None,
+        }
+    }
+
+    /// the command for a swipe with these updates
+    fn swipe(d: &mut GestureDaemon, fingers: u32, updates: &[(f64, f64)]) -> Option<String> {
+        d.handle(SwipeEvent::Begin { fingers });
+        for &(dx, dy) in updates {
+            d.handle(SwipeEvent::Update { fingers, dx, dy });
+        }
+        d.handle(SwipeEvent::End {
+            fingers,
+            cancelled: false,
+        })
+        .map(|c| c.name())
+    }
+
+    #[test]
+    fn parses_begin_and_end() {
+        assert_eq!(
+            SwipeEvent::parse(" event7   GESTURE_SWIPE_BEGIN     +2.345s\t3"),
+            Some(SwipeEvent::Begin { fingers: 3 })
+        );
+        assert_eq!(
+            SwipeEvent::parse(" event7   GESTURE_SWIPE_END       +2.500s\t4"),
+            Some(SwipeEvent::End {
+                fingers: 4,
+                cancelled: false
+            })
+        );
+        assert_eq!(
+            SwipeEvent::parse(" event7   GESTURE_SWIPE_END       +2.500s\t3 cancelled"),
+            Some(SwipeEvent::End {
+                fingers: 3,
+                cancelled: true
+            })
+        );
+    }
+
+    #[test]
+    fn parses_updates() {
+        assert_eq!(
+            SwipeEvent::parse(
+                " event7   GESTURE_SWIPE_UPDATE    +2.350s\t3  1.23/ 0.45 ( 2.00/ 0.70 unaccelerated)"
+            ),
+            Some(SwipeEvent::Update {
+                fingers: 3,
+                dx: 1.23,
+                dy: 0.45
+            })
+        );
+        // wide values leave no space around the '/'
+        assert_eq!(
+            SwipeEvent::parse(
+                " event7   GESTURE_SWIPE_UPDATE    +2.350s\t4 12.34/-10.00 (20.00/-9.70 unaccelerated)"
+            ),
+            Some(SwipeEvent::Update {
+                fingers: 4,
+                dx: 12.34,
+                dy: -10.0
+            })
+        );
+    }
+
+    #[test]
+    fn ignores_other_lines() {
+        for line in [
+            "",
+            "-event2   DEVICE_ADDED            Touchpad    seat0 default group7",
+            " event7   GESTURE_PINCH_BEGIN     +1.000s\t2",
+            " event7   GESTURE_SWIPE_UPDATE    +2.350s\t3 garbage",
+            " event7   GESTURE_SWIPE_BEGIN     +2.345s\tthree",
+        ] {
+            assert_eq!(SwipeEvent::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn swipes_move_against_the_fingers() {
+        let mut d = daemon();
+        assert_eq!(
+            swipe(&mut d, 3, &[(-80.0, 5.0), (-40.0, 0.0)]).as_deref(),
+            Some("move-right")
+        );
+        assert_eq!(
+            swipe(&mut d, 3, &[(150.0, 0.0)]).as_deref(),
+            Some("move-left")
+        );
+        assert_eq!(
+            swipe(&mut d, 3, &[(10.0, -150.0)]).as_deref(),
+            Some("move-down")
+        );
+        assert_eq!(
+            swipe(&mut d, 3, &[(0.0, 150.0)]).as_deref(),
+            Some("move-up")
+        );
+        assert_eq!(
+            swipe(&mut d, 4, &[(-150.0, 0.0)]).as_deref(),
+            Some("next-activity")
+        );
+        assert_eq!(
+            swipe(&mut d, 4, &[(150.0, 0.0)]).as_deref(),
+            Some("prev-activity")
+        );
+
+        d.config.natural = false;
+        assert_eq!(
+            swipe(&mut d, 3, &[(-150.0, 0.0)]).as_deref(),
+            Some("move-left")
+        );
+    }
+
+    #[test]
+    fn short_cancelled_and_unknown_swipes_do_nothing() {
+        let mut d = daemon();
+        assert_eq!(swipe(&mut d, 3, &[(-50.0, 0.0)]), None);
+        assert_eq!(swipe(&mut d, 5, &[(-150.0, 0.0)]), None);
+
+        d.handle(SwipeEvent::Begin { fingers: 3 });
+        d.handle(SwipeEvent::Update {
+            fingers: 3,
+            dx: -150.0,
+            dy: 0.0,
+        });
+        let end = d.handle(SwipeEvent::End {
+            fingers: 3,
+            cancelled: true,
+        });
+        assert!(end.is_none());
+        // an end without a begin
+        assert!(d
+            .handle(SwipeEvent::End {
+                fingers: 3,
+                cancelled: false
+            })
+            .is_none());
+    }
+}
@@ -0,0 +1,150 @@
+use std::{
+    process::Stdio,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tracing::{debug, error, warn};
+
+use crate::{
+    config::HooksConfig,
+    notify,
+    state::{activity_name, parse_cell},
+};
+
+/// environment variables hooks keep when clean_env is set
+const KEPT_ENV: [&str; 8] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "XDG_RUNTIME_DIR",
+    "WAYLAND_DISPLAY",
+    "HYPRLAND_INSTANCE_SIGNATURE",
+    "DBUS_SESSION_BUS_ADDRESS",
+];
+
+/// runs the [hooks] commands. they never block switching: they run in the background, are
+/// killed after the timeout and are skipped while too many are still running
+#[derive(Debug, Default)]
+pub struct Hooks {
+    config: HooksConfig,
+    notify_errors: bool,
+    /// workspaces are named without the activity
+    flat_grid: bool,
+    running: Arc<AtomicUsize>,
+}
+
+impl Hooks {
+    pub fn new(config: HooksConfig, notify_errors: bool, flat_grid: bool) -> Self {
+        Self {
+            config,
+            notify_errors,
+            flat_grid,
+            ..Default::default()
+        }
+    }
+
+    /// runs the hooks for a switch from one workspace to another without waiting for them
+    pub fn switched(&self, from: Option<&str>, to: &str) {
+        if from == Some(to) {
+            return;
+        }
+        let hooks = &self.config;
+        let from_activity = from.map(|w| activity_name(w, self.flat_grid));
+        let to_activity = activity_name(to, self.flat_grid);
+
+        let mut commands = vec![hooks.on_workspace_change.as_ref()];
+        if from_activity != Some(to_activity) {
+            commands.push(hooks.on_activity_change.as_ref());
+            commands.push(from_activity.and_then(|a| hooks.on_leave.get(a)));
+            commands.push(hooks.on_enter.get(to_activity));
+        }
+
+        let cell = |w: &str| {
+            parse_cell(w, self.flat_grid)
+                .map(|(x, y)| format!("{},{}", x, y))
+                .unwrap_or_default()
+        };
+        let vars = [
+            ("HYPRKOOL_FROM", from.unwrap_or_default().to_owned()),
+            ("HYPRKOOL_TO", to.to_owned()),
+            (
+                "HYPRKOOL_FROM_ACTIVITY",
+                from_activity.unwrap_or_default().to_owned(),
+            ),
+            ("HYPRKOOL_TO_ACTIVITY", to_activity.to_owned()),
+            ("HYPRKOOL_FROM_CELL", from.map(cell).unwrap_or_default()),
+            ("HYPRKOOL_TO_CELL", cell(to)),
+        ];
+        for command in commands.into_iter().flatten() {
+            self.run(command, &vars);
+        }
+    }
+
+    fn run(&self, command: &str, vars: &[(&str, String)]) {
+        if self.running.load(Ordering::SeqCst) >= self.config.max_running {
+            warn!("too many hooks still running. skipping '{}'", command);
+            return;
+        }
+        debug!("running hook: {}", command);
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(command);
+        if self.config.clean_env {
+            cmd.env_clear();
+            for (k, v) in std::env::vars().filter(|(k, _)| KEPT_ENV.contains(&k.as_str())) {
+                cmd.env(k, v);
+            }
+        }
+        let child = cmd
+            .envs(vars.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                self.failed(format!("could not run hook '{}': {}", command, e));
+                return;
+            }
+        };
+
+        self.running.fetch_add(1, Ordering::SeqCst);
+        let running = self.running.clone();
+        let timeout = Duration::from_millis(self.config.timeout_ms);
+        let notify_errors = self.notify_errors;
+        let command = command.to_owned();
+        tokio::spawn(async move {
+            let failure = match tokio::time::timeout(timeout, child.wait()).await {
+                Ok(Ok(status)) if status.success() => None,
+                Ok(Ok(status)) => Some(format!("hook '{}' failed with {}", command, status)),
+                Ok(Err(e)) => Some(format!("hook '{}' failed: {}", command, e)),
+                // dropping the child kills it
+                Err(_) => Some(format!(
+                    "hook '{}' did not finish in {}ms. killed it",
+                    command,
+                    timeout.as_millis()
+                )),
+            };
+            drop(child);
+            running.fetch_sub(1, Ordering::SeqCst);
+            if let Some(failure) = failure {
+                error!("{}", &failure);
+                if notify_errors {
+                    notify::error(&failure).await;
+                }
+            }
+        });
+    }
+
+    fn failed(&self, message: String) {
+        error!("{}", &message);
+        if self.notify_errors {
+            tokio::spawn(async move { notify::error(&message).await });
+        }
+    }
+}
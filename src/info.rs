@@ -1,9 +1,15 @@
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    future::Future,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
 use clap::{arg, Subcommand};
 use hyprland::{
-    data::{Client, Clients, Workspace},
+    data::{Client, Clients, Monitor, Monitors, Workspace, Workspaces},
     event_listener::{EventListener, WindowEventData},
     shared::{
         Address, HyprData, HyprDataActive, HyprDataActiveOptional, HyprDataVec, WorkspaceType,
@@ -15,12 +21,14 @@ use tokio::{
     io::AsyncWriteExt,
     net::UnixStream,
     sync::{
-        mpsc::{Receiver, Sender},
-        Mutex,
+        broadcast::error::RecvError,
+        mpsc::{Receiver, Sender, UnboundedReceiver, UnboundedSender},
+        oneshot, Mutex,
     },
 };
+use tracing::{debug, error};
 
-use crate::{Message, State};
+use crate::{focus::FocusStatus, state::parse_cell, Message, State};
 
 #[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum InfoCommand {
@@ -29,8 +37,25 @@ pub enum InfoCommand {
 
     Submap,
     Activities,
+    /// recently visited activities. most recent first (needs daemon)
+    ActivityHistory,
+    /// switch_started/switch_completed events along with what caused them (needs daemon)
+    SwitchEvents,
     Workspaces,
     AllWorkspaces,
+    /// workspaces of the current activity in the json format of the `hyprland-workspaces` tool,
+    /// so bar configs written for it work with hyprkool
+    HyprlandWorkspaces,
+    /// focused monitor, activity and workspace along with the whole grid of the current activity
+    Status,
+    /// focused workspace split into activity, x and y, along with its monitor
+    ActiveWorkspace,
+    /// focused activity with its index, icon and display name. null if not on a hyprkool workspace
+    ActiveActivity,
+    /// grid size and the workspace names of every activity, row by row
+    Grid,
+    /// every monitor with the workspace it shows, split like in active-workspace
+    Monitors,
     ActiveWindow {
         /// try to find smallest icon bigger/equal to this size in px
         /// default is 0
@@ -57,34 +82,162 @@ pub enum InfoCommand {
     },
 }
 
+/// messages are written by a task that owns the output, so that nothing (like handlers
+/// holding the state lock) waits on slow clients. a client that falls behind only gets the
+/// newest messages
 #[derive(Clone, Debug)]
 struct InfoOutput {
-    stream: InfoOutputStream,
-    tx: Sender<()>,
+    tx: UnboundedSender<Output>,
+}
+
+#[derive(Debug)]
+enum Output {
+    Mesg(String),
+    /// answered once everything before it is written
+    Flush(oneshot::Sender<()>),
 }
+
 impl InfoOutput {
-    fn new(stream: InfoOutputStream) -> (Self, Receiver<()>) {
-        let (tx, rx) = tokio::sync::mpsc::channel::<()>(1);
-        (Self { stream, tx }, rx)
+    /// older messages are skipped when a client is this many behind
+    const BACKLOG_LIMIT: usize = 32;
+    /// clients that do not take a message for this long are dropped
+    const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    fn new(stream: InfoOutputStream, diff: bool) -> (Self, Receiver<()>) {
+        let (exit_tx, exit_rx) = tokio::sync::mpsc::channel::<()>(1);
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let diff = diff.then(DiffState::default);
+        tokio::spawn(Self::write_loop(stream, diff, rx, exit_tx));
+        (Self { tx }, exit_rx)
     }
+
     async fn send_mesg(&self, mesg: String) -> Result<()> {
-        self.stream.send_mesg(mesg, self.tx.clone()).await
+        // fails only if the client is gone, which exit takes care of
+        let _ = self.tx.send(Output::Mesg(mesg));
+        Ok(())
+    }
+
+    /// waits till everything sent so far is written
+    async fn flush(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Output::Flush(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+
+    async fn write_loop(
+        stream: InfoOutputStream,
+        mut diff: Option<DiffState>,
+        mut rx: UnboundedReceiver<Output>,
+        exit: Sender<()>,
+    ) {
+        let mut pending = VecDeque::new();
+        let mut flushes = Vec::new();
+        while let Some(out) = rx.recv().await {
+            let mut next = Some(out);
+            while let Some(out) = next {
+                match out {
+                    Output::Mesg(mesg) => pending.push_back(mesg),
+                    Output::Flush(tx) => flushes.push(tx),
+                }
+                next = rx.try_recv().ok();
+            }
+            if pending.len() > Self::BACKLOG_LIMIT {
+                let skipped = pending.len() - Self::BACKLOG_LIMIT;
+                debug!("info client is behind. skipping {} messages", skipped);
+                pending.drain(..skipped);
+            }
+            for mesg in pending.drain(..) {
+                // diffs are against what the client actually got
+                let mesg = match diff.as_mut().map(|d| d.next(&mesg)) {
+                    None => mesg,
+                    Some(Ok(Some(mesg))) => mesg,
+                    Some(Ok(None)) => continue,
+                    Some(Err(e)) => {
+                        error!("could not diff info message: {}", e);
+                        continue;
+                    }
+                };
+                let res = tokio::time::timeout(Self::WRITE_TIMEOUT, stream.send_mesg(mesg)).await;
+                if !matches!(res, Ok(Ok(()))) {
+                    let _ = exit.send(()).await;
+                    return;
+                }
+            }
+            for tx in flushes.drain(..) {
+                let _ = tx.send(());
+            }
+        }
+    }
+}
+
+/// turns a stream of json messages into json merge patches (RFC 7386)
+/// with a full snapshot every [`DiffState::FULL_SNAPSHOT_EVERY`] messages
+#[derive(Debug, Default)]
+struct DiffState {
+    last: Option<serde_json::Value>,
+    since_full: usize,
+}
+impl DiffState {
+    const FULL_SNAPSHOT_EVERY: usize = 20;
+
+    fn next(&mut self, mesg: &str) -> Result<Option<String>> {
+        let new = serde_json::from_str::<serde_json::Value>(mesg)?;
+        let out = match &self.last {
+            Some(last) if self.since_full < Self::FULL_SNAPSHOT_EVERY => {
+                let Some(patch) = merge_patch_diff(last, &new) else {
+                    return Ok(None);
+                };
+                self.since_full += 1;
+                serde_json::json!({ "patch": patch })
+            }
+            _ => {
+                self.since_full = 0;
+                serde_json::json!({ "full": new })
+            }
+        };
+        self.last = Some(new);
+        Ok(Some(serde_json::to_string(&out)?))
+    }
+}
+
+/// returns the merge patch that turns old into new. None if nothing changed
+fn merge_patch_diff(old: &serde_json::Value, new: &serde_json::Value) -> Option<serde_json::Value> {
+    use serde_json::Value;
+
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let mut patch = serde_json::Map::new();
+            for (k, v) in new.iter() {
+                match old.get(k) {
+                    Some(ov) => {
+                        if let Some(p) = merge_patch_diff(ov, v) {
+                            patch.insert(k.clone(), p);
+                        }
+                    }
+                    None => {
+                        patch.insert(k.clone(), v.clone());
+                    }
+                }
+            }
+            for k in old.keys().filter(|k| !new.contains_key(*k)) {
+                patch.insert(k.clone(), Value::Null);
+            }
+            (!patch.is_empty()).then_some(Value::Object(patch))
+        }
+        // merge patches replace everything that is not an object
+        (old, new) => (old != new).then(|| new.clone()),
     }
 }
 
 #[derive(Clone, Debug)]
 pub enum InfoOutputStream {
-    // TODO: jonhoo recommends not using Arc<Mutex<IO_promitive>>
-    // - [decrusting tokio](https://youtu.be/o2ob8zkeq2s?si=SPFOQtLSiBuiSLZJ&t=6020)
-    // - use actor pattern instead
-    //   - keep a separate task that owns this socket and use channels to communicate
-    // could also try to use tokio::sync::Notify
-    //  - very cool usage
-    //  - run a loop just readig and printing state whenever the notify is woken
-    //    the notify 'waker' can be cloned and sent everywhere like inside hyprland callbacks
-    // also tokio::sync_or_something::watch might be nice here
+    // only the writer task of an InfoOutput writes to it. the mutex is there because info
+    // commands that fail are restarted on the same socket
     Stream(Arc<Mutex<UnixStream>>),
     Stdout,
+    /// collects the messages. for answering rpc requests
+    Buffer(Arc<std::sync::Mutex<Vec<String>>>),
 }
 impl InfoOutputStream {
     async fn _send_mesg(stream: &Arc<Mutex<UnixStream>>, mesg: String) -> Result<()> {
@@ -95,18 +248,18 @@ impl InfoOutputStream {
         Ok(())
     }
 
-    async fn send_mesg(&self, mesg: String, tx: Sender<()>) -> Result<()> {
+    async fn send_mesg(&self, mesg: String) -> Result<()> {
         match self {
-            InfoOutputStream::Stream(s) => {
-                if Self::_send_mesg(s, mesg).await.is_err() {
-                    tx.send(()).await?;
-                }
-            }
+            InfoOutputStream::Stream(s) => Self::_send_mesg(s, mesg).await,
             InfoOutputStream::Stdout => {
                 println!("{}", mesg);
+                Ok(())
+            }
+            InfoOutputStream::Buffer(b) => {
+                b.lock().expect("info buffer lock poisoned").push(mesg);
+                Ok(())
             }
         }
-        Ok(())
     }
 }
 
@@ -116,9 +269,12 @@ impl InfoCommand {
         stream: InfoOutputStream,
         state: Arc<Mutex<State>>,
         monitor: bool,
+        diff: bool,
     ) -> Result<()> {
         let mut el = EventListener::new();
-        let (stream, mut exit) = InfoOutput::new(stream);
+        let (stream, mut exit) = InfoOutput::new(stream, diff);
+        // the handlers below take stream
+        let output = stream.clone();
 
         match self {
             InfoCommand::WaybarActivityStatus => {
@@ -127,9 +283,26 @@ impl InfoCommand {
                     name: String,
                     stream: InfoOutput,
                 ) -> Result<()> {
+                    let windows = Workspaces::get_async()
+                        .await?
+                        .into_iter()
+                        .map(|w| (w.name, w.windows))
+                        .collect::<HashMap<_, _>>();
                     let state = state.lock().await;
-                    for a in state.get_activity_status_repr(&name).into_iter() {
-                        let msg = serde_json::to_string(&WaybarText { text: a })?;
+                    for a in state.get_activity_status_repr(&name, &windows).into_iter() {
+                        let breadcrumbs = state.recent_activities(3);
+                        let tooltip = state
+                            .labels
+                            .get(&name)
+                            .cloned()
+                            .into_iter()
+                            .chain((!breadcrumbs.is_empty()).then(|| breadcrumbs.join(" < ")))
+                            .collect::<Vec<_>>();
+                        let msg = serde_json::to_string(&WaybarText {
+                            text: a,
+                            tooltip: (!tooltip.is_empty()).then(|| tooltip.join("\n")),
+                            position: GridPosition::of(&state, &name),
+                        })?;
                         stream.send_mesg(msg).await?;
                     }
                     Ok(())
@@ -138,12 +311,189 @@ impl InfoCommand {
                 let workspace = Workspace::get_active_async().await?;
                 print_state(state.clone(), workspace.name, stream.clone()).await?;
 
+                let (s, st) = (stream.clone(), state.clone());
                 el.add_workspace_change_handler(move |e| match e {
                     WorkspaceType::Regular(name) => {
-                        tokio::spawn(print_state(state.clone(), name, stream.clone()));
+                        tokio::spawn(print_state(st.clone(), name, s.clone()));
                     }
                     WorkspaceType::Special(..) => {}
                 });
+                add_window_change_handlers(&mut el, move || {
+                    let (state, stream) = (state.clone(), stream.clone());
+                    async move {
+                        let workspace = Workspace::get_active_async().await?;
+                        print_state(state, workspace.name, stream).await
+                    }
+                });
+            }
+            InfoCommand::Status => {
+                async fn print_status(stream: InfoOutput, state: Arc<Mutex<State>>) -> Result<()> {
+                    let workspace = Workspace::get_active_async().await?;
+                    let monitor = Monitor::get_active_async().await?;
+                    let windows = Workspaces::get_async()
+                        .await?
+                        .into_iter()
+                        .map(|w| (w.name, w.windows))
+                        .collect::<HashMap<_, _>>();
+                    let state = state.lock().await;
+                    let activity_index = state.workspace_activity_index(&workspace.name);
+                    let activities = state
+                        .activities
+                        .iter()
+                        .enumerate()
+                        .map(|(i, name)| {
+                            let (icon, display) = state.activity_meta(name);
+                            ActivityStatus {
+                                name: name.clone(),
+                                icon,
+                                display,
+                                focused: Some(i) == activity_index,
+                                frozen: state.freezer.is_frozen(name),
+                            }
+                        })
+                        .collect();
+                    let grid = activity_index
+                        .map(|i| {
+                            state
+                                .workspace_rows(i)
+                                .map(|row| {
+                                    let mut row = row
+                                        .iter()
+                                        .map(|w| CellStatus {
+                                            name: w.clone(),
+                                            focused: w == &workspace.name,
+                                            windows: windows.get(w).copied().unwrap_or(0),
+                                            label: state.labels.get(w).cloned(),
+                                            named_focus: {
+                                                let mut names = state
+                                                    .named_focii
+                                                    .iter()
+                                                    .filter(|(_, v)| *v == w)
+                                                    .map(|(k, _)| k.clone())
+                                                    .collect::<Vec<_>>();
+                                                names.sort();
+                                                names
+                                            },
+                                        })
+                                        .collect::<Vec<_>>();
+                                    if state.config.invert_horizontal {
+                                        row.reverse();
+                                    }
+                                    row
+                                })
+                                .collect()
+                        })
+                        .unwrap_or_default();
+                    let status = Status {
+                        monitor: monitor.name,
+                        activity: activity_index.map(|i| state.activities[i].clone()),
+                        workspace: workspace.name.clone(),
+                        activities,
+                        grid,
+                        focus: state.focus.status(),
+                        position: GridPosition::of(&state, &workspace.name),
+                    };
+                    stream.send_mesg(serde_json::to_string(&status)?).await?;
+                    Ok(())
+                }
+
+                print_status(stream.clone(), state.clone()).await?;
+
+                let (s, st) = (stream.clone(), state.clone());
+                el.add_workspace_change_handler(move |_| {
+                    tokio::spawn(print_status(s.clone(), st.clone()));
+                });
+                let (s, st) = (stream.clone(), state.clone());
+                el.add_active_monitor_change_handler(move |_| {
+                    tokio::spawn(print_status(s.clone(), st.clone()));
+                });
+                add_window_change_handlers(&mut el, move || {
+                    print_status(stream.clone(), state.clone())
+                });
+            }
+            InfoCommand::ActiveWorkspace | InfoCommand::Monitors => {
+                async fn print_monitors(
+                    stream: InfoOutput,
+                    state: Arc<Mutex<State>>,
+                    active_only: bool,
+                ) -> Result<()> {
+                    let monitors = Monitors::get_async().await?;
+                    let state = state.lock().await;
+                    let monitors = monitors
+                        .into_iter()
+                        .map(|m| MonitorWorkspace {
+                            monitor: m.name,
+                            focused: m.focused,
+                            workspace: ParsedWorkspace::of(&state, &m.active_workspace.name),
+                        })
+                        .collect::<Vec<_>>();
+                    let mesg = match active_only {
+                        true => match monitors.into_iter().find(|m| m.focused) {
+                            Some(m) => serde_json::to_string(&m)?,
+                            None => return Ok(()),
+                        },
+                        false => serde_json::to_string(&monitors)?,
+                    };
+                    stream.send_mesg(mesg).await?;
+                    Ok(())
+                }
+
+                let active_only = matches!(self, InfoCommand::ActiveWorkspace);
+                print_monitors(stream.clone(), state.clone(), active_only).await?;
+                add_focus_change_handlers(&mut el, move || {
+                    print_monitors(stream.clone(), state.clone(), active_only)
+                });
+            }
+            InfoCommand::ActiveActivity => {
+                async fn print_activity(
+                    stream: InfoOutput,
+                    state: Arc<Mutex<State>>,
+                ) -> Result<()> {
+                    let workspace = Workspace::get_active_async().await?;
+                    let state = state.lock().await;
+                    let activity = state.workspace_activity_index(&workspace.name).map(|i| {
+                        let name = state.activities[i].clone();
+                        let (icon, display) = state.activity_meta(&name);
+                        ActiveActivity {
+                            index: i,
+                            name,
+                            icon,
+                            display,
+                        }
+                    });
+                    stream.send_mesg(serde_json::to_string(&activity)?).await?;
+                    Ok(())
+                }
+
+                print_activity(stream.clone(), state.clone()).await?;
+                add_focus_change_handlers(&mut el, move || {
+                    print_activity(stream.clone(), state.clone())
+                });
+            }
+            InfoCommand::Grid => {
+                async fn print_grid(stream: InfoOutput, state: Arc<Mutex<State>>) -> Result<()> {
+                    let state = state.lock().await;
+                    let (cols, rows) = state.config.workspaces;
+                    let grid = GridInfo {
+                        cols,
+                        rows,
+                        invert_horizontal: state.config.invert_horizontal,
+                        activities: (0..state.activities.len())
+                            .map(|i| ActivityGrid {
+                                name: state.activities[i].clone(),
+                                workspaces: state.workspace_rows(i).collect(),
+                            })
+                            .collect(),
+                    };
+                    stream.send_mesg(serde_json::to_string(&grid)?).await?;
+                    Ok(())
+                }
+
+                print_grid(stream.clone(), state.clone()).await?;
+                // grid resizes and new activities come with a workspace switch
+                el.add_workspace_change_handler(move |_| {
+                    tokio::spawn(print_grid(stream.clone(), state.clone()));
+                });
             }
             InfoCommand::WaybarActiveWindow => {
                 let windows = Arc::new(Mutex::new(Clients::get_async().await?));
@@ -157,6 +507,8 @@ impl InfoCommand {
                     let Some(addr) = addr else {
                         let w = WaybarText {
                             text: "Hyprland".to_owned(),
+                            tooltip: None,
+                            position: None,
                         };
                         let msg = serde_json::to_string(&w)?;
                         stream.send_mesg(msg).await?;
@@ -171,6 +523,8 @@ impl InfoCommand {
 
                     let msg = serde_json::to_string(&WaybarText {
                         text: w.map(|w| w.initial_title).unwrap(),
+                        tooltip: None,
+                        position: None,
                     })?;
 
                     stream.send_mesg(msg).await?;
@@ -374,6 +728,7 @@ impl InfoCommand {
                     else {
                         return Ok(());
                     };
+                    let pinned = pinned_workspaces(&state).await?;
 
                     let mut activity = Vec::new();
                     let nx = state.config.workspaces.0 as usize;
@@ -386,15 +741,17 @@ impl InfoCommand {
                             focii.insert(v.clone(), vec![k.clone()]);
                         }
                     });
-                    for (i, w) in state.workspaces[activity_index].iter().enumerate() {
+                    for (i, w) in state.workspace_names(activity_index).enumerate() {
                         if i % nx == 0 && i > 0 {
                             activity.push(wss);
                             wss = Vec::new();
                         }
                         let mut ws = WorkspaceStatus {
-                            name: w.to_owned(),
                             focused: false,
-                            named_focus: focii.get(w).cloned().unwrap_or_default(),
+                            named_focus: focii.get(&w).cloned().unwrap_or_default(),
+                            pinned: pinned.contains(&w),
+                            label: state.labels.get(&w).cloned(),
+                            name: w,
                         };
                         if i == workspace_index {
                             ws.focused = true;
@@ -402,6 +759,10 @@ impl InfoCommand {
                         wss.push(ws);
                     }
                     activity.push(wss);
+                    // rows go right to left if the grid is mirrored
+                    if state.config.invert_horizontal {
+                        activity.iter_mut().for_each(|row| row.reverse());
+                    }
 
                     let mesg = serde_json::to_string(&activity)?;
                     stream.send_mesg(mesg).await?;
@@ -411,6 +772,62 @@ impl InfoCommand {
                 let workspace = Workspace::get_active_async().await?;
                 print_state(stream.clone(), state.clone(), workspace.name).await?;
 
+                let s = stream.clone();
+                let st = state.clone();
+                add_window_change_handlers(&mut el, move || {
+                    let s = s.clone();
+                    let st = st.clone();
+                    async move {
+                        let workspace = Workspace::get_active_async().await?;
+                        print_state(s, st, workspace.name).await
+                    }
+                });
+                el.add_workspace_change_handler(move |e| match e {
+                    WorkspaceType::Regular(name) => {
+                        tokio::spawn(print_state(stream.clone(), state.clone(), name));
+                    }
+                    WorkspaceType::Special(..) => {}
+                });
+            }
+            InfoCommand::HyprlandWorkspaces => {
+                async fn print_state(
+                    stream: InfoOutput,
+                    state: Arc<Mutex<State>>,
+                    name: String,
+                ) -> Result<()> {
+                    let state = state.lock().await;
+                    let Some((activity_index, Some(_))) = state.get_indices(&name) else {
+                        return Ok(());
+                    };
+                    let workspaces = state
+                        .workspace_names(activity_index)
+                        .enumerate()
+                        .map(|(i, w)| {
+                            let id = i + 1;
+                            let active = w == name;
+                            let class = match active {
+                                true => {
+                                    format!("workspace-button w{} workspace-active wa{}", id, id)
+                                }
+                                false => format!("workspace-button w{}", id),
+                            };
+                            HyprlandWorkspace {
+                                active,
+                                class,
+                                id,
+                                name: w,
+                            }
+                        })
+                        .collect::<Vec<_>>();
+
+                    let mesg = serde_json::to_string(&workspaces)?;
+                    stream.send_mesg(mesg).await?;
+                    Ok(())
+                }
+
+                let workspace = Workspace::get_active_async().await?;
+                print_state(stream.clone(), state.clone(), workspace.name).await?;
+
                 el.add_workspace_change_handler(move |e| match e {
                     WorkspaceType::Regular(name) => {
                         tokio::spawn(print_state(stream.clone(), state.clone(), name));
@@ -427,6 +844,7 @@ impl InfoCommand {
                     name: String,
                 ) -> Result<()> {
                     let state = state.lock().await;
+                    let pinned = pinned_workspaces(&state).await?;
                     let mut activities = Vec::new();
                     let mut focii = HashMap::<String, Vec<String>>::new();
                     state.named_focii.iter().for_each(|(k, v)| {
@@ -440,22 +858,27 @@ impl InfoCommand {
                         let mut activity = Vec::new();
                         let nx = state.config.workspaces.0 as usize;
                         let mut wss = Vec::new();
-                        for (i, w) in state.workspaces[i].iter().enumerate() {
+                        for (i, w) in state.workspace_names(i).enumerate() {
                             if i % nx == 0 && i > 0 {
                                 activity.push(wss);
                                 wss = Vec::new();
                             }
                             let mut ws = WorkspaceStatus {
-                                name: w.to_owned(),
                                 focused: false,
-                                named_focus: focii.get(w).cloned().unwrap_or_default(),
+                                named_focus: focii.get(&w).cloned().unwrap_or_default(),
+                                pinned: pinned.contains(&w),
+                                label: state.labels.get(&w).cloned(),
+                                name: w,
                             };
-                            if w == &name {
+                            if ws.name == name {
                                 ws.focused = true;
                             }
                             wss.push(ws);
                         }
                         activity.push(wss);
+                        if state.config.invert_horizontal {
+                            activity.iter_mut().for_each(|row| row.reverse());
+                        }
                         activities.push(activity);
                     }
 
@@ -467,6 +890,16 @@ impl InfoCommand {
                 let workspace = Workspace::get_active_async().await?;
                 print_state(stream.clone(), state.clone(), workspace.name).await?;
 
+                let s = stream.clone();
+                let st = state.clone();
+                add_window_change_handlers(&mut el, move || {
+                    let s = s.clone();
+                    let st = st.clone();
+                    async move {
+                        let workspace = Workspace::get_active_async().await?;
+                        print_state(s, st, workspace.name).await
+                    }
+                });
                 el.add_workspace_change_handler(move |e| match e {
                     WorkspaceType::Regular(name) => {
                         tokio::spawn(print_state(stream.clone(), state.clone(), name));
@@ -489,9 +922,15 @@ impl InfoCommand {
                     let acs = state
                         .activities
                         .iter()
-                        .map(|name| ActivityStatus {
-                            name: name.into(),
-                            focused: &w == name,
+                        .map(|name| {
+                            let (icon, display) = state.activity_meta(name);
+                            ActivityStatus {
+                                name: name.into(),
+                                icon,
+                                display,
+                                focused: &w == name,
+                                frozen: state.freezer.is_frozen(name),
+                            }
                         })
                         .collect::<Vec<_>>();
                     let mesg = serde_json::to_string(&acs)?;
@@ -514,6 +953,37 @@ impl InfoCommand {
                     tokio::spawn(print_state(stream.clone(), w.to_owned(), state.clone()));
                 });
             }
+            InfoCommand::SwitchEvents => {
+                let mut events = state.lock().await.switch_events.subscribe();
+                let stream = stream.clone();
+                tokio::spawn(async move {
+                    loop {
+                        match events.recv().await {
+                            Ok(e) => stream.send_mesg(serde_json::to_string(&e)?).await?,
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => break,
+                        }
+                    }
+                    Result::<()>::Ok(())
+                });
+            }
+            InfoCommand::ActivityHistory => {
+                async fn print_state(stream: InfoOutput, state: Arc<Mutex<State>>) -> Result<()> {
+                    let state = state.lock().await;
+                    let history = state.recent_activities(state.activity_history.len());
+                    let mesg = serde_json::to_string(&history)?;
+                    stream.send_mesg(mesg).await?;
+                    Ok(())
+                }
+                print_state(stream.clone(), state.clone()).await?;
+
+                el.add_workspace_change_handler(move |e| match e {
+                    WorkspaceType::Regular(..) => {
+                        tokio::spawn(print_state(stream.clone(), state.clone()));
+                    }
+                    WorkspaceType::Special(..) => {}
+                });
+            }
         }
 
         if monitor {
@@ -524,6 +994,7 @@ impl InfoCommand {
                 _ = exit.recv() => {}
             }
         }
+        output.flush().await;
 
         Ok(())
     }
@@ -532,12 +1003,134 @@ impl InfoCommand {
 #[derive(Deserialize, Serialize, Debug)]
 struct WaybarText {
     text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tooltip: Option<String>,
+    #[serde(flatten)]
+    position: Option<GridPosition>,
 }
 
 #[derive(Serialize, Debug)]
 struct ActivityStatus {
     name: String,
+    icon: Option<String>,
+    display: Option<String>,
     focused: bool,
+    /// processes of this activity are stopped
+    frozen: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct Status {
+    monitor: String,
+    /// None if not on a hyprkool activity
+    activity: Option<String>,
+    workspace: String,
+    activities: Vec<ActivityStatus>,
+    /// rows of the current activity's grid, top to bottom. left to right as seen on screen
+    grid: Vec<Vec<CellStatus>>,
+    /// running focus session
+    focus: Option<FocusStatus>,
+    #[serde(flatten)]
+    position: Option<GridPosition>,
+}
+
+/// a workspace name split the way hyprkool reads it
+#[derive(Serialize, Debug)]
+struct ParsedWorkspace {
+    workspace: String,
+    /// None if not a hyprkool workspace
+    activity: Option<String>,
+    /// column of the cell starting at 1, as in the workspace name. None if not a cell
+    x: Option<u32>,
+    /// row of the cell starting at 1
+    y: Option<u32>,
+}
+impl ParsedWorkspace {
+    fn of(state: &State, workspace_name: &str) -> Self {
+        let indices = state.get_indices(workspace_name);
+        let cell = match indices {
+            Some((_, Some(_))) => parse_cell(workspace_name, state.config.flat_grid),
+            _ => None,
+        };
+        Self {
+            workspace: workspace_name.to_owned(),
+            activity: indices.map(|(i, _)| state.activities[i].clone()),
+            x: cell.map(|(x, _)| x),
+            y: cell.map(|(_, y)| y),
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct MonitorWorkspace {
+    monitor: String,
+    focused: bool,
+    #[serde(flatten)]
+    workspace: ParsedWorkspace,
+}
+
+#[derive(Serialize, Debug)]
+struct ActiveActivity {
+    index: usize,
+    name: String,
+    icon: Option<String>,
+    display: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct GridInfo {
+    cols: u32,
+    rows: u32,
+    /// rows are drawn right to left
+    invert_horizontal: bool,
+    activities: Vec<ActivityGrid>,
+}
+
+#[derive(Serialize, Debug)]
+struct ActivityGrid {
+    name: String,
+    /// rows top to bottom, cells in the order of x
+    workspaces: Vec<Vec<String>>,
+}
+
+/// where a workspace is in the grid, for drawing it without parsing the rendered text
+#[derive(Deserialize, Serialize, Debug)]
+struct GridPosition {
+    activity_index: usize,
+    /// column of the cell starting at 1, as in the workspace name (not flipped by invert_horizontal)
+    x: u32,
+    /// row of the cell starting at 1
+    y: u32,
+    rows: u32,
+    cols: u32,
+}
+impl GridPosition {
+    /// None if the workspace is not a cell of an activity
+    fn of(state: &State, workspace_name: &str) -> Option<Self> {
+        let (activity_index, Some(_)) = state.get_indices(workspace_name)? else {
+            return None;
+        };
+        let (x, y) = parse_cell(workspace_name, state.config.flat_grid)?;
+        let (cols, rows) = state.config.workspaces;
+        Some(Self {
+            activity_index,
+            x,
+            y,
+            rows,
+            cols,
+        })
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct CellStatus {
+    name: String,
+    focused: bool,
+    windows: u16,
+    /// set with 'hyprkool label set'
+    label: Option<String>,
+    /// aliases of the cell (named_focii)
+    named_focus: Vec<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -545,6 +1138,77 @@ struct WorkspaceStatus {
     name: String,
     focused: bool,
     named_focus: Vec<String>,
+    /// has a window of one of the pinned apps
+    pinned: bool,
+    /// set with 'hyprkool label set'
+    label: Option<String>,
+}
+
+/// workspace in the shape that `hyprland-workspaces` prints
+#[derive(Serialize, Debug)]
+struct HyprlandWorkspace {
+    active: bool,
+    class: String,
+    /// cell number in the activity, row by row starting at 1
+    id: usize,
+    name: String,
+}
+
+/// names of workspaces that have a window of one of the pinned apps
+async fn pinned_workspaces(state: &State) -> Result<HashSet<String>> {
+    let apps = &state.config.pinned_apps;
+    if apps.is_empty() {
+        return Ok(HashSet::new());
+    }
+    let pinned = Clients::get_async()
+        .await?
+        .into_iter()
+        .filter(|c| apps.iter().any(|a| a == &c.class || a == &c.initial_class))
+        .map(|c| c.workspace.name)
+        .collect();
+    Ok(pinned)
+}
+
+/// calls f whenever a different workspace or monitor gets focus, or monitors come and go
+fn add_focus_change_handlers<F, Fut>(el: &mut EventListener, f: F)
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let g = f.clone();
+    el.add_workspace_change_handler(move |_| {
+        tokio::spawn(g());
+    });
+    let g = f.clone();
+    el.add_active_monitor_change_handler(move |_| {
+        tokio::spawn(g());
+    });
+    let g = f.clone();
+    el.add_monitor_added_handler(move |_| {
+        tokio::spawn(g());
+    });
+    el.add_monitor_removed_handler(move |_| {
+        tokio::spawn(f());
+    });
+}
+
+/// calls f whenever a window is opened, closed or moved
+fn add_window_change_handlers<F, Fut>(el: &mut EventListener, f: F)
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<()>> + Send + 'static,
+{
+    let g = f.clone();
+    el.add_window_open_handler(move |_| {
+        tokio::spawn(g());
+    });
+    let g = f.clone();
+    el.add_window_close_handler(move |_| {
+        tokio::spawn(g());
+    });
+    el.add_window_moved_handler(move |_| {
+        tokio::spawn(f());
+    });
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -0,0 +1,276 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use clap::Subcommand;
+use hyprland::keyword::{Keyword, OptionValue};
+use serde::{Deserialize, Serialize};
+
+use crate::batch::DispatchBatch;
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum KeywordsCommand {
+    /// hyprland keywords and rules set by hyprkool, oldest first, and the values they had before
+    List {
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+    /// set keywords back to their values from before hyprkool changed them. hyprkool leaves
+    /// them alone till the daemon restarts
+    Revert {
+        #[arg(required_unless_present("all"), conflicts_with("all"))]
+        key: Option<String>,
+
+        #[arg(long, default_value_t = false)]
+        all: bool,
+    },
+}
+
+/// one keyword or rule that hyprkool sent to hyprland
+#[derive(Serialize, Debug, Clone)]
+pub struct KeywordChange {
+    /// counts up with every change
+    pub version: u64,
+    pub key: String,
+    pub value: String,
+    /// what set it. a workspace name, "restore", "revert" or a feature like "dim"
+    pub source: String,
+}
+
+/// a keyword that is currently different from what it was before hyprkool touched it
+#[derive(Serialize, Debug, Clone)]
+pub struct ChangedKeyword {
+    pub key: String,
+    /// None for rules, they can't be taken back without reloading the hyprland config
+    pub original: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct KeywordListing {
+    changed: Vec<ChangedKeyword>,
+    reverted: Vec<String>,
+    log: Vec<KeywordChange>,
+}
+
+/// hyprland keywords changed by hyprkool along with their values from before, so that
+/// they can be restored. also keeps a log of every change
+#[derive(Debug, Default)]
+pub struct KeywordLedger {
+    original: HashMap<String, String>,
+    /// values hyprkool set last. keys that already have the value are not sent again
+    current: HashMap<String, String>,
+    /// keys set by workspace overrides. other changed keys are left alone by [Self::enter]
+    overridden: HashSet<String>,
+    /// rules added by hyprkool. (key, value)
+    rules: Vec<(String, String)>,
+    /// keywords reverted by the user. they are not touched anymore
+    reverted: HashSet<String>,
+    log: VecDeque<KeywordChange>,
+    version: u64,
+    /// workspace whose overrides are applied
+    active: Option<String>,
+}
+
+impl KeywordLedger {
+    const LOG_LIMIT: usize = 200;
+
+    /// applies the overrides of the workspace and restores keywords it does not override
+    pub async fn enter(
+        &mut self,
+        workspace: &str,
+        overrides: &HashMap<String, String>,
+    ) -> Result<()> {
+        if self.active.as_deref() == Some(workspace) {
+            return Ok(());
+        }
+        self.active = Some(workspace.to_owned());
+
+        let mut batch = DispatchBatch::new();
+        let stale = self
+            .overridden
+            .iter()
+            .filter(|k| !overrides.contains_key(*k))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in stale {
+            self.overridden.remove(&key);
+            if let Some(value) = self.original.remove(&key) {
+                self.set(&mut batch, &key, &value, "restore");
+            }
+        }
+        for (key, value) in overrides.iter() {
+            if self.reverted.contains(key) || self.current.get(key) == Some(value) {
+                continue;
+            }
+            if !self.original.contains_key(key) {
+                let old = Keyword::get_async(key).await?;
+                self.original
+                    .insert(key.clone(), option_value_string(old.value));
+            }
+            self.overridden.insert(key.clone());
+            self.set(&mut batch, key, value, workspace);
+        }
+        batch.send().await
+    }
+
+    /// whether hyprkool has changed the keyword and not put it back yet
+    pub fn is_changed(&self, key: &str) -> bool {
+        self.original.contains_key(key)
+    }
+
+    /// remembers the value the keyword has before hyprkool changes it
+    pub fn remember_original(&mut self, key: &str, value: String) {
+        self.original.entry(key.to_owned()).or_insert(value);
+    }
+
+    /// changes a keyword that is not a workspace override. [Self::enter] leaves it alone
+    pub fn change(&mut self, batch: &mut DispatchBatch, key: &str, value: &str, source: &str) {
+        if !self.reverted.contains(key) {
+            self.set(batch, key, value, source);
+        }
+    }
+
+    /// puts back the value the keyword had before it was changed
+    pub fn restore(&mut self, batch: &mut DispatchBatch, key: &str) {
+        self.overridden.remove(key);
+        if let Some(value) = self.original.remove(key) {
+            self.set(batch, key, &value, "restore");
+        }
+    }
+
+    /// logs a rule that was added to hyprland
+    pub fn rule_added(&mut self, key: &str, value: &str, source: &str) {
+        self.rules.push((key.to_owned(), value.to_owned()));
+        self.log(key, value, source);
+    }
+
+    /// restores every keyword changed by hyprkool. should be called before the daemon exits
+    pub async fn restore_all(&mut self) -> Result<()> {
+        let mut batch = DispatchBatch::new();
+        for (key, value) in std::mem::take(&mut self.original) {
+            self.set(&mut batch, &key, &value, "restore");
+        }
+        self.overridden.clear();
+        self.active = None;
+        batch.send().await
+    }
+
+    /// restores the keyword and leaves it alone from now on
+    pub async fn revert(&mut self, key: &str) -> Result<()> {
+        let Some(value) = self.original.remove(key) else {
+            if self.rules.iter().any(|(k, _)| k == key) {
+                return Err(anyhow!(
+                    "'{}' rules can't be reverted without reloading the hyprland config",
+                    key
+                ));
+            }
+            return Err(anyhow!("hyprkool has not changed '{}'", key));
+        };
+        self.overridden.remove(key);
+        let mut batch = DispatchBatch::new();
+        self.set(&mut batch, key, &value, "revert");
+        self.reverted.insert(key.to_owned());
+        batch.send().await
+    }
+
+    pub async fn revert_all(&mut self) -> Result<()> {
+        let keys = self.original.keys().cloned().collect::<Vec<_>>();
+        for key in keys {
+            self.revert(&key).await?;
+        }
+        Ok(())
+    }
+
+    /// keywords and rules that are different from before hyprkool touched them
+    pub fn changed(&self) -> Vec<ChangedKeyword> {
+        let mut changed = self
+            .original
+            .iter()
+            .map(|(key, value)| ChangedKeyword {
+                key: key.clone(),
+                original: Some(value.clone()),
+            })
+            .collect::<Vec<_>>();
+        changed.sort_by(|a, b| a.key.cmp(&b.key));
+        changed.extend(self.rules.iter().map(|(key, _)| ChangedKeyword {
+            key: key.clone(),
+            original: None,
+        }));
+        changed
+    }
+
+    /// sends and logs the value unless hyprkool already set it
+    fn set(&mut self, batch: &mut DispatchBatch, key: &str, value: &str, source: &str) {
+        if self.current.get(key).is_some_and(|v| v == value) {
+            return;
+        }
+        batch.keyword(key, value);
+        self.current.insert(key.to_owned(), value.to_owned());
+        self.log(key, value, source);
+    }
+
+    fn log(&mut self, key: &str, value: &str, source: &str) {
+        self.version += 1;
+        self.log.push_back(KeywordChange {
+            version: self.version,
+            key: key.to_owned(),
+            value: value.to_owned(),
+            source: source.to_owned(),
+        });
+        if self.log.len() > Self::LOG_LIMIT {
+            self.log.pop_front();
+        }
+    }
+}
+
+impl KeywordsCommand {
+    pub async fn execute(&self, ledger: &mut KeywordLedger) -> Result<Option<String>> {
+        match self {
+            KeywordsCommand::List { json } => {
+                let mut reverted = ledger.reverted.iter().cloned().collect::<Vec<_>>();
+                reverted.sort();
+                let listing = KeywordListing {
+                    changed: ledger.changed(),
+                    reverted,
+                    log: ledger.log.iter().cloned().collect(),
+                };
+                if *json {
+                    return Ok(Some(serde_json::to_string(&listing)?));
+                }
+                let mut lines = listing
+                    .log
+                    .iter()
+                    .map(|c| format!("v{} {} = {} ({})", c.version, &c.key, &c.value, &c.source))
+                    .collect::<Vec<_>>();
+                for c in &listing.changed {
+                    match &c.original {
+                        Some(original) => {
+                            lines.push(format!("changed {} (was {})", &c.key, original))
+                        }
+                        None => lines.push(format!("added {} rule", &c.key)),
+                    }
+                }
+                for key in &listing.reverted {
+                    lines.push(format!("reverted {}", key));
+                }
+                Ok(Some(lines.join("\n")))
+            }
+            KeywordsCommand::Revert { key: Some(key), .. } => {
+                ledger.revert(key).await?;
+                Ok(None)
+            }
+            KeywordsCommand::Revert { key: None, .. } => {
+                ledger.revert_all().await?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn option_value_string(value: OptionValue) -> String {
+    match value {
+        OptionValue::Int(i) => i.to_string(),
+        OptionValue::Float(f) => f.to_string(),
+        OptionValue::String(s) => s,
+    }
+}
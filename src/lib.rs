@@ -0,0 +1,87 @@
+//! hyprkool as a library. the cli and daemon are built on top of this, but other tools can
+//! use it too, e.g. to parse the config, navigate the grid or work with workspace names.
+//!
+//! - [`Config`] is the parsed `hyprkool.toml`
+//! - [`State`] holds the activities and workspaces of the grid ([`State::get_indices`],
+//!   [`State::workspace_name`], [`State::moved_workspace_from`])
+//! - [`navigation::NavigationPolicy`] decides where directional moves go. the default
+//!   [`navigation::Grid`] uses [`state::moved_index`]. [`state::parse_cell`] and
+//!   [`state::activity_name`] parse workspace names, given the config's `flat_grid`
+//! - [`Target`] parses workspace targets like `activity:(x y)`
+//! - [`run_cli`] is what the `hyprkool` binary runs
+
+use anyhow::Result;
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+
+use crate::{cli::Cli, command::Command, state::Animation};
+
+pub use crate::{config::Config, state::State, target::Target};
+
+pub(crate) mod animation;
+pub(crate) mod backend;
+pub(crate) mod backoff;
+pub(crate) mod batch;
+pub(crate) mod binds;
+pub(crate) mod cli;
+pub(crate) mod command;
+pub(crate) mod completions;
+pub mod config;
+pub(crate) mod daemon;
+pub(crate) mod dim;
+pub(crate) mod exec;
+pub(crate) mod focus;
+pub(crate) mod freeze;
+pub(crate) mod gestures;
+pub(crate) mod hooks;
+pub(crate) mod info;
+pub(crate) mod keywords;
+pub(crate) mod logging;
+pub(crate) mod minimap;
+pub mod navigation;
+pub(crate) mod notify;
+pub(crate) mod profile;
+pub(crate) mod rpc;
+pub(crate) mod rules;
+pub(crate) mod session;
+pub(crate) mod snapshot;
+pub mod state;
+pub(crate) mod stats;
+pub(crate) mod steal;
+pub(crate) mod systemd;
+pub mod target;
+pub(crate) mod wallpaper;
+
+/// runs the cli with the arguments of the process
+pub async fn run_cli() -> Result<()> {
+    let cli = Cli::parse();
+    let start = std::time::Instant::now();
+    if cli.profile {
+        profile::enable();
+    }
+    logging::init(cli.verbose, cli.log_file.as_deref(), cli.journald)?;
+    let res = cli::run(cli).await;
+    if let Some(report) = profile::report(start.elapsed()) {
+        println!("{}", report);
+    }
+    res
+}
+
+/// what the cli and the daemon send over the daemon socket. other tools should send the
+/// json requests of [`rpc::Request`](crate::rpc) instead
+// variant names go over the socket as they are
+#[allow(clippy::enum_variant_names)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub(crate) enum Message {
+    IpcOk,
+    IpcErr(String),
+    IpcMessage(String),
+    Command(Command),
+    /// a command with --animation or --no-animation
+    AnimatedCommand(Command, Animation),
+}
+impl Message {
+    fn msg(&self) -> Vec<u8> {
+        serde_json::to_string(self).unwrap().into_bytes()
+    }
+}
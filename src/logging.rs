@@ -0,0 +1,32 @@
+use std::{fs::OpenOptions, path::Path, sync::Mutex};
+
+use anyhow::{Context, Result};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+/// logs go to journald, a file or stderr. verbosity 0 logs info and up, 1 debug, 2 trace
+pub fn init(verbosity: u8, log_file: Option<&Path>, journald: bool) -> Result<()> {
+    let level = match verbosity {
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let registry = tracing_subscriber::registry().with(level);
+    if journald {
+        let layer = tracing_journald::layer().context("could not connect to journald")?;
+        registry.with(layer).init();
+    } else if let Some(path) = log_file {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("could not open log file {:?}", path))?;
+        registry
+            .with(fmt::layer().with_ansi(false).with_writer(Mutex::new(file)))
+            .init();
+    } else {
+        registry
+            .with(fmt::layer().with_writer(std::io::stderr))
+            .init();
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use std::{
+    process::Stdio,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::process::Child;
+use tracing::error;
+
+use crate::config::{MinimapConfig, StatusConfig};
+
+/// shows the grid for a moment after every switch using the configured command
+#[derive(Debug, Default)]
+pub struct Minimap {
+    config: MinimapConfig,
+    /// the command that shows the grid right now
+    shown: Arc<Mutex<Option<Child>>>,
+}
+
+impl Minimap {
+    pub fn new(config: MinimapConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.config.command.is_empty()
+    }
+
+    pub fn theme(&self) -> &StatusConfig {
+        &self.config.theme
+    }
+
+    /// replaces whatever minimap is shown with this grid
+    pub fn show(&self, grid: &str) {
+        let Some((cmd, args)) = self.config.command.split_first() else {
+            return;
+        };
+        let mut shown = self.shown.lock().expect("minimap lock poisoned");
+        if let Some(mut previous) = shown.take() {
+            let _ = previous.start_kill();
+        }
+        let child = tokio::process::Command::new(cmd)
+            .args(args)
+            .arg(grid)
+            .env("HYPRKOOL_MINIMAP_POSITION", self.config.position.as_str())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+        let child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                error!("could not run minimap command: {}", e);
+                return;
+            }
+        };
+        let id = child.id();
+        *shown = Some(child);
+
+        let shown = self.shown.clone();
+        let timeout = Duration::from_millis(self.config.timeout);
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let mut shown = shown.lock().expect("minimap lock poisoned");
+            // a later switch might have replaced it already
+            if shown.as_ref().and_then(|c| c.id()) == id {
+                if let Some(mut child) = shown.take() {
+                    let _ = child.start_kill();
+                }
+            }
+        });
+    }
+}
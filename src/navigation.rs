@@ -0,0 +1,168 @@
+use std::fmt::Debug;
+
+use crate::state::moved_index;
+
+/// decides which workspace a directional move goes to. [`State`](crate::State) asks its policy
+/// for every move (keyboard and screen edges), so a custom one changes the topology
+/// (torus, hex, linear, ...) while switching, animations and the daemon stay the same.
+/// set it with [`State::with_navigation`](crate::State::with_navigation)
+pub trait NavigationPolicy: Debug + Send + Sync {
+    /// row major index of the workspace reached by moving (x, y) from the workspace at index
+    /// in a grid of size (nx, ny). x is already flipped if invert_horizontal is set.
+    /// cycle is set if moves past the edge should wrap around. return index to not move
+    fn moved_index(
+        &self,
+        index: usize,
+        grid: (u32, u32),
+        direction: (i64, i64),
+        cycle: bool,
+    ) -> usize;
+}
+
+/// the default. a rectangular grid that stops at the edges or wraps around with cycle
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Grid;
+
+impl NavigationPolicy for Grid {
+    fn moved_index(
+        &self,
+        index: usize,
+        grid: (u32, u32),
+        direction: (i64, i64),
+        cycle: bool,
+    ) -> usize {
+        moved_index(index, grid, direction, cycle)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{Config, State};
+
+    #[test]
+    fn moves_in_the_grid() {
+        let m = |i, d, cycle| Grid.moved_index(i, (3, 2), d, cycle);
+        // 0 1 2
+        // 3 4 5
+        assert_eq!(m(0, (1, 0), false), 1);
+        assert_eq!(m(1, (0, 1), false), 4);
+        assert_eq!(m(4, (-1, -1), false), 0);
+        assert_eq!(m(0, (0, 0), false), 0);
+        // edges stop the move, or wrap around with cycle
+        assert_eq!(m(2, (1, 0), false), 2);
+        assert_eq!(m(2, (1, 0), true), 0);
+        assert_eq!(m(0, (-1, 0), true), 2);
+        assert_eq!(m(4, (0, 1), false), 4);
+        assert_eq!(m(4, (0, 1), true), 1);
+        assert_eq!(m(0, (-1, -1), true), 5);
+        assert_eq!(m(5, (1, 1), true), 0);
+        // only the part that is too far gets cut off
+        assert_eq!(m(3, (5, -1), false), 2);
+    }
+
+    #[test]
+    fn non_square_grids() {
+        // one row, one column and a single cell
+        assert_eq!(Grid.moved_index(3, (5, 1), (1, 0), false), 4);
+        assert_eq!(Grid.moved_index(3, (5, 1), (0, 1), true), 3);
+        assert_eq!(Grid.moved_index(4, (5, 1), (1, 0), true), 0);
+        assert_eq!(Grid.moved_index(1, (1, 4), (0, 1), false), 2);
+        assert_eq!(Grid.moved_index(3, (1, 4), (0, 1), true), 0);
+        assert_eq!(Grid.moved_index(3, (1, 4), (1, 0), true), 3);
+        assert_eq!(Grid.moved_index(0, (1, 1), (1, -1), true), 0);
+        assert_eq!(Grid.moved_index(0, (1, 1), (1, -1), false), 0);
+        // 2x4
+        assert_eq!(Grid.moved_index(1, (2, 4), (0, 3), false), 7);
+        assert_eq!(Grid.moved_index(7, (2, 4), (1, 1), true), 0);
+    }
+
+    #[test]
+    fn moves_from_workspaces() {
+        let config = |invert_horizontal| Config {
+            activities: vec!["work".into(), "play".into()],
+            workspaces: (3, 2),
+            invert_horizontal,
+            ..Default::default()
+        };
+        let s = State::new(config(false)).unwrap();
+        let moved = |name, x, y, cycle| s.moved_workspace_from(name, x, y, cycle).unwrap();
+        assert_eq!(moved("work:(1 1)", 1, 0, false), "work:(2 1)");
+        assert_eq!(moved("play:(3 1)", 1, 1, false), "play:(3 2)");
+        assert_eq!(moved("play:(3 1)", 1, 1, true), "play:(1 2)");
+        assert_eq!(moved("work:(1 2)", -1, 0, true), "work:(3 2)");
+        // workspaces outside the grid can't be moved from
+        assert!(s.moved_workspace_from("work:(4 1)", 1, 0, false).is_err());
+        assert!(s.moved_workspace_from("1", 1, 0, false).is_err());
+        assert!(s.moved_workspace_from("chat:(1 1)", 1, 0, false).is_err());
+
+        // (1 1) is on the right, so moving right goes to lower x
+        let s = State::new(config(true)).unwrap();
+        let moved = |name, x, y, cycle| s.moved_workspace_from(name, x, y, cycle).unwrap();
+        assert_eq!(moved("work:(2 1)", 1, 0, false), "work:(1 1)");
+        assert_eq!(moved("work:(1 1)", 1, 0, false), "work:(1 1)");
+        assert_eq!(moved("work:(1 1)", 1, 0, true), "work:(3 1)");
+        assert_eq!(moved("work:(1 1)", -1, 1, false), "work:(2 2)");
+        assert_eq!(moved("work:(1 1)", 0, 1, false), "work:(1 2)");
+    }
+
+    /// a grid, an index in it and a move of up to a few grids in each direction
+    fn moves() -> impl Strategy<Value = ((u32, u32), usize, (i64, i64))> {
+        (1u32..8, 1u32..8).prop_flat_map(|(nx, ny)| {
+            (
+                Just((nx, ny)),
+                0..(nx * ny) as usize,
+                (
+                    -3 * nx as i64..=3 * nx as i64,
+                    -3 * ny as i64..=3 * ny as i64,
+                ),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn stays_in_bounds((grid, index, d) in moves(), cycle: bool) {
+            let i = Grid.moved_index(index, grid, d, cycle);
+            prop_assert!(i < (grid.0 * grid.1) as usize);
+        }
+
+        #[test]
+        fn cycle_wraps_around((grid, index, (x, y)) in moves()) {
+            let (nx, ny) = (grid.0 as i64, grid.1 as i64);
+            // a full lap in either direction comes back
+            prop_assert_eq!(Grid.moved_index(index, grid, (nx, 0), true), index);
+            prop_assert_eq!(Grid.moved_index(index, grid, (0, -ny), true), index);
+            // and moves are the same modulo the grid size
+            prop_assert_eq!(
+                Grid.moved_index(index, grid, (x, y), true),
+                Grid.moved_index(index, grid, (x.rem_euclid(nx), y.rem_euclid(ny)), true)
+            );
+            // going back undoes a move
+            let moved = Grid.moved_index(index, grid, (x, y), true);
+            prop_assert_eq!(Grid.moved_index(moved, grid, (-x, -y), true), index);
+        }
+
+        #[test]
+        fn clamps_without_cycle((grid, index, (x, y)) in moves()) {
+            let nx = grid.0 as i64;
+            let (ix, iy) = (index as i64 % nx, index as i64 / nx);
+            let i = Grid.moved_index(index, grid, (x, y), false) as i64;
+            let (mx, my) = (i % nx, i / nx);
+            // never further than asked, never backwards
+            prop_assert!((mx - ix).abs() <= x.abs() && (my - iy).abs() <= y.abs());
+            prop_assert!((mx - ix) * x >= 0 && (my - iy) * y >= 0);
+            // a move that got cut short ends on the edge, where moving further does nothing
+            if mx - ix != x {
+                prop_assert!(mx == 0 || mx == nx - 1);
+                prop_assert_eq!(Grid.moved_index(i as usize, grid, (x.signum(), 0), false) as i64, i);
+            }
+            if my - iy != y {
+                prop_assert!(my == 0 || my == grid.1 as i64 - 1);
+                prop_assert_eq!(Grid.moved_index(i as usize, grid, (0, y.signum()), false) as i64, i);
+            }
+        }
+    }
+}
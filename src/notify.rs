@@ -0,0 +1,85 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use notify_rust::{Notification, Timeout};
+use tracing::error;
+
+use crate::config::SwitchNotificationConfig;
+
+/// desktop notification about something that failed. commands run from keybinds have no
+/// terminal to print errors to
+pub async fn error(message: &str) {
+    show(message).await
+}
+
+/// desktop notification with the message
+pub async fn show(message: &str) {
+    let message = message.to_owned();
+    // showing blocks till the notification daemon answers
+    let res = tokio::task::spawn_blocking(move || {
+        Notification::new()
+            .appname("hyprkool")
+            .summary("hyprkool")
+            .body(&message)
+            .show()
+            .map(|_| ())
+    })
+    .await;
+    match res {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => error!("could not show notification: {}", e),
+        Err(e) => error!("could not show notification: {}", e),
+    }
+}
+
+/// notifies where switches went. every notification replaces the previous one
+#[derive(Debug, Default)]
+pub struct SwitchNotifier {
+    config: SwitchNotificationConfig,
+    last: Mutex<Option<Instant>>,
+    /// id of the last notification, so that the next one replaces it
+    id: Arc<Mutex<Option<u32>>>,
+}
+
+impl SwitchNotifier {
+    pub fn new(config: SwitchNotificationConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// shows the message without waiting for it. command is what caused the switch
+    pub fn switched(&self, command: Option<&str>, message: String) {
+        if !self.config.enable || command.is_some_and(|c| self.config.skip.iter().any(|s| s == c)) {
+            return;
+        }
+        let now = Instant::now();
+        let mut last = self.last.lock().expect("notification lock poisoned");
+        if last.is_some_and(|t| now < t + Duration::from_millis(self.config.min_interval)) {
+            return;
+        }
+        *last = Some(now);
+
+        let timeout = self.config.timeout;
+        let id = self.id.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut notification = Notification::new();
+            notification
+                .appname("hyprkool")
+                .summary(&message)
+                .timeout(Timeout::Milliseconds(timeout));
+            if let Some(id) = *id.lock().expect("notification lock poisoned") {
+                notification.id(id);
+            }
+            match notification.show() {
+                Ok(handle) => {
+                    *id.lock().expect("notification lock poisoned") = Some(handle.id());
+                }
+                Err(e) => error!("could not show switch notification: {}", e),
+            }
+        });
+    }
+}
@@ -0,0 +1,39 @@
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// recorded spans. None if profiling is disabled
+static SPANS: Mutex<Option<Vec<(String, Duration)>>> = Mutex::new(None);
+
+pub fn enable() {
+    *SPANS.lock().expect("profile lock poisoned") = Some(Vec::new());
+}
+
+/// awaits f and records how long it took if profiling is enabled
+pub async fn span<T>(name: impl Into<String>, f: impl Future<Output = T>) -> T {
+    let start = Instant::now();
+    let res = f.await;
+    if let Some(spans) = SPANS.lock().expect("profile lock poisoned").as_mut() {
+        spans.push((name.into(), start.elapsed()));
+    }
+    res
+}
+
+/// breakdown of all recorded spans. None if profiling is disabled
+pub fn report(total: Duration) -> Option<String> {
+    let spans = SPANS.lock().expect("profile lock poisoned");
+    let spans = spans.as_ref()?;
+    let mut lines = spans
+        .iter()
+        .map(|(name, d)| format!("{:>10.3}ms  {}", d.as_secs_f64() * 1000.0, name))
+        .collect::<Vec<_>>();
+    let spent = spans.iter().map(|(_, d)| *d).sum::<Duration>();
+    lines.push(format!(
+        "{:>10.3}ms  hyprkool (everything else)",
+        total.saturating_sub(spent).as_secs_f64() * 1000.0
+    ));
+    lines.push(format!("{:>10.3}ms  total", total.as_secs_f64() * 1000.0));
+    Some(lines.join("\n"))
+}
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+    sync::Mutex,
+};
+use tracing::debug;
+
+use crate::{cli::Cli, command::Command, info::InfoOutputStream, state::SwitchSource, State};
+
+/// a request on the daemon socket. one json object per line, each answered with one
+/// [`Response`] line. `command` and `args` are what would be typed after `hyprkool`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Request {
+    /// anything. echoed back in the response
+    #[serde(default)]
+    pub id: Value,
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Response {
+    pub id: Value,
+    /// what the command printed. parsed if it is json, a string if not and null if nothing
+    pub result: Value,
+    /// set if the command failed. result is null then
+    pub error: Option<String>,
+}
+
+impl Request {
+    async fn execute(&self, state: Arc<Mutex<State>>) -> Result<Value> {
+        let args = ["hyprkool", self.command.as_str()]
+            .into_iter()
+            .chain(self.args.iter().map(|a| a.as_str()));
+        let cli = Cli::try_parse_from(args).context("invalid command")?;
+        let animation = cli.animation_override();
+        let output = match cli.command {
+            Command::Info {
+                command,
+                monitor: false,
+                ..
+            } => {
+                let buffer = Arc::default();
+                command
+                    .execute(
+                        InfoOutputStream::Buffer(Arc::clone(&buffer)),
+                        state,
+                        false,
+                        false,
+                    )
+                    .await?;
+                let mut buffer = buffer.lock().expect("info buffer lock poisoned");
+                buffer.pop()
+            }
+            Command::Info { .. } => {
+                return Err(anyhow!(
+                    "info --monitor can't be answered once. use hyprkool info -m instead"
+                ));
+            }
+            command if command.runs_locally() => {
+                return Err(anyhow!("'{}' can't be run by the daemon", command.name()));
+            }
+            command => {
+                command
+                    .execute_from(state, true, SwitchSource::Keyboard, animation)
+                    .await?
+            }
+        };
+        Ok(match output {
+            Some(out) => serde_json::from_str(&out).unwrap_or(Value::String(out)),
+            None => Value::Null,
+        })
+    }
+
+    async fn respond(line: &str, state: Arc<Mutex<State>>) -> Response {
+        let req = match serde_json::from_str::<Request>(line) {
+            Ok(req) => req,
+            Err(e) => {
+                return Response {
+                    id: Value::Null,
+                    result: Value::Null,
+                    error: Some(format!("invalid request: {}", e)),
+                };
+            }
+        };
+        debug!("rpc request: {:?}", &req);
+        match req.execute(state).await {
+            Ok(result) => Response {
+                id: req.id,
+                result,
+                error: None,
+            },
+            Err(e) => Response {
+                id: req.id,
+                result: Value::Null,
+                error: Some(format!("{:#}", e)),
+            },
+        }
+    }
+}
+
+/// answers requests on the connection till the client closes it. line is the first request
+pub async fn serve(
+    state: Arc<Mutex<State>>,
+    mut sock: BufReader<UnixStream>,
+    mut line: String,
+) -> Result<()> {
+    loop {
+        let res = Request::respond(&line, state.clone()).await;
+        sock.write_all(serde_json::to_string(&res)?.as_bytes())
+            .await?;
+        sock.write_all("\n".as_bytes()).await?;
+        sock.flush().await?;
+
+        line.clear();
+        if sock.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{Config, WindowRule};
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum RulesCommand {
+    /// explain which rules match a window and which one wins
+    Test {
+        class: String,
+        title: String,
+
+        /// print as json instead
+        #[arg(long, default_value_t = false)]
+        json: bool,
+    },
+}
+
+/// a matching rule in rules test --json
+#[derive(Serialize, Debug)]
+struct RuleMatch<'a> {
+    /// position in config
+    index: usize,
+    priority: i32,
+    class: Option<&'a str>,
+    title: Option<&'a str>,
+    workspace: &'a str,
+}
+
+impl RulesCommand {
+    pub fn execute(&self, config: &Config) -> Result<String> {
+        match self {
+            RulesCommand::Test { class, title, json } => {
+                let rules = Rules::new(&config.rules)?;
+                let matching = rules.matching(class, title).collect::<Vec<_>>();
+                if *json {
+                    let matching = matching
+                        .iter()
+                        .map(|r| RuleMatch {
+                            index: r.index,
+                            priority: r.priority,
+                            class: r.source.class.as_deref(),
+                            title: r.source.title.as_deref(),
+                            workspace: &r.workspace,
+                        })
+                        .collect::<Vec<_>>();
+                    // first one wins
+                    return Ok(serde_json::to_string(&matching)?);
+                }
+                if matching.is_empty() {
+                    return Ok("no rule matches".to_owned());
+                }
+                let lines = matching
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| {
+                        let mut line = format!(
+                            "rule #{} (priority {}): class = {:?}, title = {:?} -> {}",
+                            r.index + 1,
+                            r.priority,
+                            r.source.class.as_deref().unwrap_or("*"),
+                            r.source.title.as_deref().unwrap_or("*"),
+                            &r.workspace,
+                        );
+                        if i == 0 {
+                            line += "  <- wins";
+                        }
+                        line
+                    })
+                    .collect::<Vec<_>>();
+                Ok(lines.join("\n"))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub class: Option<Regex>,
+    pub title: Option<Regex>,
+    /// activity name or full workspace name
+    pub workspace: String,
+    pub priority: i32,
+    /// position in config
+    pub index: usize,
+    pub source: WindowRule,
+}
+
+impl Rule {
+    fn new(index: usize, rule: &WindowRule) -> Result<Self> {
+        Ok(Self {
+            class: rule.class.as_deref().map(full_match_regex).transpose()?,
+            title: rule.title.as_deref().map(full_match_regex).transpose()?,
+            workspace: rule.workspace.clone(),
+            priority: rule.priority,
+            index,
+            source: rule.clone(),
+        })
+    }
+
+    pub fn matches(&self, class: &str, title: &str) -> bool {
+        self.class
+            .as_ref()
+            .map(|r| r.is_match(class))
+            .unwrap_or(true)
+            && self
+                .title
+                .as_ref()
+                .map(|r| r.is_match(title))
+                .unwrap_or(true)
+    }
+}
+
+/// window rules sorted by priority (highest first). rules with equal priority
+/// stay in the order they are defined in the config
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    pub fn new(rules: &[WindowRule]) -> Result<Self> {
+        let mut rules = rules
+            .iter()
+            .enumerate()
+            .map(|(i, r)| Rule::new(i, r))
+            .collect::<Result<Vec<_>>>()?;
+        rules.sort_by_key(|r| std::cmp::Reverse(r.priority));
+        Ok(Self { rules })
+    }
+
+    /// all rules that match this window. the first one wins
+    pub fn matching<'a>(
+        &'a self,
+        class: &'a str,
+        title: &'a str,
+    ) -> impl Iterator<Item = &'a Rule> {
+        self.rules.iter().filter(move |r| r.matches(class, title))
+    }
+
+    /// the rule that wins for this window
+    pub fn find(&self, class: &str, title: &str) -> Option<&Rule> {
+        self.rules.iter().find(|r| r.matches(class, title))
+    }
+}
+
+/// prefix of the activity for a rule target like "new-activity:<prefix>", which gives
+/// every matching window its own activity
+pub fn new_activity_prefix(target: &str) -> Option<&str> {
+    target.strip_prefix("new-activity:")
+}
+
+/// regexes must match the entire class/title (same as hyprland window rules)
+pub fn full_match_regex(re: &str) -> Result<Regex> {
+    Regex::new(&format!("^(?:{})$", re)).with_context(|| format!("invalid regex '{}'", re))
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use hyprland::{
+    data::Clients,
+    dispatch::{Dispatch, DispatchType, WindowIdentifier, WorkspaceIdentifierWithSpecial},
+    shared::{Address, HyprData},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+/// windows on hyprkool workspaces along with the commands that launched them
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Session {
+    pub windows: Vec<SessionWindow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SessionWindow {
+    pub class: String,
+    pub title: String,
+    pub workspace: String,
+    /// argv of the process that owns the window (from /proc/<pid>/cmdline)
+    pub command: Option<Vec<String>>,
+}
+
+impl Session {
+    pub fn default_path() -> Result<PathBuf> {
+        let dir = dirs::data_local_dir().context("could not find local data dir")?;
+        Ok(dir.join("hyprkool").join("session.json"))
+    }
+
+    pub async fn capture(state: &State) -> Result<Self> {
+        let windows = Clients::get_async()
+            .await?
+            .into_iter()
+            .filter(|c| matches!(state.get_indices(&c.workspace.name), Some((_, Some(_)))))
+            .map(|c| SessionWindow {
+                command: launch_command(c.pid),
+                class: c.class,
+                title: c.title,
+                workspace: c.workspace.name,
+            })
+            .collect();
+        Ok(Self { windows })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let s = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read session from {:?}", path))?;
+        Ok(serde_json::from_str(&s)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("could not write session to {:?}", path))?;
+        Ok(())
+    }
+
+    /// removes and returns the recorded window with the same class and title
+    pub fn take_title_match(&mut self, class: &str, title: &str) -> Option<SessionWindow> {
+        let i = self
+            .windows
+            .iter()
+            .position(|w| w.class == class && w.title == title)?;
+        Some(self.windows.remove(i))
+    }
+
+    /// removes and returns the recorded window that best matches. same title first, then
+    /// same class if only one window of that class is recorded, so that windows of an app
+    /// with many windows don't take each other's places
+    pub fn take_match(&mut self, class: &str, title: &str) -> Option<SessionWindow> {
+        if let Some(w) = self.take_title_match(class, title) {
+            return Some(w);
+        }
+        let mut same_class = self
+            .windows
+            .iter()
+            .enumerate()
+            .filter(|(_, w)| w.class == class);
+        match (same_class.next(), same_class.next()) {
+            (Some((i, _)), None) => Some(self.windows.remove(i)),
+            _ => None,
+        }
+    }
+
+    /// moves already open windows back to the workspaces they were recorded on.
+    /// windows that were not found are left in the session
+    pub async fn restore_open_windows(&mut self) -> Result<()> {
+        let mut clients = Clients::get_async().await?.into_iter().collect::<Vec<_>>();
+        // every title match first, so class matches only get the windows that are left
+        let mut matches = Vec::new();
+        clients.retain(|c| match self.take_title_match(&c.class, &c.title) {
+            Some(w) => {
+                matches.push((c.clone(), w));
+                false
+            }
+            None => true,
+        });
+        for c in clients {
+            if let Some(w) = self.take_match(&c.class, &c.title) {
+                matches.push((c, w));
+            }
+        }
+        for (c, w) in matches {
+            if w.workspace != c.workspace.name {
+                move_window(c.address, &w.workspace).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// launch every window that has a known command on the workspace it was on
+    pub async fn relaunch(&self) -> Result<()> {
+        for w in self.windows.iter() {
+            let Some(command) = &w.command else {
+                println!("no launch command known for {} ({})", &w.class, &w.title);
+                continue;
+            };
+            let command = command
+                .iter()
+                .map(|a| shell_quote(a))
+                .collect::<Vec<_>>()
+                .join(" ");
+            let exec = format!("[workspace name:{} silent] {}", &w.workspace, command);
+            Dispatch::call_async(DispatchType::Exec(&exec)).await?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn move_window(address: Address, workspace: &str) -> Result<()> {
+    Dispatch::call_async(DispatchType::MoveToWorkspaceSilent(
+        WorkspaceIdentifierWithSpecial::Name(workspace),
+        Some(WindowIdentifier::Address(address)),
+    ))
+    .await?;
+    Ok(())
+}
+
+fn launch_command(pid: i32) -> Option<Vec<String>> {
+    if pid <= 0 {
+        return None;
+    }
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let args = cmdline
+        .split(|&b| b == 0)
+        .filter(|a| !a.is_empty())
+        .map(|a| String::from_utf8_lossy(a).into_owned())
+        .collect::<Vec<_>>();
+    (!args.is_empty()).then_some(args)
+}
+
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(windows: &[(&str, &str, &str)]) -> Session {
+        Session {
+            windows: windows
+                .iter()
+                .map(|&(class, title, workspace)| SessionWindow {
+                    class: class.into(),
+                    title: title.into(),
+                    workspace: workspace.into(),
+                    command: None,
+                })
+                .collect(),
+        }
+    }
+
+    fn take(s: &mut Session, class: &str, title: &str) -> Option<String> {
+        s.take_match(class, title).map(|w| w.workspace)
+    }
+
+    #[test]
+    fn matches_titles_before_classes() {
+        let mut s = session(&[
+            ("kitty", "vim", "work:(1 1)"),
+            ("kitty", "htop", "work:(2 1)"),
+            ("firefox", "docs", "web:(1 1)"),
+        ]);
+        assert_eq!(take(&mut s, "kitty", "htop").as_deref(), Some("work:(2 1)"));
+        // the only firefox window can have a new title
+        assert_eq!(
+            take(&mut s, "firefox", "news").as_deref(),
+            Some("web:(1 1)")
+        );
+        assert_eq!(take(&mut s, "firefox", "docs"), None);
+        assert_eq!(take(&mut s, "mpv", "vim"), None);
+        assert_eq!(s.windows.len(), 1);
+    }
+
+    #[test]
+    fn ambiguous_classes_need_the_title() {
+        let mut s = session(&[
+            ("kitty", "vim", "work:(1 1)"),
+            ("kitty", "htop", "work:(2 1)"),
+        ]);
+        assert_eq!(take(&mut s, "kitty", "zsh"), None);
+        assert_eq!(take(&mut s, "kitty", "vim").as_deref(), Some("work:(1 1)"));
+        // the last kitty window is not ambiguous anymore
+        assert_eq!(take(&mut s, "kitty", "zsh").as_deref(), Some("work:(2 1)"));
+    }
+}
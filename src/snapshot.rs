@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+
+use anyhow::{Context, Result};
+use hyprland::data::{Client, Monitor, Workspace};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{batch, profile};
+
+/// monitors, workspaces and windows as hyprland sees them, all taken in a single request so
+/// that they agree with each other
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub monitors: Vec<Monitor>,
+    pub workspaces: Vec<Workspace>,
+    pub clients: Vec<Client>,
+    pub active_workspace: Workspace,
+}
+
+impl Snapshot {
+    pub async fn take() -> Result<Self> {
+        profile::span("snapshot", Self::_take()).await
+    }
+
+    async fn _take() -> Result<Self> {
+        let resp = batch::query(&[
+            "j/monitors",
+            "j/workspaces",
+            "j/clients",
+            "j/activeworkspace",
+        ])
+        .await?;
+        // the replies are just written one after the other
+        let mut values = serde_json::Deserializer::from_str(&resp).into_iter::<Value>();
+        let mut next = |what: &str| -> Result<Value> {
+            values
+                .next()
+                .with_context(|| format!("no {} in the hyprland reply", what))?
+                .with_context(|| format!("could not parse the {} in the hyprland reply", what))
+        };
+        fn parse<T: DeserializeOwned>(value: Value, what: &str) -> Result<T> {
+            serde_json::from_value(value).with_context(|| format!("unexpected {} json", what))
+        }
+        Ok(Self {
+            monitors: parse(next("monitors")?, "monitors")?,
+            workspaces: parse(next("workspaces")?, "workspaces")?,
+            clients: parse(next("clients")?, "clients")?,
+            active_workspace: parse(next("active workspace")?, "active workspace")?,
+        })
+    }
+
+    /// the focused monitor. the first one if none is focused
+    pub fn active_monitor(&self) -> Option<&Monitor> {
+        self.monitors
+            .iter()
+            .find(|m| m.focused)
+            .or(self.monitors.first())
+    }
+
+    /// number of windows in each workspace
+    pub fn windows(&self) -> HashMap<String, u16> {
+        self.workspaces
+            .iter()
+            .map(|w| (w.name.clone(), w.windows))
+            .collect()
+    }
+}
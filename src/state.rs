@@ -1,128 +1,1039 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    process::Stdio,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
 use hyprland::{
-    data::Workspace,
-    dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial},
-    shared::HyprDataActive,
+    data::{Client, Clients, Monitor, Monitors, Workspace, Workspaces},
+    dispatch::{Dispatch, DispatchType, WindowIdentifier, WorkspaceIdentifierWithSpecial},
+    shared::{Address, HyprData, HyprDataActive},
 };
 use serde::{Deserialize, Serialize};
 use tokio::{
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter},
     net::UnixStream,
+    sync::{broadcast, Mutex, Notify},
 };
+use tracing::{error, info, warn};
 
-use crate::{config::Config, daemon::get_plugin_socket_path, Message};
+use crate::{
+    animation::{workspaces_animation, SwitchKind},
+    batch::DispatchBatch,
+    config::{template_vars, Config, ExternalWorkspaces, PreSwitchHook, StatusConfig, StatusStyle},
+    daemon::{get_plugin_socket_path, Rect},
+    dim::Dimmer,
+    exec::PendingSpawns,
+    focus::FocusTimer,
+    freeze::Freezer,
+    hooks::Hooks,
+    keywords::KeywordLedger,
+    minimap::Minimap,
+    navigation::{Grid, NavigationPolicy},
+    notify::SwitchNotifier,
+    profile,
+    rules::{full_match_regex, new_activity_prefix, Rules},
+    session::{move_window, Session},
+    snapshot::Snapshot,
+    stats::FocusStats,
+    steal::FocusStealGuard,
+    target::Target,
+    wallpaper::Wallpaper,
+    Message,
+};
 
 #[derive(Debug)]
 pub struct State {
     pub focused: HashMap<String, String>,
     pub named_focii: HashMap<String, String>,
+    /// activities in the order they were last visited. most recent last
+    pub activity_history: Vec<String>,
+    /// workspace names are formatted from these and the grid size when needed
     pub activities: Vec<String>,
-    pub workspaces: Vec<Vec<String>>,
+    /// activities created by "new-activity:" rules for the window with this address
+    pub window_activities: HashMap<String, String>,
+    pub rules: Rules,
     pub config: Config,
+    /// where directional moves go
+    pub navigation: Arc<dyn NavigationPolicy>,
+    /// reset plugin animation to the one configured in hyprland.conf after every switch
+    pub restore_animation: bool,
+    /// animation for the switches of the command being run instead of its own (--animation)
+    pub animation_override: Option<Animation>,
+    pub freezer: Freezer,
+    pub dimmer: Dimmer,
+    pub minimap: Minimap,
+    pub wallpaper: Wallpaper,
+    pub stats: FocusStats,
+    pub hooks: Hooks,
+    pub focus: FocusTimer,
+    pub focus_steal: FocusStealGuard,
+    /// switch workspaces when the cursor touches screen edges. can be toggled at runtime
+    pub edge_switching: bool,
+    /// the session is idle. set by idle, cleared by wake
+    pub idle: bool,
+    /// the session is locked. set by lock, cleared by unlock. kept apart from idle as
+    /// the session can wake from idle while it is still locked
+    pub locked: bool,
+    /// notified when edge_switching, idle or locked change
+    pub edge_switching_toggled: Arc<Notify>,
+    /// what caused the switches made right now
+    pub switch_source: SwitchSource,
+    /// name of the command (or "edge") that is switching right now
+    pub switch_command: Option<String>,
+    pub switch_notifier: SwitchNotifier,
+    pub switch_events: broadcast::Sender<SwitchEvent>,
+    pub keywords: KeywordLedger,
+    /// visited hyprkool workspaces, oldest first. for history back/forward
+    pub workspace_history: Vec<String>,
+    /// index of the current workspace in workspace_history
+    pub history_pos: usize,
+    /// workspace being switched to by history back/forward
+    history_target: Option<String>,
+    /// (previous, current) hyprkool workspace. for switch-back
+    pub last_workspaces: (Option<String>, Option<String>),
+    /// mark slot -> window address
+    pub marks: HashMap<String, String>,
+    /// workspace name -> note set at runtime
+    pub labels: HashMap<String, String>,
+    /// addresses of windows that follow every workspace switch (toggle-pin)
+    pub following: HashSet<String>,
+    /// monitor name -> activity shown on it while activities are paired
+    pub paired: Option<Vec<(String, String)>>,
+    /// windows of apps launched with 'hyprkool exec' that have not opened yet
+    pub spawns: PendingSpawns,
+    /// saved windows that have not opened yet since the daemon started
+    pub pending_session: Session,
+    /// pending_session is dropped then. windows opening later are not from the session
+    pending_session_until: Option<Instant>,
+    /// when the last switch feedback command was run
+    last_feedback: std::sync::Mutex<Option<Instant>>,
+    /// switch that waits for the pre-switch hook
+    pending_switch: std::sync::Mutex<Option<PendingSwitch>>,
+}
+
+/// a switch with everything needed to make it later
+#[derive(Debug)]
+struct PendingSwitch {
+    to: String,
+    move_window: bool,
+    kind: Option<SwitchKind>,
+    anim: Animation,
+    then: DispatchBatch,
+    animation_override: Option<Animation>,
+    source: SwitchSource,
+    command: Option<String>,
+}
+
+/// makes the switch that waits for the pre-switch hook, if there is one. the hook runs without
+/// the state locked. a vetoed switch is skipped
+pub async fn finish_pending_switch(state: &Arc<Mutex<State>>) -> Result<()> {
+    let (pending, hook) = {
+        let s = state.lock().await;
+        let pending = s
+            .pending_switch
+            .lock()
+            .expect("pending switch lock poisoned")
+            .take();
+        (pending, s.config.daemon.pre_switch.clone())
+    };
+    let Some(pending) = pending else {
+        return Ok(());
+    };
+    if !State::pre_switch_check(&hook, &pending.to).await? {
+        info!("switch to {} vetoed by pre-switch hook", &pending.to);
+        if pending.command.as_deref() == Some("focus") {
+            // 'focus start' could not get to its activity
+            state.lock().await.focus.stop();
+        }
+        return Ok(());
+    }
+    let mut s = state.lock().await;
+    s.switch_source = pending.source;
+    s.switch_command = pending.command.clone();
+    s.animation_override = pending.animation_override;
+    let res = s.switch_now(pending).await;
+    s.animation_override = None;
+    res
 }
 
 impl State {
+    const HISTORY_LIMIT: usize = 100;
+    /// how long saved windows are waited for after the daemon starts
+    const PENDING_SESSION_TIMEOUT: Duration = Duration::from_secs(120);
+
     pub fn new(config: Config) -> Result<Self> {
-        for a in config.activities.iter() {
-            for c in a.chars() {
-                if !c.is_alphanumeric() && !"-_".contains(c) {
-                    return Err(anyhow!(
-                        "Activity names can only contain a-z A-Z 0-9 - and _ characters. char '{}' in '{}' is not allowed",
-                        c,
-                        a,
-                    ));
-                }
-            }
-        }
-        let (x, y) = config.workspaces;
-        let raw_workspaces = (1..=y).flat_map(|y| (1..=x).map(move |x| (x, y)));
+        config.validate()?;
         let mut activities = config.activities.clone();
         if activities.is_empty() {
-            activities.push("default".into());
+            activities.push(FLAT_ACTIVITY.into());
         }
-        let cooked_workspaces = activities
-            .iter()
-            .map(|name| {
-                raw_workspaces
-                    .clone()
-                    .map(|(x, y)| format!("{name}:({x} {y})"))
-                    .collect::<Vec<_>>()
-            })
-            .collect::<Vec<_>>();
 
-        Ok(Self {
+        let mut state = Self {
+            rules: Rules::new(&config.rules)?,
+            navigation: Arc::new(Grid),
+            freezer: Freezer::new(config.daemon.freeze.clone(), config.flat_grid),
+            dimmer: Dimmer::new(config.daemon.dim.clone(), config.flat_grid),
+            minimap: Minimap::new(config.daemon.minimap.clone()),
+            wallpaper: Wallpaper::new(config.daemon.wallpaper.clone()),
+            stats: Default::default(),
+            hooks: Hooks::new(config.hooks.clone(), config.notify_errors, config.flat_grid),
+            focus: FocusTimer::new(config.daemon.focus.clone()),
+            focus_steal: Default::default(),
+            pending_session: Default::default(),
+            pending_session_until: None,
+            spawns: Default::default(),
+            paired: None,
+            following: Default::default(),
+            marks: Default::default(),
+            labels: Default::default(),
+            last_workspaces: (None, None),
+            workspace_history: Vec::new(),
+            history_pos: 0,
+            history_target: None,
+            keywords: Default::default(),
+            switch_source: SwitchSource::Keyboard,
+            switch_command: None,
+            switch_notifier: SwitchNotifier::new(config.daemon.switch_notification.clone()),
+            switch_events: broadcast::channel(16).0,
+            edge_switching: config.daemon.mouse.switch_workspace_on_edge,
+            idle: false,
+            locked: false,
+            edge_switching_toggled: Default::default(),
             focused: HashMap::new(),
             named_focii: config.named_focii.clone(),
+            window_activities: HashMap::new(),
+            activity_history: Vec::new(),
             activities,
-            workspaces: cooked_workspaces,
             config,
-        })
+            restore_animation: true,
+            animation_override: None,
+            last_feedback: Default::default(),
+            pending_switch: Default::default(),
+        };
+        state.canonicalize_targets()?;
+        Ok(state)
+    }
+
+    /// uses the policy for directional moves instead of the grid
+    pub fn with_navigation(mut self, policy: impl NavigationPolicy + 'static) -> Self {
+        self.navigation = Arc::new(policy);
+        self
     }
 
+    /// name can be an activity name or a workspace name (activity:workspace)
     pub fn get_activity_index(&self, name: impl AsRef<str>) -> Option<usize> {
-        let name = name.as_ref();
-        let activity_index = self.activities.iter().position(|a| name.starts_with(a))?;
+        let activity = activity_name(name.as_ref(), self.config.flat_grid);
+        let activity_index = self.activities.iter().position(|a| a == activity)?;
         Some(activity_index)
     }
 
+    /// activity of a hyprkool workspace. unlike get_activity_index, the name has to have the ':'
+    /// separator, so other workspaces that happen to be named like an activity (eg. hyprland's
+    /// numbered ones with an activity named "1") are not taken for one
+    pub fn workspace_activity_index(&self, workspace_name: impl AsRef<str>) -> Option<usize> {
+        let name = workspace_name.as_ref();
+        let separated = match self.config.flat_grid {
+            true => parse_cell(name, true).is_some(),
+            false => name.contains(':'),
+        };
+        if !separated {
+            return None;
+        }
+        self.get_activity_index(name)
+    }
+
+    /// indices of the activities that can be shown on the monitor. with monitor_rules, those
+    /// are the ones listed for it or, for monitors without rules, the ones not pinned anywhere
+    pub fn monitor_activities(&self, monitor: &str) -> Vec<usize> {
+        let rules = &self.config.monitor_rules;
+        let allowed = (0..self.activities.len())
+            .filter(|&i| {
+                let a = &self.activities[i];
+                match rules.get(monitor) {
+                    Some(activities) => activities.contains(a),
+                    None => !rules.values().any(|activities| activities.contains(a)),
+                }
+            })
+            .collect::<Vec<_>>();
+        if allowed.is_empty() {
+            return (0..self.activities.len()).collect();
+        }
+        allowed
+    }
+
+    /// index of the activity step activities away from the current one, going through the
+    /// activities allowed on the monitor only
+    pub fn stepped_activity(
+        &self,
+        current: Option<usize>,
+        monitor: &str,
+        step: isize,
+        cycle: bool,
+    ) -> usize {
+        let allowed = self.monitor_activities(monitor);
+        let Some(pos) = current.and_then(|c| allowed.iter().position(|&i| i == c)) else {
+            return allowed[0];
+        };
+        let n = allowed.len() as isize;
+        let pos = pos as isize + step;
+        let pos = match cycle {
+            true => pos.rem_euclid(n),
+            false => pos.clamp(0, n - 1),
+        };
+        allowed[pos as usize]
+    }
+
+    /// binds the workspaces of activities in monitor_rules to their monitors, so that they
+    /// (and the windows opening on them) always show up there. workspaces that already
+    /// exist on other monitors are moved over, the rules only apply to new ones
+    pub async fn apply_monitor_rules(&mut self) -> Result<()> {
+        let mut batch = DispatchBatch::new();
+        let mut rules = Vec::new();
+        let mut pinned = HashMap::new();
+        for (monitor, activities) in self.config.monitor_rules.iter() {
+            for activity in activities {
+                let Some(i) = self.get_activity_index(activity) else {
+                    continue;
+                };
+                for w in self.workspace_names(i) {
+                    rules.push(format!("name:{}, monitor:{}", w, monitor));
+                    pinned.insert(w, monitor);
+                }
+            }
+        }
+        if pinned.is_empty() {
+            return Ok(());
+        }
+        for w in Workspaces::get_async().await? {
+            match pinned.get(&w.name) {
+                Some(&monitor) if &w.monitor != monitor => {
+                    batch.move_workspace_to_monitor(w.id, monitor);
+                }
+                _ => (),
+            }
+        }
+        for rule in rules {
+            batch.keyword("workspace", &rule);
+            self.keywords
+                .rule_added("workspace", &rule, "monitor_rules");
+        }
+        batch.send().await
+    }
+
+    /// activity name for an activity name, icon or display name
+    pub fn activity_by_alias(&self, alias: &str) -> Option<&str> {
+        if let Some(i) = self.get_activity_index(alias) {
+            return Some(&self.activities[i]);
+        }
+        self.config
+            .activity
+            .iter()
+            .find(|(_, a)| a.icon.as_deref() == Some(alias) || a.display.as_deref() == Some(alias))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// (icon, display name) of an activity
+    pub fn activity_meta(&self, name: &str) -> (Option<String>, Option<String>) {
+        match self.config.activity.get(name) {
+            Some(a) => (a.icon.clone(), a.display.clone()),
+            None => (None, None),
+        }
+    }
+
     /// (activity index, workspace index)
     pub fn get_indices(&self, name: impl AsRef<str>) -> Option<(usize, Option<usize>)> {
         let name = name.as_ref();
-        let activity_index = self.get_activity_index(name)?;
-        let workspace_index = self.workspaces[activity_index]
-            .iter()
-            .position(|w| w == name);
+        let activity_index = self.workspace_activity_index(name)?;
+        let (nx, ny) = self.config.workspaces;
+        let workspace_index = parse_cell(name, self.config.flat_grid)
+            .filter(|&(x, y)| (1..=nx).contains(&x) && (1..=ny).contains(&y))
+            .map(|(x, y)| ((y - 1) * nx + (x - 1)) as usize);
         Some((activity_index, workspace_index))
     }
 
-    pub async fn moved_workspace(&self, x: i64, y: i64, cycle: bool) -> Result<&str> {
-        let workspace = Workspace::get_active_async().await?;
-        let Some((activity_index, Some(workspace_index))) = self.get_indices(workspace.name) else {
+    /// number of workspaces in every activity
+    pub fn workspace_count(&self) -> usize {
+        let (nx, ny) = self.config.workspaces;
+        (nx * ny) as usize
+    }
+
+    /// the session is idle or locked, so edges and gestures should do nothing
+    pub fn away(&self) -> bool {
+        self.idle || self.locked
+    }
+
+    /// name of the workspace at row major index i in the activity
+    pub fn workspace_name(&self, activity_index: usize, i: usize) -> String {
+        let nx = self.config.workspaces.0 as usize;
+        cell_workspace_name(
+            &self.activities[activity_index],
+            (i % nx + 1) as u32,
+            (i / nx + 1) as u32,
+            self.config.flat_grid,
+        )
+    }
+
+    /// names of all workspaces in the activity in row major order
+    pub fn workspace_names(&self, activity_index: usize) -> impl Iterator<Item = String> + '_ {
+        (0..self.workspace_count()).map(move |i| self.workspace_name(activity_index, i))
+    }
+
+    /// names of all workspaces in the activity, row by row
+    pub fn workspace_rows(&self, activity_index: usize) -> impl Iterator<Item = Vec<String>> + '_ {
+        let (nx, ny) = self.config.workspaces;
+        (0..ny).map(move |y| {
+            (0..nx)
+                .map(|x| self.workspace_name(activity_index, (y * nx + x) as usize))
+                .collect()
+        })
+    }
+
+    /// shows activity a on the left most monitor and activity b on the one next to it
+    pub async fn pair_activities(&mut self, a: &str, b: &str) -> Result<()> {
+        let mut monitors = Monitors::get_async().await?.into_iter().collect::<Vec<_>>();
+        if monitors.len() < 2 {
+            return Err(anyhow!("pairing activities needs at least 2 monitors"));
+        }
+        monitors.sort_by_key(|m| (m.x, m.y));
+        let focused = monitors.iter().find(|m| m.focused).map(|m| m.name.clone());
+
+        let mut batch = DispatchBatch::new();
+        let mut paired = Vec::new();
+        for (m, activity) in monitors.iter().zip([a, b]) {
+            let workspace = self.resolve_target(activity)?;
+            batch
+                .focus_monitor(&m.name)
+                .workspace_on_current_monitor(&workspace);
+            paired.push((m.name.clone(), activity.to_owned()));
+        }
+        if let Some(m) = focused {
+            batch.focus_monitor(m);
+        }
+        batch.send().await?;
+        self.paired = Some(paired);
+        Ok(())
+    }
+
+    /// exchanges the windows (and labels) of two workspaces in one batch. focus stays on
+    /// the focused workspace
+    pub async fn swap_workspaces(&mut self, a: &str, b: &str) -> Result<()> {
+        let windows = Clients::get_async()
+            .await?
+            .into_iter()
+            .map(|c| (c.workspace.name, c.address.to_string()))
+            .collect::<Vec<_>>();
+        swap_windows(&windows, a, b).send().await?;
+
+        let label_a = self.labels.remove(a);
+        if let Some(label) = self.labels.remove(b) {
+            self.labels.insert(a.to_owned(), label);
+        }
+        if let Some(label) = label_a {
+            self.labels.insert(b.to_owned(), label);
+        }
+        Ok(())
+    }
+
+    /// moves every window of activity src to the same workspace of activity dst (or the
+    /// first one of dst with collapse). src does not have to be configured anymore
+    pub async fn merge_activity(&self, src: &str, dst: &str, collapse: bool) -> Result<usize> {
+        let src = self.activity_by_alias(src).unwrap_or(src);
+        let dst = self
+            .activity_by_alias(dst)
+            .with_context(|| format!("unknown activity '{}'", dst))?;
+        if src == dst {
+            return Err(anyhow!("can't merge activity '{}' into itself", src));
+        }
+        let dst_index = self
+            .get_activity_index(dst)
+            .with_context(|| format!("unknown activity '{}'", dst))?;
+        let first = self.workspace_name(dst_index, 0);
+
+        let mut moved = 0;
+        for c in Clients::get_async().await?.into_iter() {
+            if activity_name(&c.workspace.name, self.config.flat_grid) != src {
+                continue;
+            }
+            let target = match WorkspaceName::parse(&c.workspace.name, self.config.flat_grid) {
+                Some(w) if !collapse => w.in_activity(dst).to_string(),
+                _ => first.clone(),
+            };
+            move_window(c.address, &target).await?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    /// moves windows from activities of older dates (eg. "scratch-{date}" from yesterday)
+    /// to the current one. returns the number of moved windows
+    pub async fn collect_stale_activities(&self) -> Result<usize> {
+        let clients = Clients::get_async().await?.into_iter().collect();
+        self.collect_stale_activities_in(clients).await
+    }
+
+    /// same as collect_stale_activities with the windows already known
+    pub async fn collect_stale_activities_in(&self, clients: Vec<Client>) -> Result<usize> {
+        let dated = self
+            .config
+            .activity_templates
+            .iter()
+            .filter(|(t, _)| t.contains("{date}"))
+            .collect::<Vec<_>>();
+        if dated.is_empty() {
+            return Ok(0);
+        }
+        let vars = template_vars(dated.iter().map(|(t, _)| t.as_str()))?;
+        let stale = dated
+            .into_iter()
+            .map(|(template, resolved)| {
+                let pattern = template
+                    .split("{date}")
+                    .map(|part| {
+                        let part = vars.iter().fold(part.to_owned(), |s, (k, v)| {
+                            s.replace(&format!("{{{}}}", k), v)
+                        });
+                        regex::escape(&part)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(r"\d{4}-\d{2}-\d{2}");
+                Ok((full_match_regex(&pattern)?, resolved))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut moved = 0;
+        for c in clients.into_iter() {
+            let Some(w) = WorkspaceName::parse(&c.workspace.name, self.config.flat_grid) else {
+                continue;
+            };
+            let activity = w.activity.as_str();
+            let Some((_, resolved)) = stale
+                .iter()
+                .find(|(re, resolved)| re.is_match(activity) && activity != resolved.as_str())
+            else {
+                continue;
+            };
+            move_window(c.address, &w.in_activity(resolved.as_str()).to_string()).await?;
+            moved += 1;
+        }
+        Ok(moved)
+    }
+
+    /// moves windows pinned with toggle-pin to the workspace
+    pub async fn move_following_windows(&mut self, workspace: &str) -> Result<()> {
+        if self.following.is_empty() || self.get_indices(workspace).is_none() {
+            return Ok(());
+        }
+        let clients = Clients::get_async().await?;
+        self.following
+            .retain(|a| clients.iter().any(|c| &c.address.to_string() == a));
+        for c in clients
+            .into_iter()
+            .filter(|c| self.following.contains(&c.address.to_string()))
+            .filter(|c| c.workspace.name != workspace)
+        {
+            move_window(c.address, workspace).await?;
+        }
+        Ok(())
+    }
+
+    /// restores windows that are already open and remembers the rest for when they open
+    pub async fn start_session_restore(&mut self) -> Result<()> {
+        let mut session = Session::load(&Session::default_path()?)?;
+        session.restore_open_windows().await?;
+        self.pending_session = session;
+        self.pending_session_until = Some(Instant::now() + Self::PENDING_SESSION_TIMEOUT);
+        Ok(())
+    }
+
+    /// false once the saved windows have been waited for long enough
+    fn pending_session_live(&self) -> bool {
+        self.pending_session_until
+            .is_some_and(|t| Instant::now() < t)
+    }
+
+    /// moves a newly opened window to its saved workspace. returns the workspace if it was in
+    /// the session
+    pub async fn restore_session_window(
+        &mut self,
+        address: Address,
+        class: &str,
+        title: &str,
+        workspace: &str,
+    ) -> Result<Option<String>> {
+        if !self.pending_session_live() {
+            self.pending_session = Default::default();
+            return Ok(None);
+        }
+        let Some(w) = self.pending_session.take_match(class, title) else {
+            return Ok(None);
+        };
+        if w.workspace != workspace {
+            move_window(address, &w.workspace).await?;
+        }
+        Ok(Some(w.workspace))
+    }
+
+    /// windows that have not been restored yet are kept in the saved session till they are
+    /// not waited for anymore
+    pub async fn save_session(&self) -> Result<()> {
+        let mut session = Session::capture(self).await?;
+        if self.pending_session_live() {
+            session
+                .windows
+                .extend(self.pending_session.windows.iter().cloned());
+        }
+        session.save(&Session::default_path()?)
+    }
+
+    /// removes the activity that was created for the window once its last window closes
+    pub async fn window_closed(&mut self, address: &str) -> Result<()> {
+        let Some(activity) = self.window_activities.remove(address) else {
+            return Ok(());
+        };
+        if self.window_activities.values().any(|a| a == &activity) {
+            return Ok(());
+        }
+        // windows that were moved into it keep it alive
+        let left = Clients::get_async()
+            .await?
+            .into_iter()
+            .filter(|c| c.address.to_string() != address)
+            .filter(|c| self.workspace_activity_index(&c.workspace.name).is_some())
+            .filter(|c| activity_name(&c.workspace.name, self.config.flat_grid) == activity)
+            .map(|c| c.address.to_string())
+            .collect::<Vec<_>>();
+        if !left.is_empty() {
+            for address in left {
+                self.window_activities.insert(address, activity.clone());
+            }
+            return Ok(());
+        }
+        self.activities.retain(|a| a != &activity);
+        self.focused.remove(&activity);
+        self.activity_history.retain(|a| a != &activity);
+        Ok(())
+    }
+
+    /// picks up the activities that new-activity rules made before the daemon restarted
+    pub fn rebuild_window_activities(&mut self, clients: &[Client]) {
+        let prefixes = self
+            .config
+            .rules
+            .iter()
+            .filter_map(|r| new_activity_prefix(&r.workspace))
+            .collect::<Vec<_>>();
+        for c in clients.iter().filter(|c| c.workspace.name.contains(':')) {
+            let activity = activity_name(&c.workspace.name, self.config.flat_grid);
+            let made_by_rule = activity
+                .rsplit_once('-')
+                .is_some_and(|(p, n)| prefixes.contains(&p) && n.parse::<u32>().is_ok());
+            if !made_by_rule || self.config.activities.iter().any(|a| a == activity) {
+                continue;
+            }
+            if !self.activities.iter().any(|a| a == activity) {
+                self.activities.push(activity.to_owned());
+            }
+            self.window_activities
+                .insert(c.address.to_string(), activity.to_owned());
+        }
+    }
+
+    /// moves the window to the workspace of the first matching window rule
+    pub async fn apply_window_rules(
+        &mut self,
+        address: Address,
+        class: &str,
+        title: &str,
+        workspace: &str,
+    ) -> Result<()> {
+        let Some(rule) = self.rules.find(class, title) else {
+            return Ok(());
+        };
+        if let Ok(Target {
+            activity,
+            cell: None,
+        }) = Target::parse(&rule.workspace, self.config.flat_grid)
+        {
+            // it follows the activity's focus, but windows already in the activity stay put
+            if self.workspace_activity_index(workspace).is_some()
+                && self.activity_by_alias(activity)
+                    == Some(activity_name(workspace, self.config.flat_grid))
+            {
+                return Ok(());
+            }
+        }
+        if let Some(prefix) = new_activity_prefix(&rule.workspace) {
+            let activity = (1..)
+                .map(|n| format!("{}-{}", prefix, n))
+                .find(|a| !self.activities.contains(a))
+                .expect("there are always more numbers");
+            self.activities.push(activity.clone());
+            let target = self.workspace_name(self.activities.len() - 1, 0);
+            self.window_activities
+                .insert(address.to_string(), activity.clone());
+            Dispatch::call_async(DispatchType::MoveToWorkspaceSilent(
+                WorkspaceIdentifierWithSpecial::Name(&target),
+                Some(WindowIdentifier::Address(address)),
+            ))
+            .await?;
+            return Ok(());
+        }
+        let target = self
+            .resolve_target(&rule.workspace)
+            .with_context(|| format!("could not resolve rule target '{}'", &rule.workspace))?;
+        if target == workspace {
+            return Ok(());
+        }
+        Dispatch::call_async(DispatchType::MoveToWorkspaceSilent(
+            WorkspaceIdentifierWithSpecial::Name(&target),
+            Some(WindowIdentifier::Address(address)),
+        ))
+        .await?;
+        Ok(())
+    }
+
+    pub async fn moved_workspace(&self, x: i64, y: i64, cycle: bool) -> Result<String> {
+        let workspace = self.current_cell().await?;
+        self.moved_workspace_from(&workspace, x, y, cycle)
+    }
+
+    /// the focused workspace if it is a cell. other workspaces (like hyprland's "1") are handled
+    /// as external_workspaces says
+    pub async fn current_cell(&self) -> Result<String> {
+        let current =
+            profile::span("query active workspace", Workspace::get_active_async()).await?;
+        if matches!(self.get_indices(&current.name), Some((_, Some(_)))) {
+            return Ok(current.name);
+        }
+        let last = self
+            .last_workspaces
+            .1
+            .clone()
+            .filter(|w| matches!(self.get_indices(w), Some((_, Some(_)))))
+            .unwrap_or_else(|| self.workspace_name(0, 0));
+        match self.config.external_workspaces {
+            ExternalWorkspaces::Error => Err(anyhow!("not on a hyprkool workspace")),
+            ExternalWorkspaces::LastCell => Ok(last),
+            ExternalWorkspaces::Adopt if current.windows == 0 => Ok(last),
+            ExternalWorkspaces::Adopt => {
+                let Some((activity, Some(index))) = self.get_indices(&last) else {
+                    return Ok(last);
+                };
+                let occupied = Workspaces::get_async()
+                    .await?
+                    .into_iter()
+                    .filter(|w| w.windows > 0)
+                    .map(|w| w.name)
+                    .collect::<HashSet<_>>();
+                let target = match occupied.contains(&last) {
+                    false => index,
+                    true => nearest_empty(index, self.config.workspaces, |i| {
+                        occupied.contains(&self.workspace_name(activity, i))
+                    })
+                    .with_context(|| {
+                        format!("no empty cell to adopt workspace '{}' into", current.name)
+                    })?,
+                };
+                let name = self.workspace_name(activity, target);
+                let mut batch = DispatchBatch::new();
+                batch.dispatch("renameworkspace", format!("{} {}", current.id, name));
+                batch.send().await?;
+                info!("adopted workspace '{}' as '{}'", current.name, name);
+                Ok(name)
+            }
+        }
+    }
+
+    /// workspace (x, y) away from the given one in the same activity. does not talk to hyprland
+    pub fn moved_workspace_from(
+        &self,
+        workspace_name: &str,
+        x: i64,
+        y: i64,
+        cycle: bool,
+    ) -> Result<String> {
+        let Some((activity_index, Some(workspace_index))) = self.get_indices(workspace_name) else {
             return Err(anyhow!("Error: not in a valid activity workspace"));
         };
-        let nx = self.config.workspaces.0 as i64;
-        let ny = self.config.workspaces.1 as i64;
-        let mut iy = workspace_index as i64 / nx;
-        let mut ix = workspace_index as i64 % nx;
-        if cycle {
-            ix += x + nx;
-            ix %= nx;
-            iy += y + ny;
-            iy %= ny;
-        } else {
-            ix += x;
-            ix = ix.max(0).min(nx - 1);
-            iy += y;
-            iy = iy.max(0).min(ny - 1);
+        // x is the visual direction
+        let x = if self.config.invert_horizontal { -x } else { x };
+        let i = self
+            .navigation
+            .moved_index(workspace_index, self.config.workspaces, (x, y), cycle);
+        Ok(self.workspace_name(activity_index, i))
+    }
+
+    /// moves (x, y) in the grid. with through_monitors, moving past the edge of the grid
+    /// focuses the monitor next to the focused one in that direction (or moves the window there)
+    pub async fn move_in_direction(
+        &mut self,
+        (x, y): (i64, i64),
+        cycle: bool,
+        move_window: bool,
+        through_monitors: bool,
+        anim: Animation,
+    ) -> Result<()> {
+        let current = self.current_cell().await?;
+        if through_monitors && self.moved_workspace_from(&current, x, y, false)? == current {
+            if let Some(monitor) = adjacent_monitor((x, y)).await? {
+                let mut batch = DispatchBatch::new();
+                match move_window {
+                    true => batch.dispatch("movewindow", format!("mon:{}", monitor.name)),
+                    false => batch.focus_monitor(&monitor.name),
+                };
+                return batch.send().await;
+            }
         }
-        Ok(&self.workspaces[activity_index][(iy * nx + ix) as usize])
+        let workspace = self.moved_workspace_from(&current, x, y, cycle)?;
+        self.move_to_workspace(workspace, move_window, anim).await
     }
 
     pub async fn move_to_workspace(
-        &self,
+        &mut self,
         name: impl AsRef<str>,
         move_window: bool,
         anim: Animation,
     ) -> Result<()> {
-        let res = set_workspace_anim(anim).await;
-        let name = name.as_ref();
+        self.move_to_workspace_then(name, move_window, anim, DispatchBatch::new())
+            .await
+    }
+
+    /// switches workspace and sends the extra dispatches in the same batch
+    pub async fn move_to_workspace_then(
+        &mut self,
+        name: impl AsRef<str>,
+        move_window: bool,
+        anim: Animation,
+        then: DispatchBatch,
+    ) -> Result<()> {
+        self.move_to_workspace_as(name, move_window, SwitchKind::of(anim), anim, then)
+            .await
+    }
+
+    /// kind picks the [animation] settings for the switch. anim is used if they have none
+    pub async fn move_to_workspace_as(
+        &mut self,
+        name: impl AsRef<str>,
+        move_window: bool,
+        kind: Option<SwitchKind>,
+        anim: Animation,
+        then: DispatchBatch,
+    ) -> Result<()> {
+        let current =
+            profile::span("query active workspace", Workspace::get_active_async()).await?;
+        let name = match name.as_ref() {
+            name if name != current.name => name,
+            // already there. so nothing to animate or dispatch, which makes repeated presses
+            // at the edges of the grid free
+            _ => match self.last_workspaces.0.as_deref() {
+                Some(previous) if self.config.back_and_forth && !move_window => previous,
+                _ => return then.send().await,
+            },
+        };
+        let pending = PendingSwitch {
+            to: name.to_owned(),
+            move_window,
+            kind,
+            anim,
+            then,
+            animation_override: self.animation_override,
+            source: self.switch_source,
+            command: self.switch_command.clone(),
+        };
+        // internal switches (like the info refresh hack) are not checked
+        if self.get_indices(name).is_some() {
+            if !self.focus.check_switch(
+                activity_name(&current.name, self.config.flat_grid),
+                activity_name(name, self.config.flat_grid),
+            ) {
+                info!("switch to {} refused by the focus session", name);
+                return Ok(());
+            }
+            if !self.config.daemon.pre_switch.command.is_empty() {
+                // the hook runs once the state is unlocked. see finish_pending_switch
+                *self
+                    .pending_switch
+                    .lock()
+                    .expect("pending switch lock poisoned") = Some(pending);
+                return Ok(());
+            }
+        }
+        self.switch_now(pending).await
+    }
+
+    async fn switch_now(&mut self, pending: PendingSwitch) -> Result<()> {
+        let PendingSwitch {
+            to: name,
+            move_window,
+            kind,
+            anim,
+            then,
+            ..
+        } = pending;
+        let name = name.as_str();
+        // nobody listening is not an error
+        let _ = self.switch_events.send(SwitchEvent {
+            event: SwitchEventKind::SwitchStarted,
+            source: self.switch_source,
+            to: name.to_owned(),
+        });
+        let (plugin_anim, keyword) = match (self.animation_override, kind) {
+            // turned off in hyprland too, so that it works without the plugin
+            (Some(Animation::None), _) => (Animation::None, Some("workspaces,0".to_owned())),
+            (Some(anim), _) => (anim, None),
+            (None, Some(kind)) => self.config.animation.get(kind).resolve(anim),
+            (None, None) => (anim, None),
+        };
+        let res = set_workspace_anim(plugin_anim).await;
+        let mut batch = DispatchBatch::new();
+        self.set_workspaces_animation(&mut batch, keyword).await;
         if move_window {
-            Dispatch::call_async(DispatchType::MoveToWorkspace(
-                WorkspaceIdentifierWithSpecial::Name(name),
-                None,
-            ))
-            .await?;
+            batch.move_to_workspace(name);
+        } else if self.paired.is_some() {
+            // each monitor keeps navigating in its own activity
+            batch.workspace_on_current_monitor(name);
         } else {
-            Dispatch::call_async(DispatchType::Workspace(
-                WorkspaceIdentifierWithSpecial::Name(name),
-            ))
-            .await?;
+            batch.workspace(name);
         }
+        batch.extend(then);
+        batch.send().await?;
+        let _ = self.switch_events.send(SwitchEvent {
+            event: SwitchEventKind::SwitchCompleted,
+            source: self.switch_source,
+            to: name.to_owned(),
+        });
+        self.switch_feedback(anim);
+        self.show_minimap(name).await;
+        self.notify_switch(name);
+        self.restore_workspace_anim().await?;
         res
     }
 
+    /// runs the pre-switch hook. false if it vetoed the switch
+    async fn pre_switch_check(hook: &PreSwitchHook, to: &str) -> Result<bool> {
+        let Some((cmd, args)) = hook.command.split_first() else {
+            return Ok(true);
+        };
+        let from = Workspace::get_active_async().await?.name;
+        let mut child = tokio::process::Command::new(cmd)
+            .args(args)
+            .env("HYPRKOOL_FROM", &from)
+            .env("HYPRKOOL_TO", to)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .context("could not run pre-switch hook")?;
+        match tokio::time::timeout(Duration::from_millis(hook.timeout), child.wait()).await {
+            Ok(status) => Ok(status?.success()),
+            Err(_) => {
+                warn!("pre-switch hook timed out. switching anyway");
+                Ok(true)
+            }
+        }
+    }
+
+    /// notification like "web ▸ (2,1)"
+    fn notify_switch(&self, workspace_name: &str) {
+        let Some((x, y)) = parse_cell(workspace_name, self.config.flat_grid) else {
+            return;
+        };
+        let activity = activity_name(workspace_name, self.config.flat_grid);
+        let (_, display) = self.activity_meta(activity);
+        let message = format!("{} ▸ ({},{})", display.as_deref().unwrap_or(activity), x, y);
+        self.switch_notifier
+            .switched(self.switch_command.as_deref(), message);
+    }
+
+    /// briefly shows the grid with the workspace that was switched to
+    async fn show_minimap(&self, workspace_name: &str) {
+        if !self.minimap.is_enabled() || self.get_indices(workspace_name).is_none() {
+            return;
+        }
+        let windows = match Workspaces::get_async().await {
+            Ok(ws) => ws.into_iter().map(|w| (w.name, w.windows)).collect(),
+            Err(e) => {
+                error!("could not get workspaces for the minimap: {}", e);
+                return;
+            }
+        };
+        if let Some(grid) = self.render_grid(workspace_name, &windows, self.minimap.theme()) {
+            self.minimap.show(&grid);
+        }
+    }
+
+    /// runs the configured feedback command without waiting for it
+    fn switch_feedback(&self, anim: Animation) {
+        let feedback = &self.config.daemon.on_switch_feedback;
+        let Some((cmd, args)) = feedback.command.split_first() else {
+            return;
+        };
+        let extra = match anim {
+            Animation::None | Animation::Native => return,
+            Animation::Left => &feedback.left,
+            Animation::Right => &feedback.right,
+            Animation::Up => &feedback.up,
+            Animation::Down => &feedback.down,
+            Animation::Fade => &feedback.other,
+        };
+
+        let now = Instant::now();
+        let mut last = self.last_feedback.lock().expect("feedback lock poisoned");
+        if last.is_some_and(|t| now < t + Duration::from_millis(feedback.min_interval)) {
+            return;
+        }
+        *last = Some(now);
+
+        let res = tokio::process::Command::new(cmd)
+            .args(args)
+            .args(extra)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .spawn();
+        if let Err(e) = res {
+            error!("could not run switch feedback command: {}", e);
+        }
+    }
+
+    /// sets the workspaces animation keyword for a switch, or puts back the one from before
+    /// if the switch has none. it stays till the next switch as hyprland reads the curve and
+    /// duration while animating
+    async fn set_workspaces_animation(
+        &mut self,
+        batch: &mut DispatchBatch,
+        keyword: Option<String>,
+    ) {
+        let Some(keyword) = keyword else {
+            self.keywords.restore(batch, "animation");
+            return;
+        };
+        if !self.keywords.is_changed("animation") {
+            match workspaces_animation().await {
+                Ok(original) => self.keywords.remember_original("animation", original),
+                Err(e) => {
+                    error!("could not get the workspaces animation: {}", e);
+                    return;
+                }
+            }
+        }
+        self.keywords
+            .change(batch, "animation", &keyword, "animation");
+    }
+
+    /// hyprland animates the switch as soon as it is dispatched. so the animation can be
+    /// restored immediately after
+    pub async fn restore_workspace_anim(&self) -> Result<()> {
+        if self.restore_animation {
+            set_workspace_anim(Animation::Native).await?;
+        }
+        Ok(())
+    }
+
     pub async fn move_window_to_workspace(&self, name: impl AsRef<str>) -> Result<()> {
         let name = name.as_ref();
         Dispatch::call_async(DispatchType::MoveToWorkspaceSilent(
@@ -144,49 +1055,520 @@ impl State {
     }
 
     pub async fn toggle_special_workspace(&self, name: String, anim: Animation) -> Result<()> {
-        let res = set_workspace_anim(anim).await;
+        let res = set_workspace_anim(self.animation_override.unwrap_or(anim)).await;
         Dispatch::call_async(DispatchType::ToggleSpecialWorkspace(Some(name))).await?;
+        self.restore_workspace_anim().await?;
         res
     }
 
-    pub fn get_activity_status_repr(&self, workspace_name: &str) -> Option<String> {
+    /// windows: number of windows in each workspace
+    pub fn get_activity_status_repr(
+        &self,
+        workspace_name: &str,
+        windows: &HashMap<String, u16>,
+    ) -> Option<String> {
+        self.render_grid(workspace_name, windows, &self.config.status)
+    }
+
+    /// grid of the workspace's activity drawn with the glyphs of status
+    pub fn render_grid(
+        &self,
+        workspace_name: &str,
+        windows: &HashMap<String, u16>,
+        status: &StatusConfig,
+    ) -> Option<String> {
         let Some((activity_index, Some(workspace_index))) = self.get_indices(workspace_name) else {
             return None;
         };
+        if status.style == StatusStyle::Text {
+            return self.describe_cell(activity_index, workspace_name, windows);
+        }
 
-        let mut activity = String::new();
+        let row_separator = match status.single_line {
+            true => &status.inline_row_separator,
+            false => &status.row_separator,
+        };
         let nx = self.config.workspaces.0 as usize;
-        let n = self.workspaces[activity_index].len();
-        for (i, _) in self.workspaces[activity_index].iter().enumerate() {
-            if i == 0 {
-            } else if i % nx == 0 && i > 0 && i < n {
-                activity += "\n";
-            } else {
-                activity += " ";
+        let rows = self
+            .workspace_rows(activity_index)
+            .enumerate()
+            .map(|(y, row)| {
+                let mut cells = row
+                    .iter()
+                    .enumerate()
+                    .map(|(x, w)| {
+                        if y * nx + x == workspace_index {
+                            status.active.as_str()
+                        } else if windows.get(w).is_some_and(|&n| n > 0) {
+                            status.occupied.as_str()
+                        } else {
+                            status.empty.as_str()
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                // render the row right to left if the grid is mirrored
+                if self.config.invert_horizontal {
+                    cells.reverse();
+                }
+                cells.join(&status.cell_separator)
+            })
+            .collect::<Vec<_>>();
+
+        Some(rows.join(row_separator))
+    }
+
+    /// the workspace in words. columns are counted as the grid is drawn (mirrored with
+    /// invert_horizontal)
+    fn describe_cell(
+        &self,
+        activity_index: usize,
+        workspace_name: &str,
+        windows: &HashMap<String, u16>,
+    ) -> Option<String> {
+        let (x, y) = parse_cell(workspace_name, self.config.flat_grid)?;
+        let (nx, ny) = self.config.workspaces;
+        let x = match self.config.invert_horizontal {
+            true => nx + 1 - x,
+            false => x,
+        };
+        let activity = &self.activities[activity_index];
+        let (_, display) = self.activity_meta(activity);
+        let n = windows.get(workspace_name).copied().unwrap_or(0);
+        let mut text = format!(
+            "Activity {}, row {} of {}, column {} of {}, {} {}",
+            display.as_deref().unwrap_or(activity),
+            y,
+            ny,
+            x,
+            nx,
+            n,
+            if n == 1 { "window" } else { "windows" }
+        );
+        if let Some(label) = self.labels.get(workspace_name) {
+            text.push_str(&format!(", {}", label));
+        }
+        Some(text)
+    }
+
+    /// changes the grid size at runtime. windows, rules and remembered workspaces
+    /// that are outside the new grid are moved to the closest cell inside it
+    pub async fn resize_grid(&mut self, x: u32, y: u32) -> Result<()> {
+        if x == 0 || y == 0 {
+            return Err(anyhow!("grid dimensions must be non zero"));
+        }
+        for c in Clients::get_async().await?.into_iter() {
+            if self.get_indices(&c.workspace.name).is_none() {
+                continue;
+            }
+            if let Some(target) = clamped_cell(&c.workspace.name, (x, y), self.config.flat_grid) {
+                move_window(c.address, &target).await?;
+            }
+        }
+        self.remap_to_grid(x, y)
+    }
+
+    /// points everything that refers to cells outside a grid of size (x, y) to the closest
+    /// cell inside it. marks keep working as they follow window addresses
+    fn remap_to_grid(&mut self, x: u32, y: u32) -> Result<()> {
+        let flat = self.config.flat_grid;
+        for w in self
+            .focused
+            .values_mut()
+            .chain(self.named_focii.values_mut())
+            .chain(self.config.rules.iter_mut().map(|r| &mut r.workspace))
+        {
+            if let Some(target) = clamped_cell(w, (x, y), flat) {
+                *w = target;
             }
-            if i == workspace_index {
-                activity += "   ";
-            } else {
-                activity += "███";
+        }
+        self.rules = Rules::new(&self.config.rules)?;
+
+        // a label is dropped if the cell it moves to has one already
+        let mut moved = self
+            .labels
+            .keys()
+            .filter_map(|w| Some((w.clone(), clamped_cell(w, (x, y), flat)?)))
+            .collect::<Vec<_>>();
+        moved.sort();
+        for (from, to) in moved {
+            if let Some(label) = self.labels.remove(&from) {
+                self.labels.entry(to).or_insert(label);
+            }
+        }
+
+        self.last_workspaces = (None, None);
+        self.config.workspaces = (x, y);
+        Ok(())
+    }
+
+    /// applies the keyword overrides and layout of the workspace's activity and cell
+    pub async fn apply_workspace_keywords(&mut self, workspace_name: &str) -> Result<()> {
+        let Some((i, cell)) = self.get_indices(workspace_name) else {
+            return Ok(());
+        };
+        let activity = &self.activities[i];
+        let mut overrides = HashMap::new();
+        if let Some(a) = self.config.activity.get(activity) {
+            overrides = a.keyword_values(&self.config.keyword_profiles);
+            let cell_layout = a
+                .cell_layouts
+                .iter()
+                .find(|(k, _)| {
+                    let t = format!("{}:{}", activity, k);
+                    let index = Target::parse(&t, self.config.flat_grid)
+                        .and_then(|t| t.cell_index(self.config.workspaces));
+                    cell.is_some() && index.ok().flatten() == cell
+                })
+                .map(|(_, l)| *l);
+            if let Some(layout) = cell_layout.or(a.layout) {
+                overrides.insert("general:layout".into(), layout.as_str().into());
+            }
+        }
+        self.keywords.enter(workspace_name, &overrides).await
+    }
+
+    /// remembers the previous hyprkool workspace for switch-back
+    pub fn remember_workspace_visit(&mut self, workspace_name: &str) {
+        if !matches!(self.get_indices(workspace_name), Some((_, Some(_)))) {
+            return;
+        }
+        if self.last_workspaces.1.as_deref() == Some(workspace_name) {
+            return;
+        }
+        let current = self.last_workspaces.1.replace(workspace_name.to_owned());
+        self.last_workspaces.0 = current;
+
+        if self.history_target.as_deref() == Some(workspace_name) {
+            self.history_target = None;
+            return;
+        }
+        self.history_target = None;
+        self.workspace_history.truncate(self.history_pos + 1);
+        self.workspace_history.push(workspace_name.to_owned());
+        if self.workspace_history.len() > Self::HISTORY_LIMIT {
+            self.workspace_history.remove(0);
+        }
+        self.history_pos = self.workspace_history.len() - 1;
+    }
+
+    /// workspace steps away in the history. moves the history position there
+    pub fn history_step(&mut self, steps: isize) -> Option<String> {
+        let pos = self.history_pos.checked_add_signed(steps)?;
+        let target = self.workspace_history.get(pos)?.clone();
+        self.history_pos = pos;
+        self.history_target = Some(target.clone());
+        Some(target)
+    }
+
+    pub fn remember_activity_visit(&mut self, workspace_name: &str) {
+        let Some(activity_index) = self.workspace_activity_index(workspace_name) else {
+            return;
+        };
+        let activity = &self.activities[activity_index];
+        self.activity_history.retain(|a| a != activity);
+        self.activity_history.push(activity.clone());
+    }
+
+    /// last n visited activities. most recent first
+    pub fn recent_activities(&self, n: usize) -> Vec<String> {
+        self.activity_history
+            .iter()
+            .rev()
+            .take(n)
+            .cloned()
+            .collect()
+    }
+
+    /// remembered focus of every activity that points to an empty or nonexistent workspace
+    /// is moved to the activity's most populated workspace (or its first workspace)
+    pub async fn reconcile_focus(&mut self) -> Result<()> {
+        let windows = Workspaces::get_async()
+            .await?
+            .into_iter()
+            .map(|w| (w.name, w.windows))
+            .collect::<HashMap<_, _>>();
+        self.reconcile_focus_with(&windows);
+        Ok(())
+    }
+
+    /// current name of a workspace that was named with an older scheme: "activity:n" (nth cell,
+    /// row by row), "activity:x,y" or a cell from before flat_grid was turned on or off.
+    /// None if it is not a hyprkool workspace or already named right
+    pub fn migrated_name(&self, workspace_name: &str) -> Option<String> {
+        if matches!(self.get_indices(workspace_name), Some((_, Some(_)))) {
+            return None;
+        }
+        if !self.config.flat_grid && parse_xy(workspace_name).is_some() {
+            // "(x y)" from flat_grid goes to the first activity
+            let first = self.activities.first()?;
+            return self
+                .resolve_target(&format!("{}:{}", first, workspace_name))
+                .ok();
+        }
+        let (activity, _) = workspace_name.split_once(':')?;
+        let t = Target::parse(workspace_name, self.config.flat_grid).ok()?;
+        let index = t.cell_index(self.config.workspaces).ok()??;
+        let activity_index = match self.config.flat_grid {
+            true => 0,
+            false => self.get_activity_index(activity)?,
+        };
+        Some(self.workspace_name(activity_index, index))
+    }
+
+    /// fills in what the daemon remembers (activities made by rules, focus of every activity,
+    /// history, stats) from the snapshot taken at startup, so it is right before the first
+    /// event arrives
+    pub fn warm_start(&mut self, snapshot: &Snapshot) {
+        self.rebuild_window_activities(&snapshot.clients);
+        self.reconcile_focus_with(&snapshot.windows());
+        // what the monitors show is the last focused workspace of those activities
+        for m in snapshot.monitors.iter() {
+            if let Some(w) = snapshot
+                .workspaces
+                .iter()
+                .find(|w| w.id == m.active_workspace.id)
+            {
+                self.remember_workspace(w);
             }
         }
+        let active = &snapshot.active_workspace;
+        self.remember_workspace(active);
+        self.remember_activity_visit(&active.name);
+        self.remember_workspace_visit(&active.name);
+        self.stats.focused(&active.name);
+    }
 
-        Some(activity)
+    /// windows: number of windows in each workspace
+    pub fn reconcile_focus_with(&mut self, windows: &HashMap<String, u16>) {
+        let focused = std::mem::take(&mut self.focused);
+        self.focused = focused
+            .into_iter()
+            .filter_map(|(a, w)| {
+                if windows.get(&w).copied().unwrap_or(0) > 0 {
+                    return Some((a, w));
+                }
+                let activity_index = self.activities.iter().position(|name| name == &a)?;
+                let fallback = self
+                    .workspace_names(activity_index)
+                    .filter(|w| windows.get(w).copied().unwrap_or(0) > 0)
+                    .max_by_key(|w| windows.get(w).copied().unwrap_or(0))
+                    .unwrap_or_else(|| self.workspace_name(activity_index, 0));
+                Some((a, fallback))
+            })
+            .collect();
     }
 
     pub fn remember_workspace(&mut self, w: &Workspace) {
-        let a = w
-            .name
-            .split_once(':')
-            .and_then(|(w, _)| self.activities.iter().find(|&a| a == w))
-            .cloned();
-        if let Some(a) = a {
+        if let Some(i) = self.workspace_activity_index(&w.name) {
+            let a = self.activities[i].clone();
             self.focused.insert(a, w.name.clone());
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// the one activity that workspaces belong to with flat_grid
+pub const FLAT_ACTIVITY: &str = "default";
+
+/// name of a cell workspace. "activity:(x y)", or "(x y)" with flat_grid. there is no comma in
+/// it as hyprland splits dispatcher args on commas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceName {
+    pub activity: String,
+    /// column starting at 1
+    pub x: u32,
+    /// row starting at 1
+    pub y: u32,
+    /// named without the activity, as with flat_grid
+    pub flat: bool,
+}
+impl WorkspaceName {
+    pub fn new(activity: impl Into<String>, x: u32, y: u32, flat: bool) -> Self {
+        Self {
+            activity: activity.into(),
+            x,
+            y,
+            flat,
+        }
+    }
+
+    /// None if the name is not a cell. the activity is not checked against the config
+    pub fn parse(workspace_name: &str, flat: bool) -> Option<Self> {
+        let (x, y) = parse_cell(workspace_name, flat)?;
+        let activity = activity_name(workspace_name, flat);
+        Some(Self::new(activity, x, y, flat))
+    }
+
+    /// the same cell in another activity
+    pub fn in_activity(&self, activity: impl Into<String>) -> Self {
+        Self::new(activity, self.x, self.y, self.flat)
+    }
+}
+impl fmt::Display for WorkspaceName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.flat {
+            true => write!(f, "({} {})", self.x, self.y),
+            false => write!(f, "{}:({} {})", self.activity, self.x, self.y),
+        }
+    }
+}
+
+/// name of the workspace at cell (x, y) of the activity
+pub fn cell_workspace_name(activity: &str, x: u32, y: u32, flat: bool) -> String {
+    WorkspaceName::new(activity, x, y, flat).to_string()
+}
+
+/// workspace part of a workspace name including the ':'. eg. ":(1 1)"
+pub fn workspace_suffix(workspace_name: &str) -> Option<&str> {
+    workspace_name.find(':').map(|i| &workspace_name[i..])
+}
+
+/// (x, y) of a workspace name like "activity:(x y)" (or "(x y)" if flat)
+pub fn parse_cell(workspace_name: &str, flat: bool) -> Option<(u32, u32)> {
+    let cell = match workspace_suffix(workspace_name) {
+        Some(suffix) => suffix.strip_prefix(':')?,
+        None if flat => workspace_name,
+        None => return None,
+    };
+    parse_xy(cell)
+}
+
+/// name of the closest cell to workspace_name in a grid of size (x, y). None if it is
+/// not a cell or already inside the grid
+fn clamped_cell(workspace_name: &str, (x, y): (u32, u32), flat: bool) -> Option<String> {
+    let (cx, cy) = parse_cell(workspace_name, flat)?;
+    if cx <= x && cy <= y {
+        return None;
+    }
+    Some(cell_workspace_name(
+        activity_name(workspace_name, flat),
+        cx.min(x),
+        cy.min(y),
+        flat,
+    ))
+}
+
+/// moves the windows of workspace a to b and the ones of b to a. windows are
+/// (workspace, address)
+fn swap_windows(windows: &[(String, String)], a: &str, b: &str) -> DispatchBatch {
+    let mut batch = DispatchBatch::new();
+    for (workspace, address) in windows {
+        let to = match workspace.as_str() {
+            w if w == a => b,
+            w if w == b => a,
+            _ => continue,
+        };
+        batch.move_window_silent(address, to);
+    }
+    batch
+}
+
+/// (x, y) of "(x y)". the numbers are written as cell_workspace_name writes them, so
+/// "(+1 1)" or "(01 1)" are different workspaces
+fn parse_xy(cell: &str) -> Option<(u32, u32)> {
+    let cell = cell.strip_prefix('(')?.strip_suffix(')')?;
+    let (x, y) = cell.split_once(' ')?;
+    let number = |n: &str| match n.starts_with('0') || !n.bytes().all(|b| b.is_ascii_digit()) {
+        true => None,
+        false => n.parse().ok(),
+    };
+    Some((number(x)?, number(y)?))
+}
+
+/// index of the cell (x, y) away from index in a row major grid of size (nx, ny).
+/// wraps around if cycle, else stays at the grid's border
+pub fn moved_index(index: usize, (nx, ny): (u32, u32), (x, y): (i64, i64), cycle: bool) -> usize {
+    let (nx, ny) = (nx as i64, ny as i64);
+    let (ix, iy) = (index as i64 % nx, index as i64 / nx);
+    let (ix, iy) = if cycle {
+        ((ix + x).rem_euclid(nx), (iy + y).rem_euclid(ny))
+    } else {
+        ((ix + x).clamp(0, nx - 1), (iy + y).clamp(0, ny - 1))
+    };
+    (iy * nx + ix) as usize
+}
+
+/// index of the closest (manhattan distance) other cell that is not occupied. the first in
+/// row major order of the equally close ones
+pub fn nearest_empty(
+    index: usize,
+    (nx, ny): (u32, u32),
+    occupied: impl Fn(usize) -> bool,
+) -> Option<usize> {
+    let nx = nx as usize;
+    let (x, y) = (index % nx, index / nx);
+    (0..nx * ny as usize)
+        .filter(|&i| i != index && !occupied(i))
+        .min_by_key(|&i| (i % nx).abs_diff(x) + (i / nx).abs_diff(y))
+}
+
+/// closest monitor in the direction (x, y) from the focused monitor that overlaps it on the
+/// other axis, going by the monitor layout
+async fn adjacent_monitor((x, y): (i64, i64)) -> Result<Option<Monitor>> {
+    let monitors = Monitors::get_async().await?.into_iter().collect::<Vec<_>>();
+    let Some(focused) = monitors.iter().find(|m| m.focused) else {
+        return Ok(None);
+    };
+    let r = Rect::of(focused);
+    let monitor = monitors
+        .iter()
+        .filter(|m| m.name != focused.name)
+        .filter_map(|m| {
+            let o = Rect::of(m);
+            let overlaps_y = o.y < r.y + r.h && r.y < o.y + o.h;
+            let overlaps_x = o.x < r.x + r.w && r.x < o.x + o.w;
+            let gap = match (x.signum(), y.signum()) {
+                (1, _) if overlaps_y => o.x - (r.x + r.w),
+                (-1, _) if overlaps_y => r.x - (o.x + o.w),
+                (_, 1) if overlaps_x => o.y - (r.y + r.h),
+                (_, -1) if overlaps_x => r.y - (o.y + o.h),
+                _ => return None,
+            };
+            (gap >= 0).then_some((gap, m))
+        })
+        .min_by_key(|(gap, _)| *gap)
+        .map(|(_, m)| m.clone());
+    Ok(monitor)
+}
+
+/// activity part of a workspace name. activity names can not contain ':'. flat grid cells
+/// are in FLAT_ACTIVITY
+pub fn activity_name(workspace_name: &str, flat: bool) -> &str {
+    if flat && parse_cell(workspace_name, flat).is_some() && !workspace_name.contains(':') {
+        return FLAT_ACTIVITY;
+    }
+    workspace_name
+        .split_once(':')
+        .map(|(a, _)| a)
+        .unwrap_or(workspace_name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SwitchSource {
+    /// hyprkool commands (keybinds, scripts)
+    Keyboard,
+    /// screen edges
+    Mouse,
+    /// touchpad swipes
+    Gesture,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchEventKind {
+    SwitchStarted,
+    SwitchCompleted,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SwitchEvent {
+    pub event: SwitchEventKind,
+    pub source: SwitchSource,
+    /// workspace being switched to
+    pub to: String,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Animation {
     None = 0,
     Left = 1,
@@ -194,59 +1576,271 @@ pub enum Animation {
     Up = 3,
     Down = 4,
     Fade = 5,
+    /// whatever is configured in hyprland.conf
+    Native = 6,
 }
 
 // TODO: do all this plugin ipc properly
 pub async fn is_plugin_running() -> Result<bool> {
-    _send_plugin_event(Animation::None as _).await
+    _send_plugin_event(Animation::Native as _).await
 }
 
 pub async fn set_workspace_anim(anim: Animation) -> Result<()> {
-    _send_plugin_event(anim as _).await?;
+    profile::span(
+        format!("plugin animation ({:?})", anim),
+        _send_plugin_event(anim as _),
+    )
+    .await?;
     Ok(())
 }
 
 async fn _send_plugin_event(e: usize) -> Result<bool> {
     let sock_path = get_plugin_socket_path()?;
 
-    if let Ok(sock) = UnixStream::connect(&sock_path).await {
-        let mut sock = BufWriter::new(sock);
-        sock.write_all(format!("{}", e).as_bytes()).await?;
-        sock.flush().await?;
-        sock.shutdown().await?;
-
-        let sleep = tokio::time::sleep(Duration::from_millis(300));
-        let mut sock = BufReader::new(sock);
-        let mut line = String::new();
-        tokio::select! {
-            res = sock.read_line(&mut line) => {
-                res?;
-                let command = match serde_json::from_str(&line) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        println!("{}", e);
-                        return Ok(false);
-                    }
-                };
-                match command {
-                    Message::IpcOk => {
-                        println!("Ok");
-                        return Ok(true);
-                    }
-                    Message::IpcErr(message) => {
-                        println!("{}", message);
-                        return Ok(false);
-                    }
-                    _ => {
-                        unreachable!();
-                    }
+    // plugin is not loaded
+    let Ok(sock) = UnixStream::connect(&sock_path).await else {
+        return Ok(false);
+    };
+
+    let mut sock = BufWriter::new(sock);
+    sock.write_all(format!("{}", e).as_bytes()).await?;
+    sock.flush().await?;
+    sock.shutdown().await?;
+
+    let sleep = tokio::time::sleep(Duration::from_millis(300));
+    let mut sock = BufReader::new(sock);
+    let mut line = String::new();
+    tokio::select! {
+        res = sock.read_line(&mut line) => {
+            res?;
+            let command = serde_json::from_str(&line)
+                .with_context(|| format!("invalid response from hyprkool plugin: {:?}", line))?;
+            match command {
+                Message::IpcOk => Ok(true),
+                Message::IpcErr(message) => Err(anyhow!("hyprkool plugin error: {}", message)),
+                _ => {
+                    unreachable!();
                 }
             }
-            _ = sleep => {
-                println!("timeout. could not connect to hyprkool plugin");
+        }
+        _ = sleep => {
+            Err(anyhow!("timeout. could not connect to hyprkool plugin"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WindowRule;
+
+    fn state() -> State {
+        let config = Config {
+            activities: vec!["work".into(), "play".into()],
+            workspaces: (3, 2),
+            ..Default::default()
+        };
+        State::new(config).unwrap()
+    }
+
+    #[test]
+    fn workspace_names() {
+        assert_eq!(cell_workspace_name("work", 2, 1, false), "work:(2 1)");
+        assert_eq!(parse_cell("work:(2 1)", false), Some((2, 1)));
+        assert_eq!(parse_cell("work:2,1", false), None);
+        assert_eq!(parse_cell("(2 1)", false), None);
+        assert_eq!(parse_cell("work:(+1 1)", false), None);
+        assert_eq!(parse_cell("work:(1 01)", false), None);
+        assert_eq!(parse_cell("work:(0 1)", false), None);
+        assert_eq!(parse_cell("work:( 1 1)", false), None);
+        assert_eq!(parse_cell("work", false), None);
+        assert_eq!(activity_name("work:(2 1)", false), "work");
+        assert_eq!(activity_name("work", false), "work");
+        assert_eq!(workspace_suffix("work:(2 1)"), Some(":(2 1)"));
+
+        let name = WorkspaceName::parse("work:(3 2)", false).unwrap();
+        assert_eq!(name, WorkspaceName::new("work", 3, 2, false));
+        assert_eq!(name.in_activity("play").to_string(), "play:(3 2)");
+        assert_eq!(WorkspaceName::parse("1", false), None);
+
+        // flat grid cells have no activity in their names
+        assert_eq!(cell_workspace_name(FLAT_ACTIVITY, 2, 1, true), "(2 1)");
+        assert_eq!(parse_cell("(2 1)", true), Some((2, 1)));
+        assert_eq!(activity_name("(2 1)", true), FLAT_ACTIVITY);
+        let name = WorkspaceName::parse("(3 2)", true).unwrap();
+        assert_eq!(name, WorkspaceName::new(FLAT_ACTIVITY, 3, 2, true));
+        assert_eq!(name.to_string(), "(3 2)");
+    }
+
+    #[test]
+    fn indices_of_workspaces() {
+        let s = state();
+        assert_eq!(s.get_indices("work:(1 1)"), Some((0, Some(0))));
+        assert_eq!(s.get_indices("play:(2 2)"), Some((1, Some(4))));
+        // workspaces of the activity that are not in the grid
+        assert_eq!(s.get_indices("play:(4 1)"), Some((1, None)));
+        assert_eq!(s.get_indices("play:extra"), Some((1, None)));
+        assert_eq!(s.get_indices("chat:(1 1)"), None);
+
+        assert_eq!(s.workspace_count(), 6);
+        assert_eq!(s.workspace_name(1, 4), "play:(2 2)");
+        assert_eq!(
+            s.workspace_names(0).collect::<Vec<_>>(),
+            [
+                "work:(1 1)",
+                "work:(2 1)",
+                "work:(3 1)",
+                "work:(1 2)",
+                "work:(2 2)",
+                "work:(3 2)"
+            ]
+        );
+        assert_eq!(
+            s.workspace_rows(1).collect::<Vec<_>>(),
+            [
+                ["play:(1 1)", "play:(2 1)", "play:(3 1)"],
+                ["play:(1 2)", "play:(2 2)", "play:(3 2)"]
+            ]
+        );
+        assert_eq!(s.get_activity_index("play:(1 1)"), Some(1));
+        assert_eq!(s.get_activity_index("play"), Some(1));
+    }
+
+    #[test]
+    fn activities_of_workspaces() {
+        let s = State::new(Config {
+            activities: vec!["1".into(), "work".into()],
+            ..Default::default()
+        })
+        .unwrap();
+        // hyprland's numbered workspace is not the activity "1"
+        assert_eq!(s.workspace_activity_index("1"), None);
+        assert_eq!(s.get_activity_index("1"), Some(0));
+        assert_eq!(s.workspace_activity_index("1:(1 1)"), Some(0));
+        assert_eq!(s.workspace_activity_index("1-work"), None);
+        assert_eq!(s.workspace_activity_index("work:(1 1)"), Some(1));
+        assert_eq!(s.workspace_activity_index("work"), None);
+        assert_eq!(s.workspace_activity_index("(1 1)"), None);
+        assert_eq!(s.workspace_activity_index("special:magic"), None);
+        assert_eq!(s.workspace_activity_index("special"), None);
+    }
+
+    #[test]
+    fn activities_of_flat_grid_workspaces() {
+        let s = State::new(Config {
+            activities: vec![],
+            flat_grid: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let index = |name| s.workspace_activity_index(name);
+        assert_eq!(index("(1 1)"), Some(0));
+        assert_eq!(index("(2 2)"), Some(0));
+        assert_eq!(activity_name("(2 2)", true), FLAT_ACTIVITY);
+        assert_eq!(index("1"), None);
+        assert_eq!(index("(1 x)"), None);
+        assert_eq!(index("special:magic"), None);
+        assert_eq!(index("special"), None);
+
+        // each State goes by its own config
+        let grid = state();
+        assert_eq!(s.workspace_name(0, 1), "(2 1)");
+        assert_eq!(grid.workspace_name(0, 1), "work:(2 1)");
+        assert_eq!(grid.workspace_activity_index("(1 1)"), None);
+        assert_eq!(s.workspace_activity_index("(1 1)"), Some(0));
+    }
+
+    #[test]
+    fn custom_navigation() {
+        /// every workspace in one long row
+        #[derive(Debug)]
+        struct Linear;
+        impl NavigationPolicy for Linear {
+            fn moved_index(
+                &self,
+                i: usize,
+                (nx, ny): (u32, u32),
+                (x, y): (i64, i64),
+                _cycle: bool,
+            ) -> usize {
+                (i as i64 + x + y * nx as i64).rem_euclid((nx * ny) as i64) as usize
             }
         }
+
+        let s = state().with_navigation(Linear);
+        let moved = |name, x, y| s.moved_workspace_from(name, x, y, false).unwrap();
+        // the grid would stop at the edge
+        assert_eq!(moved("work:(3 1)", 1, 0), "work:(1 2)");
+        assert_eq!(moved("work:(3 2)", 1, 0), "work:(1 1)");
+        assert_eq!(moved("play:(2 1)", 0, 1), "play:(2 2)");
+        assert!(s.moved_workspace_from("1", 1, 0, false).is_err());
     }
 
-    Ok(false)
+    #[test]
+    fn swaps_with_empty_workspaces() {
+        let windows = [
+            ("work:(1 1)".to_owned(), "0x1".to_owned()),
+            ("work:(1 1)".to_owned(), "0x2".to_owned()),
+            ("work:(3 1)".to_owned(), "0x3".to_owned()),
+        ];
+        // nothing is on work:(2 1) yet, the names have to be used to create it
+        assert_eq!(
+            swap_windows(&windows, "work:(1 1)", "work:(2 1)").commands(),
+            [
+                "dispatch movetoworkspacesilent name:work:(2 1),address:0x1",
+                "dispatch movetoworkspacesilent name:work:(2 1),address:0x2",
+            ]
+        );
+        assert_eq!(
+            swap_windows(&windows, "work:(3 1)", "work:(1 1)").commands(),
+            [
+                "dispatch movetoworkspacesilent name:work:(3 1),address:0x1",
+                "dispatch movetoworkspacesilent name:work:(3 1),address:0x2",
+                "dispatch movetoworkspacesilent name:work:(1 1),address:0x3",
+            ]
+        );
+    }
+
+    #[test]
+    fn shrinking_the_grid_remaps_what_points_outside() {
+        let mut s = State::new(Config {
+            activities: vec!["work".into(), "play".into()],
+            workspaces: (3, 3),
+            named_focii: HashMap::from([
+                ("mail".into(), "work:(3 3)".into()),
+                ("chat".into(), "play:2,1".into()),
+            ]),
+            rules: vec![WindowRule {
+                priority: 0,
+                class: Some("steam".into()),
+                title: None,
+                workspace: "play:9".into(),
+            }],
+            ..Default::default()
+        })
+        .unwrap();
+        s.focused.insert("work".into(), "work:(1 3)".into());
+        for (w, label) in [
+            ("work:(2 2)", "kept"),
+            ("work:(3 2)", "dropped"),
+            ("play:(3 1)", "moved"),
+        ] {
+            s.labels.insert(w.into(), label.into());
+        }
+
+        s.remap_to_grid(2, 2).unwrap();
+        assert_eq!(s.config.workspaces, (2, 2));
+        assert_eq!(s.named_focii["mail"], "work:(2 2)");
+        assert_eq!(s.named_focii["chat"], "play:(2 1)");
+        assert_eq!(s.config.rules[0].workspace, "play:(2 2)");
+        assert_eq!(s.focused["work"], "work:(1 2)");
+        assert_eq!(
+            s.labels,
+            HashMap::from([
+                ("work:(2 2)".into(), "kept".into()),
+                ("play:(2 1)".into(), "moved".into()),
+            ])
+        );
+        assert_eq!(s.resolve_target("mail").unwrap(), "work:(2 2)");
+    }
 }
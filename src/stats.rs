@@ -0,0 +1,172 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use clap::{Subcommand, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::State;
+
+#[derive(Subcommand, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum StatsCommand {
+    /// how long every cell of every activity was focused, drawn over the grid
+    Heatmap {
+        #[arg(long, value_enum, default_value_t = HeatmapFormat::Json)]
+        format: HeatmapFormat,
+    },
+}
+
+#[derive(ValueEnum, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum HeatmapFormat {
+    Json,
+    Svg,
+}
+
+/// how long each workspace was focused since the daemon started
+#[derive(Debug, Default)]
+pub struct FocusStats {
+    time: HashMap<String, Duration>,
+    /// focused workspace and since when
+    current: Option<(String, Instant)>,
+}
+
+impl FocusStats {
+    pub fn focused(&mut self, workspace_name: &str) {
+        let now = Instant::now();
+        if let Some((w, since)) = self.current.take() {
+            *self.time.entry(w).or_default() += now - since;
+        }
+        self.current = Some((workspace_name.to_owned(), now));
+    }
+
+    /// time focused so far, including the ongoing focus
+    pub fn time(&self, workspace_name: &str) -> Duration {
+        let ongoing = match &self.current {
+            Some((w, since)) if w == workspace_name => since.elapsed(),
+            _ => Duration::ZERO,
+        };
+        self.time.get(workspace_name).copied().unwrap_or_default() + ongoing
+    }
+}
+
+#[derive(Serialize, Debug)]
+struct ActivityHeatmap {
+    name: String,
+    /// seconds focused, row by row
+    cells: Vec<Vec<u64>>,
+}
+
+impl StatsCommand {
+    pub fn execute(&self, state: &State) -> Result<String> {
+        match self {
+            StatsCommand::Heatmap { format } => {
+                let heatmaps = state
+                    .activities
+                    .iter()
+                    .enumerate()
+                    .map(|(i, name)| ActivityHeatmap {
+                        name: name.clone(),
+                        cells: state
+                            .workspace_rows(i)
+                            .map(|row| row.iter().map(|w| state.stats.time(w).as_secs()).collect())
+                            .collect(),
+                    })
+                    .collect::<Vec<_>>();
+                match format {
+                    HeatmapFormat::Json => Ok(serde_json::to_string(&heatmaps)?),
+                    HeatmapFormat::Svg => Ok(svg(&heatmaps)),
+                }
+            }
+        }
+    }
+}
+
+/// one grid per activity, stacked. cells are more opaque the longer they were focused
+fn svg(heatmaps: &[ActivityHeatmap]) -> String {
+    const CELL: usize = 40;
+    const GAP: usize = 4;
+    const TITLE: usize = 20;
+
+    let max = heatmaps
+        .iter()
+        .flat_map(|a| a.cells.iter().flatten())
+        .copied()
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let ny = heatmaps.first().map(|a| a.cells.len()).unwrap_or(0);
+    let nx = heatmaps
+        .first()
+        .and_then(|a| a.cells.first())
+        .map(Vec::len)
+        .unwrap_or(0);
+    let width = GAP + nx * (CELL + GAP);
+    let height = TITLE + ny * (CELL + GAP);
+
+    let mut out = vec![format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        width,
+        height * heatmaps.len()
+    )];
+    for (i, a) in heatmaps.iter().enumerate() {
+        let top = i * height;
+        out.push(format!(
+            r#"<text x="{}" y="{}">{}</text>"#,
+            GAP,
+            top + TITLE - 6,
+            escape(&a.name)
+        ));
+        for (y, row) in a.cells.iter().enumerate() {
+            for (x, secs) in row.iter().enumerate() {
+                out.push(format!(
+                    r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#e0502a" fill-opacity="{:.2}"><title>{}:({} {}) {}m</title></rect>"##,
+                    GAP + x * (CELL + GAP),
+                    top + TITLE + y * (CELL + GAP),
+                    CELL,
+                    CELL,
+                    0.05 + 0.95 * *secs as f64 / max as f64,
+                    escape(&a.name),
+                    x + 1,
+                    y + 1,
+                    secs / 60
+                ));
+            }
+        }
+    }
+    out.push("</svg>".into());
+    out.join("\n")
+}
+
+/// activity names can have anything in them
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_names_in_svg() {
+        let heatmaps = [ActivityHeatmap {
+            name: r#"<a & "b">"#.into(),
+            cells: vec![vec![60]],
+        }];
+        let svg = svg(&heatmaps);
+        assert!(svg.contains(">&lt;a &amp; &quot;b&quot;&gt;</text>"));
+        assert!(svg.contains("<title>&lt;a &amp; &quot;b&quot;&gt;:(1 1) 1m</title>"));
+        assert!(!svg.contains("<a "));
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use hyprland::{
+    data::Client,
+    dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial},
+    shared::HyprDataActiveOptional,
+};
+
+use crate::{config::FocusSteal, state::activity_name, State};
+
+/// reverts activity switches caused by windows opening in background activities
+#[derive(Debug, Default)]
+pub struct FocusStealGuard {
+    /// last focused regular workspace
+    current: Option<String>,
+    /// (from, to, when) of the last switch to another activity
+    switched: Option<(String, String, Instant)>,
+    /// workspace a window was last opened on
+    opened: Option<(String, Instant)>,
+    /// addresses of windows that recently asked for attention
+    urgent: HashMap<String, Instant>,
+}
+
+impl FocusStealGuard {
+    /// switches and window opens closer than this are treated as focus steals
+    const STEAL_WINDOW: Duration = Duration::from_millis(1000);
+    /// windows opened later than this after a switch were most likely opened by the user
+    /// after switching
+    const OPENED_AFTER_SWITCH: Duration = Duration::from_millis(100);
+
+    pub fn workspace_changed(&mut self, name: String, flat_grid: bool) {
+        if let Some(prev) = self.current.replace(name.clone()) {
+            if activity_name(&prev, flat_grid) != activity_name(&name, flat_grid) {
+                self.switched = Some((prev, name, Instant::now()));
+            }
+        }
+    }
+
+    pub fn window_opened(&mut self, workspace: String) {
+        self.opened = Some((workspace, Instant::now()));
+    }
+
+    pub fn urgent(&mut self, address: String) {
+        self.urgent.retain(|_, t| t.elapsed() < Self::STEAL_WINDOW);
+        self.urgent.insert(address, Instant::now());
+    }
+}
+
+impl State {
+    /// checks if the last activity switch was caused by a window opening and
+    /// switches back if the activity does not allow it
+    pub async fn revert_focus_steal(&mut self) -> Result<()> {
+        let guard = &mut self.focus_steal;
+        let (Some((from, to, switched_at)), Some((opened, opened_at))) =
+            (&guard.switched, &guard.opened)
+        else {
+            return Ok(());
+        };
+        if opened != to
+            || switched_at.elapsed() > FocusStealGuard::STEAL_WINDOW
+            || opened_at.elapsed() > FocusStealGuard::STEAL_WINDOW
+            || opened_at.saturating_duration_since(*switched_at)
+                > FocusStealGuard::OPENED_AFTER_SWITCH
+        {
+            return Ok(());
+        }
+        let (from, to) = (from.clone(), to.clone());
+
+        let policy = self
+            .config
+            .activity
+            .get(activity_name(&to, self.config.flat_grid))
+            .map(|a| a.focus_steal)
+            .unwrap_or_default();
+        let allowed = match policy {
+            FocusSteal::Allow => true,
+            FocusSteal::Deny => false,
+            FocusSteal::UrgentOnly => match Client::get_active_async().await? {
+                Some(c) => self.focus_steal.urgent.contains_key(&c.address.to_string()),
+                None => false,
+            },
+        };
+        self.focus_steal.switched = None;
+        self.focus_steal.opened = None;
+        if allowed {
+            return Ok(());
+        }
+
+        Dispatch::call_async(DispatchType::Workspace(
+            WorkspaceIdentifierWithSpecial::Name(&from),
+        ))
+        .await?;
+        Ok(())
+    }
+}
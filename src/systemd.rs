@@ -0,0 +1,138 @@
+use std::{
+    os::{
+        fd::{AsRawFd, FromRawFd},
+        unix::net::UnixListener as StdUnixListener,
+    },
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use tokio::net::UnixListener;
+use tracing::warn;
+
+use crate::cli::Cli;
+
+/// where the socket unit listens. the daemon socket path depends on the hyprland instance,
+/// which a socket unit can't know, so clients fall back to this one
+pub fn activation_socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir().map(|p| p.join("hyprkool.sock"))
+}
+
+/// the listening socket that systemd passes when the daemon is socket activated
+/// (sd_listen_fds). None if the daemon was started some other way
+pub fn activated_listener() -> Result<Option<UnixListener>> {
+    /// first fd after stdin, stdout and stderr
+    const SD_LISTEN_FDS_START: i32 = 3;
+
+    let pid = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|p| p.parse::<u32>().ok());
+    let fds = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<u32>().ok())
+        .unwrap_or(0);
+    // the vars are not cleared, as that is not safe with other threads running. hooks and
+    // launched apps inherit them but don't take them for theirs, as LISTEN_PID is not their pid
+    if pid != Some(std::process::id()) || fds == 0 {
+        return Ok(None);
+    }
+    if fds > 1 {
+        warn!("systemd passed {} sockets. only the first one is used", fds);
+    }
+
+    // SAFETY: LISTEN_PID says that the fds were passed to this process, and nothing else
+    // takes ownership of them
+    let listener = unsafe { StdUnixListener::from_raw_fd(SD_LISTEN_FDS_START) };
+    // systemd passes it without close-on-exec. launched apps should not get the socket
+    // SAFETY: the fd is owned by listener
+    if unsafe { libc::fcntl(listener.as_raw_fd(), libc::F_SETFD, libc::FD_CLOEXEC) } == -1 {
+        return Err(std::io::Error::last_os_error()).context("could not set FD_CLOEXEC");
+    }
+    listener.set_nonblocking(true)?;
+    Ok(Some(UnixListener::from_std(listener)?))
+}
+
+/// writes hyprkool.socket and hyprkool.service user units. returns the directory they are in
+pub fn install_service(cli: &Cli, move_to_hyprkool_activity: bool) -> Result<PathBuf> {
+    let dir = dirs::config_dir()
+        .context("could not find the config dir")?
+        .join("systemd/user");
+    std::fs::create_dir_all(&dir).with_context(|| format!("could not create {:?}", &dir))?;
+
+    let exe = std::env::current_exe().context("could not find the hyprkool executable")?;
+    let mut exec_start = vec![exe.display().to_string(), "--journald".into()];
+    if let Some(config_dir) = &cli.config_dir {
+        exec_start.push(format!("--config-dir={}", config_dir));
+    }
+    if cli.no_restore {
+        exec_start.push("--no-restore".into());
+    }
+    // the socket unit owns the daemon, so it takes over from one started by hand
+    exec_start.extend(["daemon".into(), "--replace".into()]);
+    if move_to_hyprkool_activity {
+        exec_start.push("--move-to-hyprkool-activity".into());
+    }
+
+    let socket = "[Unit]
+Description=hyprkool daemon socket
+
+[Socket]
+ListenStream=%t/hyprkool.sock
+SocketMode=0600
+
+[Install]
+WantedBy=sockets.target
+";
+    let service = format!(
+        "[Unit]
+Description=hyprkool daemon
+Requires=hyprkool.socket
+After=hyprkool.socket
+
+[Service]
+ExecStart={}
+Restart=on-failure
+",
+        exec_start
+            .iter()
+            .map(|a| exec_arg(a))
+            .collect::<Vec<_>>()
+            .join(" ")
+    );
+
+    for (name, contents) in [("hyprkool.socket", socket), ("hyprkool.service", &service)] {
+        let path = dir.join(name);
+        std::fs::write(&path, contents).with_context(|| format!("could not write {:?}", &path))?;
+    }
+    Ok(dir)
+}
+
+/// quotes an argument for ExecStart if it has to be. % and $ are escaped as systemd would
+/// expand them
+fn exec_arg(arg: &str) -> String {
+    let escaped = arg
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('%', "%%")
+        .replace('$', "$$");
+    if arg.is_empty() || arg.contains(|c: char| c.is_whitespace() || "\\\"'".contains(c)) {
+        format!("\"{}\"", escaped)
+    } else {
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_exec_args() {
+        assert_eq!(exec_arg("/usr/bin/hyprkool"), "/usr/bin/hyprkool");
+        assert_eq!(exec_arg("--config-dir=/a b/c"), "\"--config-dir=/a b/c\"");
+        assert_eq!(exec_arg("/x\"y"), "\"/x\\\"y\"");
+        assert_eq!(exec_arg("/x\\y"), "\"/x\\\\y\"");
+        assert_eq!(exec_arg("/100%/$HOME"), "/100%%/$$HOME");
+        assert_eq!(exec_arg(""), "\"\"");
+    }
+}
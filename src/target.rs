@@ -0,0 +1,327 @@
+use anyhow::{anyhow, Context, Result};
+
+use crate::{
+    rules::{new_activity_prefix, Rules},
+    state::{activity_name, cell_workspace_name, parse_cell},
+    State,
+};
+
+/// cell part of a workspace target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    /// (x, y) starting at 1
+    Xy(u32, u32),
+    /// row major index starting at 1
+    Index(u32),
+}
+
+/// something that points to a workspace. one of
+/// - activity:(x y)
+/// - activity:x,y
+/// - activity:n (nth workspace of the activity)
+/// - activity (last focused workspace of the activity)
+///
+/// the activity can also be its icon or display name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Target<'a> {
+    pub activity: &'a str,
+    pub cell: Option<Cell>,
+}
+
+impl<'a> Target<'a> {
+    /// flat_grid as in the config
+    pub fn parse(target: &'a str, flat_grid: bool) -> Result<Self> {
+        let Some((activity, cell)) = target.split_once(':') else {
+            // "(x y)" with flat_grid
+            if let Some((x, y)) = parse_cell(target, flat_grid) {
+                return Ok(Self {
+                    activity: activity_name(target, flat_grid),
+                    cell: Some(Cell::Xy(x, y)),
+                });
+            }
+            return Ok(Self {
+                activity: target,
+                cell: None,
+            });
+        };
+        let parsed = if let Some((x, y)) = parse_cell(target, flat_grid) {
+            Some(Cell::Xy(x, y))
+        } else if let Some((x, y)) = cell.split_once(',') {
+            x.trim()
+                .parse()
+                .ok()
+                .zip(y.trim().parse().ok())
+                .map(|(x, y)| Cell::Xy(x, y))
+        } else {
+            cell.trim().parse().ok().map(Cell::Index)
+        };
+        let cell = parsed.with_context(|| {
+            format!(
+                "invalid workspace target '{}'. use activity, activity:(x y), activity:x,y or activity:n",
+                target
+            )
+        })?;
+        Ok(Self {
+            activity,
+            cell: Some(cell),
+        })
+    }
+
+    /// row major index of the cell in a grid of size (nx, ny). None if there is no cell
+    pub fn cell_index(&self, (nx, ny): (u32, u32)) -> Result<Option<usize>> {
+        let (x, y) = match self.cell {
+            None => return Ok(None),
+            Some(Cell::Xy(x, y)) => (x, y),
+            Some(Cell::Index(i)) if i >= 1 && i <= nx * ny => ((i - 1) % nx + 1, (i - 1) / nx + 1),
+            Some(Cell::Index(i)) => {
+                return Err(anyhow!("workspace {} is not in the {}x{} grid", i, nx, ny));
+            }
+        };
+        if x < 1 || x > nx || y < 1 || y > ny {
+            return Err(anyhow!(
+                "cell ({} {}) is not in the {}x{} grid",
+                x,
+                y,
+                nx,
+                ny
+            ));
+        }
+        Ok(Some(((y - 1) * nx + (x - 1)) as usize))
+    }
+}
+
+/// target rewritten for a grid of size new when it was written for a grid of size old.
+/// cells outside the new grid move to the closest cell inside it. None if the target
+/// points to the same cell as before
+pub fn remap_target(
+    target: &str,
+    (ox, oy): (u32, u32),
+    (nx, ny): (u32, u32),
+    flat_grid: bool,
+) -> Option<String> {
+    if new_activity_prefix(target).is_some() {
+        return None;
+    }
+    let t = Target::parse(target, flat_grid).ok()?;
+    let (x, y) = match t.cell? {
+        Cell::Xy(x, y) => (x, y),
+        Cell::Index(i) if i >= 1 && i <= ox * oy => ((i - 1) % ox + 1, (i - 1) / ox + 1),
+        Cell::Index(_) => return None,
+    };
+    let (cx, cy) = (x.min(nx), y.min(ny));
+    let same = match t.cell? {
+        Cell::Xy(..) => (cx, cy) == (x, y),
+        // indices are row major, so they move whenever the width changes
+        Cell::Index(i) => (cy - 1) * nx + cx == i,
+    };
+    if same {
+        return None;
+    }
+    Some(cell_workspace_name(t.activity, cx, cy, flat_grid))
+}
+
+impl State {
+    /// full workspace name that a target points to
+    pub fn resolve_target(&self, target: &str) -> Result<String> {
+        let t = Target::parse(target, self.config.flat_grid)?;
+        if t.cell.is_none() && self.activity_by_alias(t.activity).is_none() {
+            if let Some(workspace) = self.named_focii.get(target) {
+                return Ok(workspace.clone());
+            }
+        }
+        let activity = self
+            .activity_by_alias(t.activity)
+            .with_context(|| format!("unknown activity '{}'", t.activity))?;
+        let activity_index = self
+            .get_activity_index(activity)
+            .with_context(|| format!("unknown activity '{}'", activity))?;
+        match t.cell_index(self.config.workspaces)? {
+            Some(i) => Ok(self.workspace_name(activity_index, i)),
+            None => Ok(self
+                .focused
+                .get(activity)
+                .cloned()
+                .unwrap_or_else(|| self.workspace_name(activity_index, 0))),
+        }
+    }
+
+    /// aliases that an activity would shadow can't be used as targets
+    pub fn check_alias(&self, alias: &str) -> Result<()> {
+        if alias.is_empty() || alias.contains(':') || self.activity_by_alias(alias).is_some() {
+            return Err(anyhow!("'{}' can't be used as an alias", alias));
+        }
+        Ok(())
+    }
+
+    /// rewrites configured targets to full workspace names. rule targets that are just an
+    /// activity are kept as is as they follow the activity's focus
+    pub fn canonicalize_targets(&mut self) -> Result<()> {
+        let named_focii = self
+            .named_focii
+            .iter()
+            .map(|(k, v)| Ok((k.clone(), self.resolve_target(v)?)))
+            .collect::<Result<_>>()?;
+        self.named_focii = named_focii;
+
+        let mut rules = self.config.rules.clone();
+        for r in rules.iter_mut() {
+            if new_activity_prefix(&r.workspace).is_some() {
+                continue;
+            }
+            if Target::parse(&r.workspace, self.config.flat_grid)?
+                .cell
+                .is_some()
+            {
+                r.workspace = self.resolve_target(&r.workspace)?;
+            }
+        }
+        self.rules = Rules::new(&rules)?;
+        self.config.rules = rules;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        config::{ActivityConfig, Config},
+        state::FLAT_ACTIVITY,
+    };
+
+    fn state() -> State {
+        let mut config = Config {
+            activities: vec!["work".into(), "play".into()],
+            workspaces: (3, 2),
+            ..Default::default()
+        };
+        config.activity.insert(
+            "play".into(),
+            ActivityConfig {
+                icon: Some("P".into()),
+                display: Some("Games".into()),
+                ..Default::default()
+            },
+        );
+        config.named_focii.insert("mail".into(), "work:3".into());
+        State::new(config).unwrap()
+    }
+
+    fn cell(target: &str) -> Result<Option<Cell>> {
+        Target::parse(target, false).map(|t| t.cell)
+    }
+
+    #[test]
+    fn parses_every_form() {
+        let t = Target::parse("work", false).unwrap();
+        assert_eq!((t.activity, t.cell), ("work", None));
+        let t = Target::parse("work:(2 1)", false).unwrap();
+        assert_eq!((t.activity, t.cell), ("work", Some(Cell::Xy(2, 1))));
+        assert_eq!(cell("work:2,1").unwrap(), Some(Cell::Xy(2, 1)));
+        assert_eq!(cell("work: 2, 1 ").unwrap(), Some(Cell::Xy(2, 1)));
+        assert_eq!(cell("work:4").unwrap(), Some(Cell::Index(4)));
+        // aliases are only looked up when resolving
+        assert_eq!(Target::parse("P:1", false).unwrap().activity, "P");
+        // flat grid cells are named without the activity
+        let t = Target::parse("(2 1)", true).unwrap();
+        assert_eq!((t.activity, t.cell), (FLAT_ACTIVITY, Some(Cell::Xy(2, 1))));
+        let t = Target::parse("(2 1)", false).unwrap();
+        assert_eq!((t.activity, t.cell), ("(2 1)", None));
+    }
+
+    #[test]
+    fn remaps_targets_into_a_smaller_grid() {
+        let remap = |t| remap_target(t, (3, 3), (2, 2), false);
+        assert_eq!(remap("work:(3 1)").as_deref(), Some("work:(2 1)"));
+        assert_eq!(remap("work:3,3").as_deref(), Some("work:(2 2)"));
+        assert_eq!(remap("work:(2 2)"), None);
+        assert_eq!(remap("work:2,2"), None);
+        // index 4 was (1 2) and is now index 3
+        assert_eq!(remap("work:4").as_deref(), Some("work:(1 2)"));
+        assert_eq!(remap("work:1"), None);
+        assert_eq!(remap("work"), None);
+        assert_eq!(remap("new-activity:9"), None);
+        assert_eq!(
+            remap_target("(3 3)", (3, 3), (2, 2), true).as_deref(),
+            Some("(2 2)")
+        );
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for target in [
+            "work:",
+            "work:x",
+            "work:(1)",
+            "work:(1 1",
+            "work:1,",
+            "work:1,y",
+            "work:-1",
+            "work:(1 1 1)",
+        ] {
+            assert!(cell(target).is_err(), "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn cell_indices() {
+        let grid = (3, 2);
+        let index = |cell| {
+            Target {
+                activity: "work",
+                cell: Some(cell),
+            }
+            .cell_index(grid)
+        };
+        assert_eq!(index(Cell::Xy(1, 1)).unwrap(), Some(0));
+        assert_eq!(index(Cell::Xy(3, 1)).unwrap(), Some(2));
+        assert_eq!(index(Cell::Xy(1, 2)).unwrap(), Some(3));
+        assert_eq!(index(Cell::Xy(3, 2)).unwrap(), Some(5));
+        assert_eq!(index(Cell::Index(1)).unwrap(), Some(0));
+        assert_eq!(index(Cell::Index(4)).unwrap(), Some(3));
+        assert_eq!(index(Cell::Index(6)).unwrap(), Some(5));
+        let none = Target {
+            activity: "work",
+            cell: None,
+        };
+        assert_eq!(none.cell_index(grid).unwrap(), None);
+
+        for out in [
+            Cell::Xy(0, 1),
+            Cell::Xy(1, 0),
+            Cell::Xy(4, 1),
+            Cell::Xy(1, 3),
+            Cell::Index(0),
+            Cell::Index(7),
+        ] {
+            assert!(index(out).is_err(), "{:?}", out);
+        }
+    }
+
+    #[test]
+    fn resolves_targets() {
+        let s = state();
+        let resolve = |t| s.resolve_target(t).unwrap();
+        // not focused yet, so the first cell
+        assert_eq!(resolve("work"), "work:(1 1)");
+        assert_eq!(resolve("play:(2 2)"), "play:(2 2)");
+        assert_eq!(resolve("play:2,2"), "play:(2 2)");
+        assert_eq!(resolve("play:5"), "play:(2 2)");
+        assert_eq!(resolve("P:4"), "play:(1 2)");
+        assert_eq!(resolve("Games"), "play:(1 1)");
+        assert_eq!(resolve("mail"), "work:(3 1)");
+
+        for target in ["nope", "nope:1", "work:(4 1)", "work:7", "play:0", "work:x"] {
+            assert!(s.resolve_target(target).is_err(), "{:?}", target);
+        }
+    }
+
+    #[test]
+    fn resolves_to_the_focused_workspace() {
+        let mut s = state();
+        s.focused.insert("play".into(), "play:(3 2)".into());
+        assert_eq!(s.resolve_target("play").unwrap(), "play:(3 2)");
+        assert_eq!(s.resolve_target("Games").unwrap(), "play:(3 2)");
+        assert_eq!(s.resolve_target("play:1").unwrap(), "play:(1 1)");
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    process::Stdio,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use tracing::{debug, error};
+
+use crate::config::WallpaperConfig;
+
+/// runs the wallpaper command when the focused activity has a different wallpaper
+#[derive(Debug, Default)]
+pub struct Wallpaper {
+    config: WallpaperConfig,
+    /// wallpaper that was set last
+    current: Option<String>,
+    /// bumped on every change so that only the last of quick switches sets its wallpaper
+    generation: Arc<AtomicU64>,
+}
+
+impl Wallpaper {
+    pub fn new(config: WallpaperConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// sets the wallpaper once no other activity was entered for the debounce time
+    pub fn enter(&mut self, wallpaper: Option<&str>) {
+        let Some(wallpaper) = wallpaper else {
+            return;
+        };
+        if self.config.command.is_empty() || self.current.as_deref() == Some(wallpaper) {
+            return;
+        }
+        self.current = Some(wallpaper.to_owned());
+
+        let generation = self.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let latest = self.generation.clone();
+        let debounce = Duration::from_millis(self.config.debounce_ms);
+        let command = self
+            .config
+            .command
+            .iter()
+            .map(|arg| arg.replace("{wallpaper}", wallpaper))
+            .collect::<Vec<_>>();
+        tokio::spawn(async move {
+            tokio::time::sleep(debounce).await;
+            if latest.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            debug!("setting wallpaper: {:?}", &command);
+            let res = tokio::process::Command::new(&command[0])
+                .args(&command[1..])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .status()
+                .await;
+            match res {
+                Ok(status) if !status.success() => {
+                    error!("wallpaper command failed with {}", status)
+                }
+                Ok(_) => {}
+                Err(e) => error!("could not run wallpaper command: {}", e),
+            }
+        });
+    }
+}